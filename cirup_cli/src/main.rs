@@ -1,11 +1,16 @@
 use std::error::Error;
+use std::io::{self, IsTerminal};
 use std::process::ExitCode;
+use std::time::Duration;
 
 use clap::{ArgAction, Parser, Subcommand, ValueEnum};
 use env_logger::{Builder, Env};
-use log::error;
+use log::{error, info};
 
-use cirup_core::{OutputEncoding, query};
+use cirup_core::{
+    OutputEncoding, ParseError, batch, changelog, codegen, coverage, diagnostic, edit, handoff, inconsistencies,
+    inline_diff, lint, merge, pseudo, query, rename, sort, stale, stats, sync, tm, usage, watch,
+};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
 enum CliOutputFormat {
@@ -24,6 +29,106 @@ impl From<CliOutputFormat> for query::QueryOutputFormat {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum CliDiagnosticFormat {
+    Msbuild,
+    Github,
+}
+
+impl From<CliDiagnosticFormat> for diagnostic::DiagnosticFormat {
+    fn from(value: CliDiagnosticFormat) -> Self {
+        match value {
+            CliDiagnosticFormat::Msbuild => diagnostic::DiagnosticFormat::Msbuild,
+            CliDiagnosticFormat::Github => diagnostic::DiagnosticFormat::Github,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum CliSortOrder {
+    Key,
+    CaseInsensitive,
+    Natural,
+    OrdinalIgnoreCase,
+}
+
+impl From<CliSortOrder> for sort::SortOrder {
+    fn from(value: CliSortOrder) -> Self {
+        match value {
+            CliSortOrder::Key => sort::SortOrder::Key,
+            CliSortOrder::CaseInsensitive => sort::SortOrder::CaseInsensitive,
+            CliSortOrder::Natural => sort::SortOrder::Natural,
+            CliSortOrder::OrdinalIgnoreCase => sort::SortOrder::OrdinalIgnoreCase,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum CliMergeStrategy {
+    PreferRight,
+    PreferLeft,
+    UpdateOnly,
+    AddOnly,
+}
+
+impl From<CliMergeStrategy> for merge::MergeStrategy {
+    fn from(value: CliMergeStrategy) -> Self {
+        match value {
+            CliMergeStrategy::PreferRight => merge::MergeStrategy::PreferRight,
+            CliMergeStrategy::PreferLeft => merge::MergeStrategy::PreferLeft,
+            CliMergeStrategy::UpdateOnly => merge::MergeStrategy::UpdateOnly,
+            CliMergeStrategy::AddOnly => merge::MergeStrategy::AddOnly,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum CliDiffGranularity {
+    Word,
+    Char,
+}
+
+impl From<CliDiffGranularity> for inline_diff::DiffGranularity {
+    fn from(value: CliDiffGranularity) -> Self {
+        match value {
+            CliDiffGranularity::Word => inline_diff::DiffGranularity::Word,
+            CliDiffGranularity::Char => inline_diff::DiffGranularity::Char,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum CliUsageFindingKind {
+    Unused,
+    Undefined,
+}
+
+impl From<CliUsageFindingKind> for usage::UsageFindingKind {
+    fn from(value: CliUsageFindingKind) -> Self {
+        match value {
+            CliUsageFindingKind::Unused => usage::UsageFindingKind::Unused,
+            CliUsageFindingKind::Undefined => usage::UsageFindingKind::Undefined,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum CliCodegenLanguage {
+    #[value(name = "csharp")]
+    CSharp,
+    #[value(name = "typescript")]
+    TypeScript,
+}
+
+impl From<CliCodegenLanguage> for codegen::CodegenLanguage {
+    fn from(value: CliCodegenLanguage) -> Self {
+        match value {
+            CliCodegenLanguage::CSharp => codegen::CodegenLanguage::CSharp,
+            CliCodegenLanguage::TypeScript => codegen::CodegenLanguage::TypeScript,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
 enum CliLogLevel {
     Error,
@@ -85,6 +190,15 @@ struct Cli {
     #[arg(short = 'C', long = "show-changes", global = true, action = ArgAction::SetTrue, help = "additionally print keys that have values in [file2] but that do not match the values in [file1]")]
     show_changes: bool,
 
+    #[arg(
+        long = "diff-granularity",
+        global = true,
+        value_enum,
+        default_value = "word",
+        help = "granularity of the inline diff of changed values printed with --show-changes"
+    )]
+    diff_granularity: CliDiffGranularity,
+
     #[arg(long = "touch", global = true, action = ArgAction::SetTrue, help = "force writing output files even when output content has not changed")]
     touch: bool,
 
@@ -106,6 +220,14 @@ struct Cli {
     )]
     output_format: CliOutputFormat,
 
+    #[arg(
+        long = "diagnostic-format",
+        global = true,
+        value_enum,
+        help = "print parse errors, missing keys and lint and coverage findings as diagnostics instead of rows: msbuild (file(line,col): error CODE: message), github (::error file=...)"
+    )]
+    diagnostic_format: Option<CliDiagnosticFormat>,
+
     #[arg(long = "dry-run", global = true, action = ArgAction::SetTrue, help = "compute results without writing output files")]
     dry_run: bool,
 
@@ -141,6 +263,17 @@ struct Cli {
     )]
     limit: Option<usize>,
 
+    #[arg(long = "watch", global = true, action = ArgAction::SetTrue, conflicts_with = "check", help = "re-run the command whenever its input files change")]
+    watch: bool,
+
+    #[arg(
+        long = "debounce",
+        global = true,
+        default_value_t = watch::DEFAULT_DEBOUNCE_MS,
+        help = "milliseconds to wait for input file changes to settle before re-running in --watch mode"
+    )]
+    debounce: u64,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -152,15 +285,32 @@ enum Commands {
 
     #[command(
         name = "file-convert",
-        about = "convert [file] to another type. possible extensions are .json, .resx and .restext"
+        about = "convert [file] to another type. possible extensions are .json, .resx, .restext and .resources"
     )]
     FileConvert { file: String, output: String },
 
     #[command(
         name = "file-sort",
-        about = "sort [file] by key name. possible extensions are .json, .resx and .restext"
+        about = "sort [file] by key name, or by the key order of another file. possible extensions are .json, .resx, .restext and .resources"
     )]
-    FileSort { file: String, output: Option<String> },
+    FileSort {
+        file: String,
+        output: Option<String>,
+        #[arg(
+            long = "order",
+            value_enum,
+            default_value = "key",
+            help = "key order: binary (key), case-insensitive, natural with numbers in order, or .NET ordinal-ignore-case as in Visual Studio"
+        )]
+        order: CliSortOrder,
+        #[arg(
+            long = "align",
+            value_name = "BASE",
+            conflicts_with = "order",
+            help = "order the keys as in [BASE] instead, with the keys that are not in it at the end"
+        )]
+        align: Option<String>,
+    },
 
     #[command(
         name = "file-diff",
@@ -177,6 +327,17 @@ enum Commands {
         file1: String,
         file2: String,
         output: Option<String>,
+        #[arg(
+            long = "strategy",
+            value_enum,
+            default_value = "prefer-right",
+            help = "prefer-right adds new keys and takes the values of [file2], prefer-left keeps the non-empty values of [file1], update-only never adds keys and add-only never changes values"
+        )]
+        strategy: CliMergeStrategy,
+        #[arg(long = "skip-empty", help = "ignore the empty values of [file2]")]
+        skip_empty: bool,
+        #[arg(long = "base", help = "drop the keys that are not in this file from the merged file")]
+        base: Option<String>,
     },
 
     #[command(
@@ -199,11 +360,423 @@ enum Commands {
         output: Option<String>,
     },
 
+    #[command(
+        name = "file-pull",
+        about = "bring [target] into the key set and order of [base], adding missing keys and dropping obsolete ones. [target] is updated unless [output] is given"
+    )]
+    FilePull {
+        base: String,
+        target: String,
+        output: Option<String>,
+        #[arg(
+            long = "placeholder",
+            default_value = sync::DEFAULT_PLACEHOLDER,
+            help = "value of the added keys, where {key} and {value} stand for the key and its base value; empty by default"
+        )]
+        placeholder: String,
+    },
+
+    #[command(
+        name = "file-push",
+        about = "apply the values of [working] that differ from [master] back into [master], or into [output]"
+    )]
+    FilePush {
+        working: String,
+        master: String,
+        output: Option<String>,
+    },
+
+    #[command(
+        name = "rename-key",
+        about = "rename [from] to [to] in [file] and its culture files, e.g. Strings.resx and Strings.fr.resx. a trailing * renames a prefix"
+    )]
+    RenameKey {
+        file: String,
+        #[arg(required_unless_present = "mapping")]
+        from: Option<String>,
+        #[arg(required_unless_present = "mapping")]
+        to: Option<String>,
+        #[arg(
+            long = "mapping",
+            conflicts_with_all = ["from", "to"],
+            help = "JSON object file mapping old key names to new key names, instead of [from] and [to]"
+        )]
+        mapping: Option<String>,
+    },
+
+    #[command(
+        name = "set-key",
+        about = "set [key] to a value in one or more files, given as FILE=VALUE, adding it where it is missing"
+    )]
+    SetKey {
+        #[arg(required_unless_present = "edits")]
+        key: Option<String>,
+        #[arg(value_name = "FILE=VALUE", required_unless_present = "edits")]
+        values: Vec<String>,
+        #[arg(
+            long = "edits",
+            conflicts_with_all = ["key", "values"],
+            help = "JSONL file of edits with file, key and value members, or - for stdin, instead of [key] and [values]"
+        )]
+        edits: Option<String>,
+    },
+
+    #[command(
+        name = "add-key",
+        about = "add [key] with a value to one or more files, given as FILE=VALUE. fails if a file already has the key"
+    )]
+    AddKey {
+        #[arg(required_unless_present = "edits")]
+        key: Option<String>,
+        #[arg(value_name = "FILE=VALUE", required_unless_present = "edits")]
+        values: Vec<String>,
+        #[arg(
+            long = "edits",
+            conflicts_with_all = ["key", "values"],
+            help = "JSONL file of edits with file, key and value members, or - for stdin, instead of [key] and [values]"
+        )]
+        edits: Option<String>,
+    },
+
+    #[command(
+        name = "delete-key",
+        about = "delete [key] from one or more [files]. fails if a file does not have the key"
+    )]
+    DeleteKey {
+        #[arg(required_unless_present = "edits")]
+        key: Option<String>,
+        #[arg(required_unless_present = "edits")]
+        files: Vec<String>,
+        #[arg(
+            long = "edits",
+            conflicts_with_all = ["key", "files"],
+            help = "JSONL file of edits with file and key members, or - for stdin, instead of [key] and [files]"
+        )]
+        edits: Option<String>,
+    },
+
+    #[command(
+        name = "changelog",
+        about = "list the keys added, removed, modified or renamed from [old] to [new], and write them as Markdown to [output]"
+    )]
+    Changelog {
+        old: String,
+        new: String,
+        output: Option<String>,
+    },
+
     #[command(
         name = "diff-with-base",
         about = "output keys that have values in [new] but not in [old] with the value in [base]"
     )]
     DiffWithBase { old: String, new: String, base: String },
+
+    #[command(
+        name = "export",
+        about = "write the keys of [target] that need translation to an .xlf, .xliff or .csv hand-off [output] with the source text from [base]"
+    )]
+    Export {
+        base: String,
+        target: String,
+        output: String,
+        #[arg(
+            long = "since",
+            help = "previous version of [base]; keys whose source value changed since then are exported as well"
+        )]
+        since: Option<String>,
+        #[arg(
+            long = "source-language",
+            default_value = "en",
+            help = "source language written to XLIFF files"
+        )]
+        source_language: String,
+        #[arg(
+            long = "target-language",
+            help = "target language written to XLIFF files; inferred from [target] by default"
+        )]
+        target_language: Option<String>,
+    },
+
+    #[command(
+        name = "import",
+        about = "validate a translated hand-off [file] against [base] and merge it into [target]"
+    )]
+    Import {
+        base: String,
+        target: String,
+        file: String,
+        output: Option<String>,
+        #[arg(
+            long = "allow-stale",
+            action = ArgAction::SetTrue,
+            help = "import translations even when their source text no longer matches [base]"
+        )]
+        allow_stale: bool,
+    },
+
+    #[command(
+        name = "stale",
+        about = "output translations in [target] whose source value in [base] changed since they were fingerprinted"
+    )]
+    Stale {
+        base: String,
+        target: String,
+        #[arg(
+            long = "lock",
+            help = "fingerprint lock file; defaults to [target] with a .cirup.lock suffix"
+        )]
+        lock: Option<String>,
+        #[arg(
+            long = "update",
+            action = ArgAction::SetTrue,
+            help = "record the current source fingerprints of the translated keys instead of listing stale ones"
+        )]
+        update: bool,
+    },
+
+    #[command(
+        name = "tm-add",
+        about = "add the translations of each of [targets] to the translation memory [tm], with the source text from [base]"
+    )]
+    TmAdd {
+        tm: String,
+        base: String,
+        #[arg(required = true)]
+        targets: Vec<String>,
+        #[arg(
+            long = "language",
+            help = "language of the translations; inferred from each target file name by default"
+        )]
+        language: Option<String>,
+    },
+
+    #[command(
+        name = "suggest",
+        about = "suggest translations from the translation memory [tm] for the keys of [base] missing from [target]; [output] is written with an [output].review list of the keys that need review"
+    )]
+    Suggest {
+        tm: String,
+        base: String,
+        target: String,
+        output: Option<String>,
+        #[arg(
+            long = "language",
+            help = "language of [target]; inferred from the file name by default"
+        )]
+        language: Option<String>,
+        #[arg(
+            long = "min-score",
+            default_value_t = tm::DEFAULT_MIN_SCORE,
+            value_parser = clap::value_parser!(u8).range(1..=100),
+            help = "minimum similarity, in percent, of fuzzy matches"
+        )]
+        min_score: u8,
+    },
+
+    #[command(
+        name = "pseudo",
+        about = "write a pseudo-localized copy of [file] to [output]. possible extensions are .json, .resx, .restext and .resources"
+    )]
+    Pseudo {
+        file: String,
+        output: String,
+        #[arg(
+            long = "expansion",
+            default_value_t = pseudo::DEFAULT_EXPANSION,
+            help = "length expansion of the translatable text, in percent"
+        )]
+        expansion: u16,
+        #[arg(
+            long = "no-brackets",
+            action = ArgAction::SetTrue,
+            help = "do not wrap values in [ ] markers"
+        )]
+        no_brackets: bool,
+    },
+
+    #[command(
+        name = "usage",
+        about = "report keys of [files] that are never referenced in the sources, and references to keys that do not exist"
+    )]
+    Usage {
+        #[arg(required = true)]
+        files: Vec<String>,
+        #[arg(
+            long = "source",
+            action = ArgAction::Append,
+            help = "repeatable source directory or file to scan for .cs, .xaml, .ts, .tsx, .js, .jsx, .cshtml and .razor references, skipping generated *.Designer.cs files in directories; defaults to the current directory"
+        )]
+        source: Vec<String>,
+        #[arg(
+            long = "extractor",
+            action = ArgAction::Append,
+            help = "repeatable regex extracting a key reference from a source line, with a 'key' named group or a single capture group"
+        )]
+        extractor: Vec<String>,
+        #[arg(
+            long = "no-builtin-extractors",
+            action = ArgAction::SetTrue,
+            help = "only use the --extractor patterns"
+        )]
+        no_builtin_extractors: bool,
+        #[arg(long = "kind", value_enum, help = "only report unused or undefined keys")]
+        kind: Option<CliUsageFindingKind>,
+    },
+
+    #[command(
+        name = "lint",
+        about = "check [targets] against [base] for placeholder mismatches, duplicate, unknown and empty keys, whitespace differences and glossary terms"
+    )]
+    Lint {
+        base: String,
+        targets: Vec<String>,
+        #[arg(
+            long = "glossary",
+            help = "glossary (.csv or .tbx) of mandated translations and do-not-translate terms, checked in the language of the culture suffix of each of [targets]"
+        )]
+        glossary: Option<String>,
+    },
+
+    #[command(
+        name = "coverage",
+        about = "report the share of the keys of [base] translated in each of [targets]"
+    )]
+    Coverage {
+        base: String,
+        #[arg(required = true)]
+        targets: Vec<String>,
+        #[arg(
+            long = "min",
+            help = "minimum coverage percentage; files below it are reported as errors, and --check exits with code 2"
+        )]
+        min: Option<f64>,
+    },
+
+    #[command(
+        name = "stats",
+        about = "count the source words, characters and repeated segments of the keys of [base] to translate in each of [targets], by file and by key prefix"
+    )]
+    Stats {
+        base: String,
+        #[arg(required = true)]
+        targets: Vec<String>,
+        #[arg(
+            long = "since",
+            help = "previous version of [base]; keys whose source value changed since then are counted as well"
+        )]
+        since: Option<String>,
+    },
+
+    #[command(
+        name = "inconsistencies",
+        about = "report source strings translated differently, and translations shared by different source strings, across [pairs]"
+    )]
+    Inconsistencies {
+        #[arg(
+            required = true,
+            help = "BASE=TARGET pairs, or culture files such as UIResources.fr.resx, whose base is the neutral file UIResources.resx"
+        )]
+        pairs: Vec<String>,
+    },
+
+    #[command(
+        name = "codegen",
+        about = "generate strongly-typed accessors for the keys of [file] as a C# class (.cs) or a TypeScript key map (.ts)"
+    )]
+    Codegen {
+        file: String,
+        output: String,
+        #[arg(
+            long = "language",
+            value_enum,
+            help = "generated language; inferred from the [output] extension by default"
+        )]
+        language: Option<CliCodegenLanguage>,
+        #[arg(
+            long = "class-name",
+            help = "generated class or constant name; defaults to the [file] name up to the first dot"
+        )]
+        class_name: Option<String>,
+        #[arg(long = "namespace", help = "C# namespace of the generated class")]
+        namespace: Option<String>,
+        #[arg(
+            long = "resource-name",
+            help = "C# ResourceManager base name; defaults to <namespace>.<class-name>"
+        )]
+        resource_name: Option<String>,
+        #[arg(
+            long = "public",
+            action = ArgAction::SetTrue,
+            help = "generate a public instead of an internal C# class"
+        )]
+        public: bool,
+    },
+
+    #[command(
+        name = "run",
+        about = "run the sort, diff, merge and convert operations listed in a JSON or TOML [manifest] in one process"
+    )]
+    Run {
+        manifest: String,
+        #[arg(
+            long = "jobs",
+            short = 'j',
+            help = "number of operations run in parallel, the number of CPUs by default"
+        )]
+        jobs: Option<usize>,
+    },
+}
+
+impl Commands {
+    /// The input files declared by the command, watched with `--watch`.
+    fn input_files(&self) -> Vec<&str> {
+        let files: Vec<&String> = match self {
+            Commands::FilePrint { file, .. }
+            | Commands::FileConvert { file, .. }
+            | Commands::Pseudo { file, .. }
+            | Commands::Codegen { file, .. }
+            | Commands::RenameKey { file, .. } => vec![file],
+            Commands::FileDiff { file1, file2, .. }
+            | Commands::FileIntersect { file1, file2, .. }
+            | Commands::FileSubtract { file1, file2, .. } => vec![file1, file2],
+            Commands::FileSort { file, align, .. } => std::iter::once(file).chain(align).collect(),
+            Commands::FileMerge { file1, file2, base, .. } => [file1, file2].into_iter().chain(base).collect(),
+            Commands::FilePull { base, target, .. } => vec![base, target],
+            Commands::FilePush { working, master, .. } => vec![working, master],
+            Commands::DiffWithBase { old, new, base } => vec![old, new, base],
+            Commands::Changelog { old, new, .. } => vec![old, new],
+            Commands::Export {
+                base, target, since, ..
+            } => [base, target].into_iter().chain(since).collect(),
+            Commands::Import { base, target, .. }
+            | Commands::Stale { base, target, .. }
+            | Commands::Suggest { base, target, .. } => vec![base, target],
+            Commands::TmAdd { base, targets, .. }
+            | Commands::Lint { base, targets, .. }
+            | Commands::Coverage { base, targets, .. } => std::iter::once(base).chain(targets).collect(),
+            Commands::Stats { base, targets, since } => std::iter::once(base).chain(targets).chain(since).collect(),
+            Commands::Inconsistencies { pairs } => {
+                return pairs
+                    .iter()
+                    .flat_map(|pair| match pair.split_once('=') {
+                        Some((base, target)) => vec![base, target],
+                        None => vec![pair.as_str()],
+                    })
+                    .collect();
+            }
+            Commands::Usage { files, .. } | Commands::DeleteKey { files, .. } => files.iter().collect(),
+            Commands::SetKey { values, .. } | Commands::AddKey { values, .. } => {
+                return values
+                    .iter()
+                    .filter_map(|value| value.split_once('='))
+                    .map(|(file, _)| file)
+                    .collect();
+            }
+            Commands::Run { manifest, .. } => vec![manifest],
+        };
+
+        files.into_iter().map(String::as_str).collect()
+    }
 }
 
 fn query_options(cli: &Cli) -> query::QueryRunOptions {
@@ -219,20 +792,123 @@ fn query_options(cli: &Cli) -> query::QueryRunOptions {
         operation_name: None,
         input_files: Vec::new(),
         output_file: None,
+        diagnostic_format: cli.diagnostic_format.map(Into::into),
+        color: io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        diff_granularity: cli.diff_granularity.into(),
     }
 }
 
-fn run(cli: &Cli) -> Result<query::QueryExecutionReport, Box<dyn Error>> {
+/// Returns the edits of a key edit command: `args` are `FILE=VALUE` assignments of `key`,
+/// or the files to delete it from, unless the edits are read from an `edits` JSONL stream.
+fn key_edits(
+    operation: edit::KeyEditOperation,
+    key: Option<&str>,
+    args: &[String],
+    edits: Option<&str>,
+) -> Result<Vec<edit::KeyEdit>, Box<dyn Error>> {
+    if let Some(edits) = edits {
+        return Ok(edit::load_key_edits(operation, edits)?);
+    }
+
+    let key = key.ok_or("expected [key] or --edits")?;
+    args.iter()
+        .map(|arg| {
+            let (file, value) = match operation {
+                edit::KeyEditOperation::Delete => (arg.as_str(), ""),
+                _ => arg
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected FILE=VALUE, got '{}'", arg))?,
+            };
+
+            Ok(edit::KeyEdit {
+                operation,
+                file: file.to_owned(),
+                key: key.to_owned(),
+                value: value.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Returns the base and translation files of `BASE=TARGET` pairs and culture files, whose base is their neutral file.
+fn translation_pairs(args: &[String]) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    args.iter()
+        .map(|arg| match arg.split_once('=') {
+            Some((base, target)) => Ok((base.to_owned(), target.to_owned())),
+            None => {
+                let base = inconsistencies::neutral_filename(arg)
+                    .ok_or_else(|| format!("cannot infer the base file of '{}', pass BASE={}", arg, arg))?;
+                Ok((base, arg.clone()))
+            }
+        })
+        .collect()
+}
+
+/// Writes the pending output files of `result` and prints it.
+#[allow(clippy::print_stdout)]
+fn emit<T>(result: query::QueryResult<T>) -> Result<query::QueryExecutionReport, Box<dyn Error>> {
+    result.write_pending()?;
+    print!("{}", result.rendered);
+    Ok(result.report)
+}
+
+#[allow(clippy::print_stdout)]
+fn print_diagnostics(diagnostics: &[diagnostic::Diagnostic], format: diagnostic::DiagnosticFormat) {
+    for diagnostic in diagnostics {
+        println!("{}", diagnostic.render(format));
+    }
+}
+
+/// Returns the query of the previous run with its input files reloaded, or loads them on the first run.
+fn cached_query<'a>(
+    cache: &'a mut Option<query::CirupQuery>,
+    operation: query::QueryOperation,
+    files: &[&str],
+) -> Result<&'a query::CirupQuery, io::Error> {
+    let query = match cache.take() {
+        Some(mut query) => {
+            query.try_reload()?;
+            query
+        }
+        None => query::CirupQuery::from_files(operation, files)?,
+    };
+
+    Ok(cache.insert(query))
+}
+
+/// Returns the parse error a command failed on, to print it as a diagnostic.
+fn parse_error<'a>(error: &'a (dyn Error + 'static)) -> Option<&'a ParseError> {
+    error
+        .downcast_ref::<io::Error>()
+        .and_then(|error| error.get_ref())
+        .and_then(|error| error.downcast_ref::<ParseError>())
+        .or_else(|| error.downcast_ref::<ParseError>())
+}
+
+fn run(cli: &Cli, cache: &mut Option<query::CirupQuery>) -> Result<query::QueryExecutionReport, Box<dyn Error>> {
+    let result = run_command(cli, cache);
+
+    if let (Err(error), Some(format)) = (&result, cli.diagnostic_format)
+        && let Some(parse_error) = parse_error(error.as_ref())
+    {
+        print_diagnostics(&[parse_error.diagnostic()], format.into());
+    }
+
+    result
+}
+
+fn run_command(
+    cli: &Cli,
+    cache: &mut Option<query::CirupQuery>,
+) -> Result<query::QueryExecutionReport, Box<dyn Error>> {
     let output_encoding: OutputEncoding = cli.output_encoding.into();
     let options = query_options(cli);
 
     match &cli.command {
         Commands::FilePrint { file, output } => {
             let options = options.with_context("file-print", &[file], output.as_deref());
-            let query = query::query_print(file);
-            query
-                .run_interactive_with_options(output.as_deref(), cli.touch, output_encoding, &options)
-                .map_err(Into::into)
+            let query = cached_query(cache, query::QueryOperation::Print, &[file])?;
+            emit(query.evaluate_with_options(output.as_deref(), cli.touch, output_encoding, &options)?)
         }
         Commands::FileDiff { file1, file2, output } => {
             let operation_name = if cli.show_changes {
@@ -242,64 +918,356 @@ fn run(cli: &Cli) -> Result<query::QueryExecutionReport, Box<dyn Error>> {
             };
             let options = options.with_context(operation_name, &[file1, file2], output.as_deref());
             if cli.show_changes {
-                let query = query::query_change(file1, file2);
-                query
-                    .run_interactive_with_options(output.as_deref(), cli.touch, output_encoding, &options)
-                    .map_err(Into::into)
+                let query = cached_query(cache, query::QueryOperation::Change, &[file1, file2])?;
+                emit(query.evaluate_changes_with_options(output.as_deref(), cli.touch, output_encoding, &options)?)
             } else {
-                let query = query::query_diff(file1, file2);
-                query
-                    .run_interactive_with_options(output.as_deref(), cli.touch, output_encoding, &options)
-                    .map_err(Into::into)
+                let query = cached_query(cache, query::QueryOperation::Diff, &[file1, file2])?;
+                emit(query.evaluate_with_options(output.as_deref(), cli.touch, output_encoding, &options)?)
             }
         }
-        Commands::FileMerge { file1, file2, output } => {
-            let options = options.with_context("file-merge", &[file1, file2], output.as_deref());
-            let query = query::query_merge(file1, file2);
-            query
-                .run_interactive_with_options(output.as_deref(), cli.touch, output_encoding, &options)
-                .map_err(Into::into)
+        Commands::FileMerge {
+            file1,
+            file2,
+            output,
+            strategy,
+            skip_empty,
+            base,
+        } => {
+            let inputs = [file1, file2]
+                .into_iter()
+                .chain(base)
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            let options = options.with_context("file-merge", &inputs, output.as_deref());
+            let file_merge = merge::merge_files(file1, file2)
+                .with_strategy((*strategy).into())
+                .with_skip_empty(*skip_empty)
+                .with_base(base.as_deref());
+            emit(file_merge.evaluate_with_options(output.as_deref(), cli.touch, output_encoding, &options)?)
         }
         Commands::FileIntersect { file1, file2, output } => {
             let options = options.with_context("file-intersect", &[file1, file2], output.as_deref());
-            let query = query::query_intersect(file1, file2);
-            query
-                .run_interactive_with_options(output.as_deref(), cli.touch, output_encoding, &options)
-                .map_err(Into::into)
+            let query = cached_query(cache, query::QueryOperation::Intersect, &[file1, file2])?;
+            emit(query.evaluate_with_options(output.as_deref(), cli.touch, output_encoding, &options)?)
         }
         Commands::FileSubtract { file1, file2, output } => {
             let options = options.with_context("file-subtract", &[file1, file2], output.as_deref());
-            let query = query::query_subtract(file1, file2);
-            query
-                .run_interactive_with_options(output.as_deref(), cli.touch, output_encoding, &options)
-                .map_err(Into::into)
+            let query = cached_query(cache, query::QueryOperation::Subtract, &[file1, file2])?;
+            emit(query.evaluate_with_options(output.as_deref(), cli.touch, output_encoding, &options)?)
         }
         Commands::FileConvert { file, output } => {
             let options = options.with_context("file-convert", &[file], Some(output));
-            let query = query::query_convert(file);
-            query
-                .run_interactive_with_options(Some(output), cli.touch, output_encoding, &options)
-                .map_err(Into::into)
+            let query = cached_query(cache, query::QueryOperation::Convert, &[file])?;
+            emit(query.evaluate_with_options(Some(output), cli.touch, output_encoding, &options)?)
         }
-        Commands::FileSort { file, output } => {
+        Commands::FileSort {
+            file,
+            output,
+            order,
+            align,
+        } => {
             let target = output.as_deref().or(Some(file.as_str()));
-            let options = options.with_context("file-sort", &[file], target);
-            let query = query::query_sort(file);
+            let inputs = std::iter::once(file)
+                .chain(align)
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            let options = options.with_context("file-sort", &inputs, target);
+            let file_sort = match (align, sort::SortOrder::from(*order)) {
+                (Some(base), _) => Some(sort::align_file(file, base)),
+                (None, sort::SortOrder::Key) => None,
+                (None, order) => Some(sort::sort_file(file, order)),
+            };
+            if let Some(file_sort) = file_sort {
+                return emit(file_sort.evaluate_with_options(target, cli.touch, output_encoding, &options)?);
+            }
+
+            let query = cached_query(cache, query::QueryOperation::Sort, &[file])?;
 
             if output.is_some() {
-                query
-                    .run_interactive_with_options(output.as_deref(), cli.touch, output_encoding, &options)
-                    .map_err(Into::into)
+                emit(query.evaluate_with_options(output.as_deref(), cli.touch, output_encoding, &options)?)
             } else {
-                query
-                    .run_interactive_with_options(Some(file), cli.touch, output_encoding, &options)
-                    .map_err(Into::into)
+                emit(query.evaluate_with_options(Some(file), cli.touch, output_encoding, &options)?)
             }
         }
+        Commands::FilePull {
+            base,
+            target,
+            output,
+            placeholder,
+        } => {
+            let out_file = output.as_deref().unwrap_or(target.as_str());
+            let options = options.with_context("file-pull", &[base, target], Some(out_file));
+            let pull = sync::sync_pull(base, target).with_placeholder(placeholder);
+            emit(pull.evaluate_with_options(Some(out_file), cli.touch, output_encoding, &options)?)
+        }
+        Commands::FilePush {
+            working,
+            master,
+            output,
+        } => {
+            let out_file = output.as_deref().unwrap_or(master.as_str());
+            let options = options.with_context("file-push", &[working, master], Some(out_file));
+            let push = sync::sync_push(working, master);
+            emit(push.evaluate_with_options(Some(out_file), cli.touch, output_encoding, &options)?)
+        }
+        Commands::RenameKey {
+            file,
+            from,
+            to,
+            mapping,
+        } => {
+            let renames = match (mapping, from, to) {
+                (Some(mapping), ..) => rename::load_key_renames(mapping)?,
+                (None, Some(from), Some(to)) => vec![rename::KeyRename::new(from, to)?],
+                _ => return Err("rename-key expects [from] and [to] or --mapping".into()),
+            };
+            let renaming = rename::rename_keys(file, renames);
+            let files = renaming.files().iter().map(String::as_str).collect::<Vec<_>>();
+            let options = options.with_context("rename-key", &files, None);
+            emit(renaming.evaluate_with_options(cli.touch, output_encoding, &options)?)
+        }
+        Commands::SetKey { key, values, edits } => {
+            let edits = key_edits(edit::KeyEditOperation::Set, key.as_deref(), values, edits.as_deref())?;
+            let editing = edit::edit_keys(edits);
+            let options = options.with_context("set-key", &editing.files(), None);
+            emit(editing.evaluate_with_options(cli.touch, output_encoding, &options)?)
+        }
+        Commands::AddKey { key, values, edits } => {
+            let edits = key_edits(edit::KeyEditOperation::Add, key.as_deref(), values, edits.as_deref())?;
+            let editing = edit::edit_keys(edits);
+            let options = options.with_context("add-key", &editing.files(), None);
+            emit(editing.evaluate_with_options(cli.touch, output_encoding, &options)?)
+        }
+        Commands::DeleteKey { key, files, edits } => {
+            let edits = key_edits(edit::KeyEditOperation::Delete, key.as_deref(), files, edits.as_deref())?;
+            let editing = edit::edit_keys(edits);
+            let options = options.with_context("delete-key", &editing.files(), None);
+            emit(editing.evaluate_with_options(cli.touch, output_encoding, &options)?)
+        }
+        Commands::Changelog { old, new, output } => {
+            let options = options.with_context("changelog", &[old, new], output.as_deref());
+            let changelog = changelog::changelog(old, new);
+            emit(changelog.evaluate_with_options(output.as_deref(), cli.touch, &options)?)
+        }
         Commands::DiffWithBase { old, new, base } => {
             let options = options.with_context("diff-with-base", &[old, new, base], None);
-            let query = query::query_diff_with_base(old, new, base);
-            query.run_triple_interactive_with_options(&options).map_err(Into::into)
+            let query = cached_query(cache, query::QueryOperation::DiffWithBase, &[old, new, base])?;
+            emit(query.evaluate_triple_with_options(&options)?)
+        }
+        Commands::Export {
+            base,
+            target,
+            output,
+            since,
+            source_language,
+            target_language,
+        } => {
+            let mut input_files = vec![base.as_str(), target.as_str()];
+            input_files.extend(since.as_deref());
+            let options = options.with_context("export", &input_files, Some(output));
+            let export = handoff::handoff_export(base, target, since.as_deref())
+                .with_languages(source_language, target_language.as_deref());
+            emit(export.evaluate_with_options(output, cli.touch, &options)?)
+        }
+        Commands::Import {
+            base,
+            target,
+            file,
+            output,
+            allow_stale,
+        } => {
+            let out_file = output.as_deref().unwrap_or(target.as_str());
+            let options = options.with_context("import", &[base, target, file], Some(out_file));
+            let import = handoff::handoff_import(base, target, file).allow_stale(*allow_stale);
+            emit(import.evaluate_with_options(Some(out_file), cli.touch, output_encoding, &options)?)
+        }
+        Commands::Stale {
+            base,
+            target,
+            lock,
+            update,
+        } => {
+            let query = stale::query_stale(base, target, lock.as_deref());
+            if *update {
+                let lock_file = lock.clone().unwrap_or_else(|| stale::default_lock_path(target));
+                let options = options.with_context("stale-update", &[base, target], Some(&lock_file));
+                emit(query.stamp_with_options(cli.touch, &options)?)
+            } else {
+                let options = options.with_context("stale", &[base, target], None);
+                emit(query.evaluate_with_options(&options)?)
+            }
+        }
+        Commands::TmAdd {
+            tm,
+            base,
+            targets,
+            language,
+        } => {
+            let mut input_files = vec![base.as_str()];
+            input_files.extend(targets.iter().map(String::as_str));
+            let options = options.with_context("tm-add", &input_files, None);
+            let add = tm::tm_add(tm, base, &input_files[1..]).with_language(language.as_deref());
+            emit(add.add_with_options(&options)?)
+        }
+        Commands::Suggest {
+            tm,
+            base,
+            target,
+            output,
+            language,
+            min_score,
+        } => {
+            let options = options.with_context("suggest", &[tm, base, target], output.as_deref());
+            let suggest = tm::tm_suggest(tm, base, target)
+                .with_language(language.as_deref())
+                .min_score(*min_score);
+            emit(suggest.evaluate_with_options(output.as_deref(), cli.touch, output_encoding, &options)?)
+        }
+        Commands::Usage {
+            files,
+            source,
+            extractor,
+            no_builtin_extractors,
+            kind,
+        } => {
+            let files = files.iter().map(String::as_str).collect::<Vec<_>>();
+            let sources = if source.is_empty() {
+                vec!["."]
+            } else {
+                source.iter().map(String::as_str).collect()
+            };
+            let options = options.with_context("usage", &files, None);
+            let query = usage::query_usage(&files, &sources)
+                .with_extractors(extractor)
+                .builtin_extractors(!*no_builtin_extractors);
+            emit(query.evaluate_with_options(kind.map(Into::into), &options)?)
+        }
+        Commands::Lint {
+            base,
+            targets,
+            glossary,
+        } => {
+            let mut input_files = vec![base.as_str()];
+            input_files.extend(targets.iter().map(String::as_str));
+            let options = options.with_context("lint", &input_files, None);
+            let query = lint::query_lint(base, &input_files[1..]).with_glossary(glossary.as_deref());
+            emit(query.evaluate_with_options(&options)?)
+        }
+        Commands::Coverage { base, targets, min } => {
+            let mut input_files = vec![base.as_str()];
+            input_files.extend(targets.iter().map(String::as_str));
+            let options = options.with_context("coverage", &input_files, None);
+            let query = coverage::query_coverage(base, &input_files[1..]).min_coverage(*min);
+            emit(query.evaluate_with_options(&options)?)
+        }
+        Commands::Stats { base, targets, since } => {
+            let targets = targets.iter().map(String::as_str).collect::<Vec<_>>();
+            let mut input_files = vec![base.as_str()];
+            input_files.extend(&targets);
+            input_files.extend(since.as_deref());
+            let options = options.with_context("stats", &input_files, None);
+            let stats = stats::translation_stats(base, &targets, since.as_deref());
+            emit(stats.evaluate_with_options(&options)?)
+        }
+        Commands::Inconsistencies { pairs } => {
+            let pairs = translation_pairs(pairs)?;
+            let pairs = pairs
+                .iter()
+                .map(|(base, target)| (base.as_str(), target.as_str()))
+                .collect::<Vec<_>>();
+            let input_files = pairs
+                .iter()
+                .flat_map(|(base, target)| [*base, *target])
+                .collect::<Vec<_>>();
+            let options = options.with_context("inconsistencies", &input_files, None);
+            emit(inconsistencies::check_inconsistencies(&pairs).evaluate_with_options(&options)?)
+        }
+        Commands::Codegen {
+            file,
+            output,
+            language,
+            class_name,
+            namespace,
+            resource_name,
+            public,
+        } => {
+            let options = options.with_context("codegen", &[file], Some(output));
+            let language = language
+                .map(Into::into)
+                .or_else(|| codegen::CodegenLanguage::from_filename(output))
+                .ok_or_else(|| format!("cannot infer the generated language of '{output}', pass --language"))?;
+            let query = codegen::query_codegen(
+                file,
+                language,
+                codegen::CodegenOptions {
+                    class_name: class_name.clone(),
+                    namespace: namespace.clone(),
+                    resource_name: resource_name.clone(),
+                    public: *public,
+                },
+            );
+            emit(query.evaluate_with_options(output, cli.touch, output_encoding, &options)?)
+        }
+        Commands::Pseudo {
+            file,
+            output,
+            expansion,
+            no_brackets,
+        } => {
+            let options = options.with_context("pseudo", &[file], Some(output));
+            let query = pseudo::query_pseudo(
+                file,
+                pseudo::PseudoOptions {
+                    expansion: *expansion,
+                    brackets: !*no_brackets,
+                },
+            );
+            emit(query.evaluate_with_options(output, cli.touch, output_encoding, &options)?)
+        }
+        Commands::Run { manifest, jobs } => {
+            let options = options.with_context("run", &[manifest], None);
+            let batch = batch::query_batch(manifest).jobs(*jobs);
+            emit(batch.run_with_options(cli.touch, output_encoding, &options)?)
+        }
+    }
+}
+
+fn watch(cli: &Cli) -> ExitCode {
+    // the declared input files are watched even when the first run fails, so that fixing them runs it again
+    let input_files = cli.command.input_files();
+    let mut watcher = match watch::FileWatcher::new(&input_files, Duration::from_millis(cli.debounce)) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            error!("failed to watch input files ({})", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    info!("watching {} for changes", input_files.join(", "));
+
+    let mut cache = None;
+    loop {
+        // before each run rather than after, so that a change made while it runs is not missed
+        watcher.mark_seen();
+
+        match run(cli, &mut cache) {
+            Ok(report) => {
+                // files read by the command beyond the declared ones, such as the culture files of a family
+                let files = report.input_files.iter().map(String::as_str).collect::<Vec<_>>();
+                if let Err(e) = watcher.watch_files(&files) {
+                    error!("failed to watch input files ({})", e);
+                    return ExitCode::FAILURE;
+                }
+            }
+            Err(e) => error!("an unexpected error occured ({})", e),
+        }
+
+        match watcher.wait_for_change() {
+            Ok(changed) => info!("{} changed, running again", changed.join(", ")),
+            Err(e) => {
+                error!("failed to watch input files ({})", e);
+                return ExitCode::FAILURE;
+            }
         }
     }
 }
@@ -323,7 +1291,11 @@ fn main() -> ExitCode {
     let mut builder = Builder::from_env(Env::default().default_filter_or(min_log_level));
     builder.init();
 
-    match run(&cli) {
+    if cli.watch {
+        return watch(&cli);
+    }
+
+    match run(&cli, &mut None) {
         Ok(report) => {
             if cli.check && report.indicates_change() {
                 ExitCode::from(2)
@@ -347,6 +1319,7 @@ mod tests {
         let cli = Cli::parse_from(["cirup", "--show-changes", "file-diff", "a.json", "b.json", "out.json"]);
 
         assert!(cli.show_changes);
+        assert_eq!(cli.diff_granularity, CliDiffGranularity::Word);
         assert!(!cli.touch);
         assert_eq!(cli.output_format, CliOutputFormat::Jsonl);
         match cli.command {
@@ -373,6 +1346,352 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_export_and_import() {
+        let export = Cli::parse_from([
+            "cirup",
+            "export",
+            "--since",
+            "old.resx",
+            "Strings.resx",
+            "Strings.fr.resx",
+            "handoff.xlf",
+        ]);
+        match export.command {
+            Commands::Export {
+                base,
+                target,
+                output,
+                since,
+                source_language,
+                target_language,
+            } => {
+                assert_eq!(base, "Strings.resx");
+                assert_eq!(target, "Strings.fr.resx");
+                assert_eq!(output, "handoff.xlf");
+                assert_eq!(since.as_deref(), Some("old.resx"));
+                assert_eq!(source_language, "en");
+                assert_eq!(target_language, None);
+            }
+            _ => panic!("expected export command"),
+        }
+
+        let import = Cli::parse_from([
+            "cirup",
+            "import",
+            "--allow-stale",
+            "Strings.resx",
+            "Strings.fr.resx",
+            "handoff.xlf",
+        ]);
+        match import.command {
+            Commands::Import {
+                file,
+                output,
+                allow_stale,
+                ..
+            } => {
+                assert_eq!(file, "handoff.xlf");
+                assert_eq!(output, None);
+                assert!(allow_stale);
+            }
+            _ => panic!("expected import command"),
+        }
+    }
+
+    #[test]
+    fn parse_file_pull_and_push() {
+        let cli = Cli::parse_from([
+            "cirup",
+            "file-pull",
+            "--placeholder",
+            "TODO {value}",
+            "Strings.resx",
+            "Strings.fr.resx",
+        ]);
+
+        match cli.command {
+            Commands::FilePull {
+                base,
+                target,
+                output,
+                placeholder,
+            } => {
+                assert_eq!(base, "Strings.resx");
+                assert_eq!(target, "Strings.fr.resx");
+                assert_eq!(output, None);
+                assert_eq!(placeholder, "TODO {value}");
+            }
+            _ => panic!("expected file-pull command"),
+        }
+
+        let cli = Cli::parse_from(["cirup", "file-pull", "Strings.resx", "Strings.fr.resx"]);
+        match cli.command {
+            Commands::FilePull { placeholder, .. } => assert_eq!(placeholder, sync::DEFAULT_PLACEHOLDER),
+            _ => panic!("expected file-pull command"),
+        }
+
+        let cli = Cli::parse_from(["cirup", "file-push", "working.resx", "Strings.resx", "out.resx"]);
+        match cli.command {
+            Commands::FilePush {
+                working,
+                master,
+                output,
+            } => {
+                assert_eq!(working, "working.resx");
+                assert_eq!(master, "Strings.resx");
+                assert_eq!(output.as_deref(), Some("out.resx"));
+            }
+            _ => panic!("expected file-push command"),
+        }
+    }
+
+    #[test]
+    fn parse_key_edit_commands() {
+        let cli = Cli::parse_from([
+            "cirup",
+            "set-key",
+            "lblHello",
+            "Strings.resx=Hello",
+            "Strings.fr.resx=Bonjour",
+        ]);
+        match cli.command {
+            Commands::SetKey { key, values, edits } => {
+                assert_eq!(key.as_deref(), Some("lblHello"));
+                assert_eq!(values, vec!["Strings.resx=Hello", "Strings.fr.resx=Bonjour"]);
+                assert_eq!(edits, None);
+            }
+            _ => panic!("expected set-key command"),
+        }
+
+        let cli = Cli::parse_from(["cirup", "add-key", "--edits", "-"]);
+        match cli.command {
+            Commands::AddKey { key, edits, .. } => {
+                assert_eq!(key, None);
+                assert_eq!(edits.as_deref(), Some("-"));
+            }
+            _ => panic!("expected add-key command"),
+        }
+
+        let cli = Cli::parse_from(["cirup", "delete-key", "lblHello", "Strings.resx", "Strings.fr.resx"]);
+        match cli.command {
+            Commands::DeleteKey { key, files, .. } => {
+                assert_eq!(key.as_deref(), Some("lblHello"));
+                assert_eq!(files, vec!["Strings.resx", "Strings.fr.resx"]);
+            }
+            _ => panic!("expected delete-key command"),
+        }
+
+        assert!(Cli::try_parse_from(["cirup", "set-key", "lblHello"]).is_err());
+        assert!(Cli::try_parse_from(["cirup", "delete-key", "--edits", "edits.jsonl", "lblHello"]).is_err());
+    }
+
+    #[test]
+    fn parse_rename_key_with_names_or_mapping() {
+        let cli = Cli::parse_from(["cirup", "rename-key", "Strings.resx", "lbl*", "label.*"]);
+        match cli.command {
+            Commands::RenameKey {
+                file,
+                from,
+                to,
+                mapping,
+            } => {
+                assert_eq!(file, "Strings.resx");
+                assert_eq!(from.as_deref(), Some("lbl*"));
+                assert_eq!(to.as_deref(), Some("label.*"));
+                assert_eq!(mapping, None);
+            }
+            _ => panic!("expected rename-key command"),
+        }
+
+        let cli = Cli::parse_from(["cirup", "rename-key", "--mapping", "renames.json", "Strings.resx"]);
+        match cli.command {
+            Commands::RenameKey { from, mapping, .. } => {
+                assert_eq!(from, None);
+                assert_eq!(mapping.as_deref(), Some("renames.json"));
+            }
+            _ => panic!("expected rename-key command"),
+        }
+
+        assert!(Cli::try_parse_from(["cirup", "rename-key", "Strings.resx", "lblOld"]).is_err());
+        assert!(
+            Cli::try_parse_from([
+                "cirup",
+                "rename-key",
+                "--mapping",
+                "renames.json",
+                "Strings.resx",
+                "lblOld",
+                "lblNew"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_stale_with_update() {
+        let cli = Cli::parse_from([
+            "cirup",
+            "--check",
+            "stale",
+            "--update",
+            "Strings.resx",
+            "Strings.fr.resx",
+        ]);
+
+        assert!(cli.check);
+        match cli.command {
+            Commands::Stale {
+                base,
+                target,
+                lock,
+                update,
+            } => {
+                assert_eq!(base, "Strings.resx");
+                assert_eq!(target, "Strings.fr.resx");
+                assert_eq!(lock, None);
+                assert!(update);
+            }
+            _ => panic!("expected stale command"),
+        }
+    }
+
+    #[test]
+    fn parse_tm_add_and_suggest() {
+        let add = Cli::parse_from([
+            "cirup",
+            "tm-add",
+            "tm.db",
+            "UIResources.resx",
+            "UIResources.fr.resx",
+            "UIResources.de.resx",
+        ]);
+        match add.command {
+            Commands::TmAdd {
+                tm, targets, language, ..
+            } => {
+                assert_eq!(tm, "tm.db");
+                assert_eq!(targets, vec!["UIResources.fr.resx", "UIResources.de.resx"]);
+                assert_eq!(language, None);
+            }
+            _ => panic!("expected tm-add command"),
+        }
+
+        let suggest = Cli::parse_from([
+            "cirup",
+            "suggest",
+            "--min-score",
+            "80",
+            "tm.db",
+            "MsgResources.resx",
+            "MsgResources.fr.resx",
+        ]);
+        match suggest.command {
+            Commands::Suggest { output, min_score, .. } => {
+                assert_eq!(output, None);
+                assert_eq!(min_score, 80);
+            }
+            _ => panic!("expected suggest command"),
+        }
+
+        assert!(Cli::try_parse_from(["cirup", "suggest", "--min-score", "0", "tm.db", "a.resx", "b.resx"]).is_err());
+    }
+
+    #[test]
+    fn parse_pseudo_with_expansion() {
+        let cli = Cli::parse_from([
+            "cirup",
+            "pseudo",
+            "--expansion",
+            "50",
+            "--no-brackets",
+            "Strings.resx",
+            "Strings.qps-ploc.json",
+        ]);
+
+        match cli.command {
+            Commands::Pseudo {
+                file,
+                output,
+                expansion,
+                no_brackets,
+            } => {
+                assert_eq!(file, "Strings.resx");
+                assert_eq!(output, "Strings.qps-ploc.json");
+                assert_eq!(expansion, 50);
+                assert!(no_brackets);
+            }
+            _ => panic!("expected pseudo command"),
+        }
+    }
+
+    #[test]
+    fn parse_usage_with_sources_and_extractors() {
+        let cli = Cli::parse_from([
+            "cirup",
+            "usage",
+            "--source",
+            "src",
+            "--source",
+            "web",
+            "--extractor",
+            "Tr\\(\"(?P<key>[^\"]+)\"\\)",
+            "--kind",
+            "undefined",
+            "UIResources.resx",
+            "MsgResources.resx",
+        ]);
+
+        match cli.command {
+            Commands::Usage {
+                files,
+                source,
+                extractor,
+                no_builtin_extractors,
+                kind,
+            } => {
+                assert_eq!(files, vec!["UIResources.resx", "MsgResources.resx"]);
+                assert_eq!(source, vec!["src", "web"]);
+                assert_eq!(extractor.len(), 1);
+                assert!(!no_builtin_extractors);
+                assert_eq!(kind, Some(CliUsageFindingKind::Undefined));
+            }
+            _ => panic!("expected usage command"),
+        }
+    }
+
+    #[test]
+    fn parse_codegen_with_namespace() {
+        let cli = Cli::parse_from([
+            "cirup",
+            "--check",
+            "codegen",
+            "--namespace",
+            "MyApp.Properties",
+            "Properties/Resources.resx",
+            "Properties/Resources.Designer.cs",
+        ]);
+
+        assert!(cli.check);
+        match cli.command {
+            Commands::Codegen {
+                file,
+                output,
+                language,
+                namespace,
+                public,
+                ..
+            } => {
+                assert_eq!(file, "Properties/Resources.resx");
+                assert_eq!(output, "Properties/Resources.Designer.cs");
+                assert_eq!(language, None);
+                assert_eq!(namespace.as_deref(), Some("MyApp.Properties"));
+                assert!(!public);
+            }
+            _ => panic!("expected codegen command"),
+        }
+    }
+
     #[test]
     fn parse_file_sort_with_touch() {
         let cli = Cli::parse_from(["cirup", "--touch", "file-sort", "a.json"]);
@@ -381,12 +1700,176 @@ mod tests {
         assert_eq!(cli.output_encoding, CliOutputEncoding::Utf8NoBom);
         assert_eq!(cli.output_format, CliOutputFormat::Jsonl);
         match cli.command {
-            Commands::FileSort { file, output } => {
+            Commands::FileSort {
+                file,
+                output,
+                order,
+                align,
+            } => {
                 assert_eq!(file, "a.json");
                 assert_eq!(output, None);
+                assert_eq!(order, CliSortOrder::Key);
+                assert_eq!(align, None);
+            }
+            _ => panic!("expected file-sort command"),
+        }
+    }
+
+    #[test]
+    fn parse_file_merge_strategy_options() {
+        let cli = Cli::parse_from([
+            "cirup",
+            "file-merge",
+            "--strategy",
+            "update-only",
+            "--skip-empty",
+            "--base",
+            "Strings.resx",
+            "Strings.fr.resx",
+            "import.fr.resx",
+        ]);
+        match cli.command {
+            Commands::FileMerge {
+                file1,
+                strategy,
+                skip_empty,
+                base,
+                ..
+            } => {
+                assert_eq!(file1, "Strings.fr.resx");
+                assert_eq!(strategy, CliMergeStrategy::UpdateOnly);
+                assert!(skip_empty);
+                assert_eq!(base.as_deref(), Some("Strings.resx"));
+            }
+            _ => panic!("expected file-merge command"),
+        }
+
+        assert!(Cli::try_parse_from(["cirup", "file-merge", "--strategy", "prefer-both", "a.json", "b.json"]).is_err());
+    }
+
+    #[test]
+    fn parse_changelog_with_markdown_output() {
+        let cli = Cli::parse_from(["cirup", "changelog", "v1/Strings.resx", "Strings.resx", "CHANGES.md"]);
+        match cli.command {
+            Commands::Changelog { old, new, output } => {
+                assert_eq!(old, "v1/Strings.resx");
+                assert_eq!(new, "Strings.resx");
+                assert_eq!(output.as_deref(), Some("CHANGES.md"));
+            }
+            _ => panic!("expected changelog command"),
+        }
+    }
+
+    #[test]
+    fn parse_stats_command() {
+        let cli = Cli::parse_from([
+            "cirup",
+            "--output-format",
+            "table",
+            "stats",
+            "Strings.resx",
+            "Strings.fr.resx",
+            "Strings.de.resx",
+            "--since",
+            "Strings.old.resx",
+        ]);
+
+        match cli.command {
+            Commands::Stats { base, targets, since } => {
+                assert_eq!(base, "Strings.resx");
+                assert_eq!(targets, vec!["Strings.fr.resx", "Strings.de.resx"]);
+                assert_eq!(since.as_deref(), Some("Strings.old.resx"));
+            }
+            _ => panic!("expected stats command"),
+        }
+    }
+
+    #[test]
+    fn parse_inconsistencies_pairs() {
+        let cli = Cli::parse_from([
+            "cirup",
+            "inconsistencies",
+            "res/UIResources.fr.resx",
+            "Messages.resx=Messages.fr-CA.resx",
+        ]);
+
+        match cli.command {
+            Commands::Inconsistencies { pairs } => {
+                assert_eq!(
+                    translation_pairs(&pairs).expect("pairs"),
+                    vec![
+                        (
+                            String::from("res/UIResources.resx"),
+                            String::from("res/UIResources.fr.resx")
+                        ),
+                        (String::from("Messages.resx"), String::from("Messages.fr-CA.resx")),
+                    ]
+                );
+            }
+            _ => panic!("expected inconsistencies command"),
+        }
+        assert!(translation_pairs(&[String::from("Strings.resx")]).is_err());
+    }
+
+    #[test]
+    fn parse_lint_glossary() {
+        let cli = Cli::parse_from([
+            "cirup",
+            "--check",
+            "lint",
+            "--glossary",
+            "glossary.tbx",
+            "Strings.resx",
+            "Strings.fr.resx",
+        ]);
+
+        assert!(cli.check);
+        match cli.command {
+            Commands::Lint {
+                base,
+                targets,
+                glossary,
+            } => {
+                assert_eq!(base, "Strings.resx");
+                assert_eq!(targets, vec!["Strings.fr.resx"]);
+                assert_eq!(glossary.as_deref(), Some("glossary.tbx"));
+            }
+            _ => panic!("expected lint command"),
+        }
+    }
+
+    #[test]
+    fn parse_file_sort_order_and_align() {
+        let cli = Cli::parse_from(["cirup", "file-sort", "--order", "ordinal-ignore-case", "Strings.resx"]);
+        match cli.command {
+            Commands::FileSort { order, align, .. } => {
+                assert_eq!(order, CliSortOrder::OrdinalIgnoreCase);
+                assert_eq!(align, None);
+            }
+            _ => panic!("expected file-sort command"),
+        }
+
+        let cli = Cli::parse_from(["cirup", "file-sort", "Strings.fr.resx", "--align", "Strings.resx"]);
+        match cli.command {
+            Commands::FileSort { file, align, .. } => {
+                assert_eq!(file, "Strings.fr.resx");
+                assert_eq!(align.as_deref(), Some("Strings.resx"));
             }
             _ => panic!("expected file-sort command"),
         }
+
+        assert!(
+            Cli::try_parse_from([
+                "cirup",
+                "file-sort",
+                "--order",
+                "natural",
+                "--align",
+                "Strings.resx",
+                "Strings.fr.resx"
+            ])
+            .is_err()
+        );
     }
 
     #[test]
@@ -482,6 +1965,73 @@ mod tests {
         assert!(cli.count_only);
     }
 
+    #[test]
+    fn parse_run_with_jobs() {
+        let cli = Cli::parse_from(["cirup", "--check", "run", "cirup.toml", "-j", "4"]);
+        assert!(cli.check);
+
+        match cli.command {
+            Commands::Run { manifest, jobs } => {
+                assert_eq!(manifest, "cirup.toml");
+                assert_eq!(jobs, Some(4));
+            }
+            _ => panic!("expected run command"),
+        }
+    }
+
+    #[test]
+    fn parse_coverage_with_min_and_diagnostic_format() {
+        let cli = Cli::parse_from([
+            "cirup",
+            "--diagnostic-format",
+            "msbuild",
+            "coverage",
+            "Strings.resx",
+            "Strings.fr.resx",
+            "Strings.de.resx",
+            "--min",
+            "95.5",
+        ]);
+        assert_eq!(cli.diagnostic_format, Some(CliDiagnosticFormat::Msbuild));
+
+        match cli.command {
+            Commands::Coverage { base, targets, min } => {
+                assert_eq!(base, "Strings.resx");
+                assert_eq!(targets, vec!["Strings.fr.resx", "Strings.de.resx"]);
+                assert_eq!(min, Some(95.5));
+            }
+            _ => panic!("expected coverage command"),
+        }
+    }
+
+    #[test]
+    fn parse_github_diagnostic_format() {
+        let cli = Cli::parse_from([
+            "cirup",
+            "--check",
+            "--diagnostic-format",
+            "github",
+            "file-diff",
+            "a.json",
+            "b.json",
+        ]);
+        assert_eq!(cli.diagnostic_format, Some(CliDiagnosticFormat::Github));
+        assert_eq!(cli.command.input_files(), vec!["a.json", "b.json"]);
+    }
+
+    #[test]
+    fn parse_watch_with_debounce() {
+        let cli = Cli::parse_from(["cirup", "file-sort", "strings.json", "--watch", "--debounce", "500"]);
+        assert!(cli.watch);
+        assert_eq!(cli.debounce, 500);
+
+        let cli = Cli::parse_from(["cirup", "file-print", "strings.json"]);
+        assert!(!cli.watch);
+        assert_eq!(cli.debounce, watch::DEFAULT_DEBOUNCE_MS);
+
+        assert!(Cli::try_parse_from(["cirup", "--watch", "--check", "file-print", "strings.json"]).is_err());
+    }
+
     #[test]
     fn parse_file_commands_with_outputs() {
         let convert = Cli::parse_from(["cirup", "file-convert", "a.json", "b.restext"]);
@@ -495,10 +2045,20 @@ mod tests {
 
         let merge = Cli::parse_from(["cirup", "file-merge", "a.json", "b.json", "out.json"]);
         match merge.command {
-            Commands::FileMerge { file1, file2, output } => {
+            Commands::FileMerge {
+                file1,
+                file2,
+                output,
+                strategy,
+                skip_empty,
+                base,
+            } => {
                 assert_eq!(file1, "a.json");
                 assert_eq!(file2, "b.json");
                 assert_eq!(output.as_deref(), Some("out.json"));
+                assert_eq!(strategy, CliMergeStrategy::PreferRight);
+                assert!(!skip_empty);
+                assert_eq!(base, None);
             }
             _ => panic!("expected file-merge command"),
         }
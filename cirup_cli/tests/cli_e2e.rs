@@ -1,6 +1,7 @@
 use std::fs;
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output};
+use std::process::{Command, Output, Stdio};
 
 use serde_json::Value;
 use tempfile::tempdir;
@@ -115,6 +116,22 @@ fn output_format_json_prints_filtered_resource_array() {
     assert_eq!(array[0]["value"], "I'm on a boat.");
 }
 
+#[test]
+fn unsupported_output_extension_fails_without_writing() {
+    let temp = tempdir().expect("tempdir");
+    let out_file = temp.path().join("out.txt");
+
+    let output = run_cirup(&[
+        "file-print",
+        &fixture_path("test.json").to_string_lossy(),
+        &out_file.to_string_lossy(),
+    ]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr_string(&output).contains("unsupported output file"));
+    assert!(!out_file.exists());
+}
+
 #[test]
 fn dry_run_summary_reports_in_place_write_without_modifying_file() {
     let temp = tempdir().expect("tempdir");
@@ -144,3 +161,867 @@ fn dry_run_summary_reports_in_place_write_without_modifying_file() {
     let after = fs::read_to_string(&file).expect("read file after dry-run");
     assert_eq!(after, original);
 }
+
+#[test]
+fn export_then_import_round_trips_translations() {
+    let temp = tempdir().expect("tempdir");
+    let base = fixture_path("test.resx");
+    let target = temp.path().join("test.fr.resx");
+    let handoff = temp.path().join("handoff.csv");
+    fs::copy(fixture_path("test_old.resx"), &target).expect("copy target fixture");
+
+    let output = run_cirup(&[
+        "export",
+        &base.to_string_lossy(),
+        &target.to_string_lossy(),
+        &handoff.to_string_lossy(),
+    ]);
+    assert!(output.status.success());
+
+    let exported = fs::read_to_string(&handoff).expect("read hand-off file");
+    assert_eq!(
+        exported,
+        "key,state,source,target\r\nlblYolo,new,You only live once,\r\nlblDogs,new,Who let the dogs out?,\r\n"
+    );
+
+    fs::write(
+        &handoff,
+        exported
+            .replace("once,\r\n", "once,Juste une vie a vivre\r\n")
+            .replace("out?,\r\n", "out?,Qui a laissé les chiens sortir?\r\n"),
+    )
+    .expect("write translated hand-off file");
+
+    let output = run_cirup(&[
+        "import",
+        &base.to_string_lossy(),
+        &target.to_string_lossy(),
+        &handoff.to_string_lossy(),
+    ]);
+    assert!(output.status.success());
+    assert!(stdout_string(&output).is_empty());
+
+    let output = run_cirup(&[
+        "--count-only",
+        "file-diff",
+        &base.to_string_lossy(),
+        &target.to_string_lossy(),
+    ]);
+    assert_eq!(stdout_string(&output), "0\n");
+}
+
+#[test]
+fn export_fails_without_writing_when_the_base_cannot_be_loaded() {
+    let temp = tempdir().expect("tempdir");
+    let base = temp.path().join("Nope.restext");
+    let handoff = temp.path().join("handoff.xlf");
+
+    let output = run_cirup(&[
+        "export",
+        &base.to_string_lossy(),
+        &temp.path().join("Nope.fr.restext").to_string_lossy(),
+        &handoff.to_string_lossy(),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr_string(&output).contains("Nope.restext"));
+    assert!(!handoff.exists());
+}
+
+#[test]
+fn file_pull_and_push_keep_culture_files_in_sync() {
+    let temp = tempdir().expect("tempdir");
+    let base = temp.path().join("Strings.restext");
+    let target = temp.path().join("Strings.fr.restext");
+    fs::write(&base, "lblOne=One\r\nlblTwo=Two\r\n").expect("write base");
+    fs::write(&target, "lblOld=Vieux\r\nlblOne=Un\r\n").expect("write target");
+
+    let output = run_cirup(&[
+        "--check",
+        "file-pull",
+        &base.to_string_lossy(),
+        &target.to_string_lossy(),
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        fs::read_to_string(&target).expect("read target"),
+        "lblOld=Vieux\r\nlblOne=Un\r\n"
+    );
+
+    let output = run_cirup(&[
+        "file-pull",
+        "--placeholder",
+        "TODO {value}",
+        &base.to_string_lossy(),
+        &target.to_string_lossy(),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    assert_eq!(
+        stdout_string(&output),
+        "{\"name\":\"lblTwo\",\"kind\":\"added\",\"value\":\"TODO Two\"}\n\
+         {\"name\":\"lblOld\",\"kind\":\"removed\",\"value\":\"Vieux\"}\n"
+    );
+    assert_eq!(
+        fs::read_to_string(&target).expect("read target"),
+        "lblOne=Un\r\nlblTwo=TODO Two\r\n"
+    );
+
+    let working = temp.path().join("working.restext");
+    fs::write(&working, "lblTwo=Deux\r\n").expect("write working copy");
+    let output = run_cirup(&[
+        "--summary",
+        "file-push",
+        &working.to_string_lossy(),
+        &target.to_string_lossy(),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    let summary: Value = serde_json::from_str(&stdout_string(&output)).expect("summary json");
+    assert_eq!(summary["operation"], "file-push");
+    assert_eq!(summary["output_count"], 1);
+    assert_eq!(
+        fs::read_to_string(&target).expect("read target"),
+        "lblOne=Un\r\nlblTwo=Deux\r\n"
+    );
+}
+
+#[test]
+fn rename_key_renames_across_the_family_or_not_at_all() {
+    let temp = tempdir().expect("tempdir");
+    let neutral = temp.path().join("Strings.restext");
+    let french = temp.path().join("Strings.fr.restext");
+    fs::write(&neutral, "# menu\r\nlblOpen=Open\r\nlblClose=Close\r\n").expect("write neutral file");
+    fs::write(&french, "lblOpen=Ouvrir\r\nlblShut=Fermer\r\n").expect("write french file");
+
+    let output = run_cirup(&["rename-key", &neutral.to_string_lossy(), "lblOpen", "lblShut"]);
+    assert!(!output.status.success());
+    assert!(stderr_string(&output).contains("collides with another key"));
+    assert_eq!(
+        fs::read_to_string(&neutral).expect("read neutral file"),
+        "# menu\r\nlblOpen=Open\r\nlblClose=Close\r\n"
+    );
+
+    let mapping = temp.path().join("renames.json");
+    fs::write(&mapping, r#"{"lblOpen": "menuOpen", "lblShut": "menuClose"}"#).expect("write mapping");
+    let output = run_cirup(&[
+        "rename-key",
+        "--mapping",
+        &mapping.to_string_lossy(),
+        &french.to_string_lossy(),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    assert_eq!(stdout_string(&output).lines().count(), 3);
+    assert_eq!(
+        fs::read_to_string(&neutral).expect("read neutral file"),
+        "# menu\r\nmenuOpen=Open\r\nlblClose=Close\r\n"
+    );
+    assert_eq!(
+        fs::read_to_string(&french).expect("read french file"),
+        "menuOpen=Ouvrir\r\nmenuClose=Fermer\r\n"
+    );
+}
+
+#[test]
+fn key_edit_commands_edit_files_or_report_changes() {
+    let temp = tempdir().expect("tempdir");
+    let neutral = temp.path().join("Strings.restext");
+    let french = temp.path().join("Strings.fr.restext");
+    fs::write(&neutral, "lblOpen=Open\r\nlblClose=Close\r\n").expect("write neutral file");
+    let neutral_value = |value: &str| format!("{}={}", neutral.to_string_lossy(), value);
+    let french_value = |value: &str| format!("{}={}", french.to_string_lossy(), value);
+
+    let output = run_cirup(&["--check", "set-key", "lblSave", &neutral_value("Save")]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        fs::read_to_string(&neutral).expect("read neutral file"),
+        "lblOpen=Open\r\nlblClose=Close\r\n"
+    );
+
+    let output = run_cirup(&["set-key", "lblOpen", &neutral_value("Open"), &french_value("Ouvrir")]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    assert_eq!(
+        fs::read_to_string(&neutral).expect("read neutral file"),
+        "lblOpen=Open\r\nlblClose=Close\r\n"
+    );
+    assert_eq!(
+        fs::read_to_string(&french).expect("read french file"),
+        "lblOpen=Ouvrir\r\n"
+    );
+
+    let output = run_cirup(&["add-key", "lblOpen", &french_value("Ouvrir le fichier")]);
+    assert!(!output.status.success());
+    assert!(stderr_string(&output).contains("'lblOpen' already exists"));
+
+    let edits = temp.path().join("edits.jsonl");
+    fs::write(
+        &edits,
+        format!(
+            "{{\"file\": {:?}, \"key\": \"lblClose\"}}\n{{\"file\": {:?}, \"key\": \"lblClose\"}}\n",
+            neutral.to_string_lossy(),
+            french.to_string_lossy()
+        ),
+    )
+    .expect("write edits");
+    let output = run_cirup(&["delete-key", "--edits", &edits.to_string_lossy()]);
+    assert!(!output.status.success());
+    assert!(stderr_string(&output).contains("'lblClose' does not exist"));
+    assert_eq!(
+        fs::read_to_string(&neutral).expect("read neutral file"),
+        "lblOpen=Open\r\nlblClose=Close\r\n"
+    );
+
+    let output = run_cirup(&["delete-key", "lblClose", &neutral.to_string_lossy()]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    assert_eq!(
+        fs::read_to_string(&neutral).expect("read neutral file"),
+        "lblOpen=Open\r\n"
+    );
+}
+
+#[test]
+fn file_sort_aligns_on_base_order_or_sorts_naturally() {
+    let temp = tempdir().expect("tempdir");
+    let base = temp.path().join("Strings.restext");
+    let french = temp.path().join("Strings.fr.restext");
+    fs::write(&base, "lbl10=Ten\r\nlbl2=Two\r\nlbl1=One\r\n").expect("write base file");
+    fs::write(&french, "lblExtra=Extra\r\nlbl1=Un\r\nlbl10=Dix\r\n").expect("write french file");
+
+    let output = run_cirup(&[
+        "file-sort",
+        &french.to_string_lossy(),
+        "--align",
+        &base.to_string_lossy(),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    assert_eq!(
+        fs::read_to_string(&french).expect("read french file"),
+        "lbl10=Dix\r\nlbl1=Un\r\nlblExtra=Extra\r\n"
+    );
+
+    let output = run_cirup(&["file-sort", "--order", "natural", &base.to_string_lossy()]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    assert_eq!(
+        fs::read_to_string(&base).expect("read base file"),
+        "lbl1=One\r\nlbl2=Two\r\nlbl10=Ten\r\n"
+    );
+}
+
+#[test]
+fn file_merge_strategy_reports_counts_in_summary() {
+    let temp = tempdir().expect("tempdir");
+    let french = temp.path().join("Strings.fr.restext");
+    let import = temp.path().join("import.fr.restext");
+    fs::write(&french, "lblOne=Un\r\nlblTwo=\r\n").expect("write french file");
+    fs::write(&import, "lblOne=Une\r\nlblTwo=Deux\r\nlblThree=\r\n").expect("write import file");
+
+    let output = run_cirup(&[
+        "--summary",
+        "--output-format",
+        "json",
+        "file-merge",
+        "--strategy",
+        "prefer-left",
+        "--skip-empty",
+        &french.to_string_lossy(),
+        &import.to_string_lossy(),
+        &french.to_string_lossy(),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    let summary: Value = serde_json::from_str(&stdout_string(&output)).expect("summary json");
+    assert_eq!(summary["merge"]["strategy"], "prefer-left");
+    assert_eq!(summary["merge"]["added"], 0);
+    assert_eq!(summary["merge"]["updated"], 1);
+    assert_eq!(summary["merge"]["skipped"], 2);
+    assert_eq!(
+        fs::read_to_string(&french).expect("read french file"),
+        "lblOne=Un\r\nlblTwo=Deux\r\n"
+    );
+}
+
+#[test]
+fn file_merge_default_strategy_reports_counts_and_keeps_key_order() {
+    let temp = tempdir().expect("tempdir");
+    let french = temp.path().join("Strings.fr.restext");
+    let import = temp.path().join("import.fr.restext");
+    fs::write(&french, "lblTwo=\r\nlblOne=Un\r\n").expect("write french file");
+    fs::write(&import, "lblAdded=Ajout\r\nlblTwo=Deux\r\n").expect("write import file");
+
+    let output = run_cirup(&[
+        "--summary",
+        "--output-format",
+        "json",
+        "file-merge",
+        &french.to_string_lossy(),
+        &import.to_string_lossy(),
+        &french.to_string_lossy(),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    let summary: Value = serde_json::from_str(&stdout_string(&output)).expect("summary json");
+    assert_eq!(summary["merge"]["strategy"], "prefer-right");
+    assert_eq!(summary["merge"]["added"], 1);
+    assert_eq!(summary["merge"]["updated"], 1);
+    assert_eq!(
+        fs::read_to_string(&french).expect("read french file"),
+        "lblTwo=Deux\r\nlblOne=Un\r\nlblAdded=Ajout\r\n"
+    );
+}
+
+#[test]
+fn changelog_lists_changes_and_writes_markdown() {
+    let temp = tempdir().expect("tempdir");
+    let old = temp.path().join("old.restext");
+    let new = temp.path().join("new.restext");
+    let markdown = temp.path().join("CHANGES.md");
+    fs::write(&old, "lblTitle=Title\r\nlblOpen=Open\r\nlblGone=Gone\r\n").expect("write old file");
+    fs::write(&new, "lblTitle=Main title\r\nmenuOpen=Open\r\n").expect("write new file");
+
+    let output = run_cirup(&[
+        "changelog",
+        &old.to_string_lossy(),
+        &new.to_string_lossy(),
+        &markdown.to_string_lossy(),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    let kinds = stdout_string(&output)
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).expect("changelog json")["kind"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, vec!["modified", "renamed", "removed"]);
+    assert_eq!(
+        fs::read_to_string(&markdown).expect("read markdown"),
+        "## Modified (1)\n\n- `lblTitle`: Title → Main title\n\n## Renamed (1)\n\n- `lblOpen` → `menuOpen`: Open\n\n## Removed (1)\n\n- `lblGone`: Gone\n"
+    );
+}
+
+#[test]
+fn show_changes_prints_inline_diff_of_changed_values() {
+    let temp = tempdir().expect("tempdir");
+    let new = temp.path().join("new.restext");
+    let old = temp.path().join("old.restext");
+    fs::write(&new, "lblSave=Save the file\r\n").expect("write new file");
+    fs::write(&old, "lblSave=Save this file\r\n").expect("write old file");
+
+    let output = run_cirup(&[
+        "--show-changes",
+        "--output-format",
+        "table",
+        "file-diff",
+        &new.to_string_lossy(),
+        &old.to_string_lossy(),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    assert!(stdout_string(&output).contains("| lblSave | Save [-this-]{+the+} file |"));
+
+    let output = run_cirup(&[
+        "--show-changes",
+        "--diff-granularity",
+        "char",
+        "file-diff",
+        &new.to_string_lossy(),
+        &old.to_string_lossy(),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    let row: Value = serde_json::from_str(&stdout_string(&output)).expect("change json");
+    assert_eq!(row["other_value"], "Save this file");
+    assert_eq!(row["spans"][1], serde_json::json!({"kind": "removed", "text": "is"}));
+}
+
+#[test]
+fn suggest_writes_the_filled_file_with_a_review_list() {
+    let temp = tempdir().expect("tempdir");
+    let tm = temp.path().join("tm.db");
+    let ui = temp.path().join("UIResources.restext");
+    let ui_french = temp.path().join("UIResources.fr.restext");
+    let msg = temp.path().join("MsgResources.restext");
+    let msg_french = temp.path().join("MsgResources.fr.restext");
+    fs::write(&ui, "lblSave=Save file\r\n").expect("write base file");
+    fs::write(&ui_french, "lblSave=Enregistrer le fichier\r\n").expect("write french file");
+    fs::write(&msg, "msgSave=Save file\r\nmsgSaveAll=Save files\r\n").expect("write second base file");
+    fs::write(&msg_french, "").expect("write second french file");
+
+    let output = run_cirup(&[
+        "tm-add",
+        &tm.to_string_lossy(),
+        &ui.to_string_lossy(),
+        &ui_french.to_string_lossy(),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+
+    let output = run_cirup(&[
+        "suggest",
+        &tm.to_string_lossy(),
+        &msg.to_string_lossy(),
+        &msg_french.to_string_lossy(),
+        &msg_french.to_string_lossy(),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+
+    assert_eq!(
+        fs::read_to_string(&msg_french).expect("read filled file"),
+        "msgSave=Enregistrer le fichier\r\nmsgSaveAll=Enregistrer le fichier\r\n"
+    );
+    assert_eq!(
+        fs::read_to_string(temp.path().join("MsgResources.fr.restext.review")).expect("read review list"),
+        "msgSaveAll\r\n"
+    );
+}
+
+#[test]
+fn stats_counts_words_to_translate_by_file_and_prefix() {
+    let temp = tempdir().expect("tempdir");
+    let base = temp.path().join("Strings.restext");
+    let french = temp.path().join("Strings.fr.restext");
+    let german = temp.path().join("Strings.de.restext");
+    fs::write(
+        &base,
+        "lblOpen=Open <b>{0}</b> files\r\nlblSave=Save\r\nmsgOpen=Open <b>{0}</b> files\r\n",
+    )
+    .expect("write base file");
+    fs::write(&french, "lblSave=Enregistrer\r\n").expect("write french file");
+    fs::write(&german, "lblOpen=Öffnen\r\nlblSave=Speichern\r\nmsgOpen=Öffnen\r\n").expect("write german file");
+
+    let output = run_cirup(&[
+        "stats",
+        &base.to_string_lossy(),
+        &french.to_string_lossy(),
+        &german.to_string_lossy(),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    let rows = stdout_string(&output)
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).expect("stats json"))
+        .collect::<Vec<_>>();
+    let counts = rows
+        .iter()
+        .map(|row| {
+            (
+                row["prefix"].clone(),
+                row["words"].clone(),
+                row["placeholders"].clone(),
+                row["repeated_words"].clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        counts,
+        vec![
+            (Value::Null, 4.into(), 2.into(), 2.into()),
+            ("lbl".into(), 2.into(), 1.into(), 0.into()),
+            ("msg".into(), 2.into(), 1.into(), 2.into()),
+            (Value::Null, 0.into(), 0.into(), 0.into()),
+        ]
+    );
+    assert_eq!(rows[0]["file"], french.to_string_lossy().as_ref());
+    assert_eq!(rows[0]["characters"], 18);
+}
+
+#[test]
+fn inconsistencies_reports_divergent_and_shared_translations() {
+    let temp = tempdir().expect("tempdir");
+    let path = |name: &str| temp.path().join(name).to_string_lossy().into_owned();
+    for (name, data) in [
+        ("UIResources.restext", "btnCancel=Cancel\r\nbtnStop=Stop\r\n"),
+        ("UIResources.fr.restext", "btnCancel=Annuler\r\nbtnStop=Arrêter\r\n"),
+        ("MsgResources.restext", "lblCancel=Cancel\r\nlblHalt=Halt\r\n"),
+        ("MsgResources.fr.restext", "lblCancel=Abandonner\r\nlblHalt=Arrêter\r\n"),
+    ] {
+        fs::write(path(name), data).expect("write resource file");
+    }
+
+    let output = run_cirup(&[
+        "inconsistencies",
+        &path("UIResources.fr.restext"),
+        &format!("{}={}", path("MsgResources.restext"), path("MsgResources.fr.restext")),
+    ]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    let findings = stdout_string(&output)
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).expect("inconsistency json"))
+        .collect::<Vec<_>>();
+    assert_eq!(findings.len(), 2);
+    assert_eq!(findings[0]["kind"], "divergent-translations");
+    assert_eq!(findings[0]["language"], "fr");
+    assert_eq!(findings[0]["value"], "Cancel");
+    assert_eq!(
+        findings[0]["variants"][1],
+        serde_json::json!({
+            "value": "Abandonner",
+            "keys": [{"file": path("MsgResources.fr.restext"), "name": "lblCancel"}],
+        })
+    );
+    assert_eq!(findings[1]["kind"], "shared-translation");
+    assert_eq!(findings[1]["value"], "Arrêter");
+
+    let output = run_cirup(&["--check", "inconsistencies", &path("UIResources.fr.restext")]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr_string(&output));
+}
+
+#[test]
+fn lint_glossary_reports_term_violations_and_gates_check() {
+    let temp = tempdir().expect("tempdir");
+    let path = |name: &str| temp.path().join(name).to_string_lossy().into_owned();
+    fs::write(
+        path("glossary.tbx"),
+        r#"<martif type="TBX" xml:lang="en"><text><body>
+<termEntry><langSet xml:lang="en"><tig><term>Data Source</term></tig></langSet><langSet xml:lang="fr"><tig><term>Source de données</term></tig></langSet></termEntry>
+<termEntry><descrip type="doNotTranslate">yes</descrip><langSet xml:lang="en"><tig><term>Devolutions</term></tig></langSet></termEntry>
+</body></text></martif>"#,
+    )
+    .expect("write glossary");
+    fs::write(
+        path("Strings.restext"),
+        "lblSource=Add a data source\r\nlblBrand=Devolutions Hub\r\n",
+    )
+    .expect("write base file");
+    fs::write(
+        path("Strings.fr.restext"),
+        "lblSource=Ajouter une origine\r\nlblBrand=Devolutions Hub\r\n",
+    )
+    .expect("write french file");
+
+    let output = run_cirup(&[
+        "--check",
+        "--diagnostic-format",
+        "msbuild",
+        "lint",
+        "--glossary",
+        &path("glossary.tbx"),
+        &path("Strings.restext"),
+        &path("Strings.fr.restext"),
+    ]);
+    assert_eq!(output.status.code(), Some(2), "{}", stderr_string(&output));
+    assert_eq!(
+        stdout_string(&output),
+        format!(
+            "{}(1,1): error CIRUP006: lblSource: 'Data Source' must be translated as 'Source de données'\n",
+            path("Strings.fr.restext")
+        )
+    );
+
+    fs::write(
+        path("Strings.fr.restext"),
+        "lblSource=Ajouter une source de données\r\nlblBrand=Devolutions Hub\r\n",
+    )
+    .expect("write french file");
+    let output = run_cirup(&[
+        "--check",
+        "lint",
+        "--glossary",
+        &path("glossary.tbx"),
+        &path("Strings.restext"),
+        &path("Strings.fr.restext"),
+    ]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr_string(&output));
+    assert_eq!(stdout_string(&output), "");
+}
+
+#[test]
+fn convert_to_binary_resources_round_trips() {
+    let temp = tempdir().expect("tempdir");
+    let source = fixture_path("test.resx");
+    let binary = temp.path().join("test.resources");
+
+    let output = run_cirup(&["file-convert", &source.to_string_lossy(), &binary.to_string_lossy()]);
+    assert!(output.status.success());
+    assert_eq!(
+        &fs::read(&binary).expect("read .resources file")[0..4],
+        &[0xCE, 0xCA, 0xEF, 0xBE]
+    );
+
+    for (left, right) in [(&source, &binary), (&binary, &source)] {
+        let output = run_cirup(&[
+            "--count-only",
+            "--show-changes",
+            "file-diff",
+            &left.to_string_lossy(),
+            &right.to_string_lossy(),
+        ]);
+        assert!(output.status.success(), "{}", stderr_string(&output));
+        assert_eq!(stdout_string(&output), "0\n");
+    }
+}
+
+#[test]
+fn watch_reruns_query_when_input_changes() {
+    let temp = tempdir().expect("tempdir");
+    let file = temp.path().join("strings.restext");
+    fs::write(&file, "lblOne=One\r\n").expect("write resources");
+
+    let mut child = cirup_command()
+        .args([
+            "--watch",
+            "--debounce",
+            "50",
+            "--log-level",
+            "info",
+            "--count-only",
+            "file-print",
+            &file.to_string_lossy(),
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn cirup");
+    let mut stdout = BufReader::new(child.stdout.take().expect("child stdout"));
+    let mut stderr = BufReader::new(child.stderr.take().expect("child stderr"));
+
+    let mut line = String::new();
+    stdout.read_line(&mut line).expect("read first run");
+    assert_eq!(line, "1\n");
+
+    // wait until the input file is watched before changing it
+    let mut log = String::new();
+    while !log.contains("watching") {
+        log.clear();
+        assert!(stderr.read_line(&mut log).expect("read stderr") > 0, "cirup exited");
+    }
+
+    fs::write(&file, "lblOne=One\r\nlblTwo=Two\r\n").expect("update resources");
+
+    line.clear();
+    stdout.read_line(&mut line).expect("read second run");
+    assert_eq!(line, "2\n");
+
+    child.kill().expect("kill cirup");
+    let _ = child.wait();
+}
+
+#[test]
+fn watch_keeps_watching_after_a_failed_first_run() {
+    let temp = tempdir().expect("tempdir");
+    let file = temp.path().join("strings.restext");
+    let output = temp.path().join("strings.qps.restext");
+
+    let mut child = cirup_command()
+        .args([
+            "--watch",
+            "--debounce",
+            "50",
+            "--log-level",
+            "info",
+            "pseudo",
+            &file.to_string_lossy(),
+            &output.to_string_lossy(),
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn cirup");
+    let mut stderr = BufReader::new(child.stderr.take().expect("child stderr"));
+
+    // the first run fails on the missing input file
+    let mut log = String::new();
+    while !log.contains("unexpected error") {
+        log.clear();
+        assert!(stderr.read_line(&mut log).expect("read stderr") > 0, "cirup exited");
+    }
+
+    fs::write(&file, "lblOne=One\r\n").expect("write resources");
+
+    while !log.contains("running again") {
+        log.clear();
+        assert!(stderr.read_line(&mut log).expect("read stderr") > 0, "cirup exited");
+    }
+    for _ in 0..100 {
+        if output.exists() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+
+    child.kill().expect("kill cirup");
+    let _ = child.wait();
+    assert_eq!(
+        fs::read_to_string(&output).expect("read pseudo-localized file"),
+        "lblOne=[Öñé~]\r\n"
+    );
+}
+
+#[test]
+fn run_manifest_reports_each_operation_and_combines_check() {
+    let temp = tempdir().expect("tempdir");
+    fs::copy(fixture_path("test.resx"), temp.path().join("test.resx")).expect("copy fixture");
+    fs::copy(fixture_path("test_old.resx"), temp.path().join("test_old.resx")).expect("copy fixture");
+    let manifest = temp.path().join("cirup.json");
+    fs::write(
+        &manifest,
+        r#"{"operations": [
+            {"operation": "convert", "file": "test.resx", "output": "test.json"},
+            {"operation": "diff", "file1": "test.resx", "file2": "test_old.resx"}
+        ]}"#,
+    )
+    .expect("write manifest");
+
+    let output = run_cirup(&["--check", "run", &manifest.to_string_lossy()]);
+    assert_eq!(output.status.code(), Some(2), "{}", stderr_string(&output));
+    assert!(!temp.path().join("test.json").exists());
+
+    let reports = stdout_string(&output)
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).expect("report line"))
+        .collect::<Vec<_>>();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0]["operation"], "file-convert");
+    assert_eq!(reports[0]["would_write"], true);
+    assert_eq!(reports[1]["operation"], "file-diff");
+    assert_eq!(reports[1]["output_count"], 2);
+
+    let output = run_cirup(&["run", &manifest.to_string_lossy()]);
+    assert!(output.status.success());
+    assert!(temp.path().join("test.json").exists());
+}
+
+#[test]
+fn run_fails_on_missing_input_without_writing_its_output() {
+    let temp = tempdir().expect("tempdir");
+    let manifest = temp.path().join("cirup.json");
+    fs::write(
+        &manifest,
+        r#"{"operations": [{"operation": "convert", "file": "missing.resx", "output": "missing.json"}]}"#,
+    )
+    .expect("write manifest");
+
+    let output = run_cirup(&["run", &manifest.to_string_lossy()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr_string(&output).contains("missing.resx"));
+    assert!(!temp.path().join("missing.json").exists());
+}
+
+#[test]
+fn lint_and_coverage_print_msbuild_diagnostics() {
+    let dir = tempdir().expect("temp dir");
+    let base = dir.path().join("Strings.restext");
+    let target = dir.path().join("Strings.fr.restext");
+    fs::write(&base, "lblHello=Hello {0}\r\nlblBye=Bye\r\n").expect("write base");
+    fs::write(&target, "lblHello=Bonjour\r\n").expect("write target");
+    let base = base.to_string_lossy().into_owned();
+    let target = target.to_string_lossy().into_owned();
+
+    let output = run_cirup(&["--diagnostic-format", "msbuild", "lint", &base, &target]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout_string(&output),
+        format!("{target}(1,1): error CIRUP001: lblHello: placeholders [] do not match [{{0}}] in {base}\n")
+    );
+
+    let output = run_cirup(&[
+        "--check",
+        "--diagnostic-format",
+        "msbuild",
+        "coverage",
+        &base,
+        &target,
+        "--min",
+        "80",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout_string(&output).starts_with(&format!("{target}: error CIRUP100: 50.0% of the keys")));
+
+    let output = run_cirup(&[
+        "--diagnostic-format",
+        "msbuild",
+        "coverage",
+        &base,
+        &target,
+        "--min",
+        "80",
+    ]);
+    assert_eq!(
+        stdout_string(&output),
+        format!(
+            "{target}: error CIRUP100: 50.0% of the keys of {base} are translated (1 missing), below the minimum of 80%\n"
+        )
+    );
+}
+
+#[test]
+fn file_diff_check_and_parse_errors_print_github_annotations() {
+    let dir = tempdir().expect("temp dir");
+    let base = dir.path().join("Strings.json");
+    let target = dir.path().join("Strings.fr.json");
+    let broken = dir.path().join("Broken.fr.json");
+    fs::write(&base, "{\n  \"lblHello\": \"Hello\",\n  \"lblBye\": \"Bye\"\n}\n").expect("write base");
+    fs::write(&target, "{\n  \"lblHello\": \"Bonjour\"\n}\n").expect("write target");
+    fs::write(
+        &broken,
+        "{\n  \"lblHello\": \"Bonjour\"\n  \"lblBye\": \"Au revoir\"\n}\n",
+    )
+    .expect("write broken");
+    let base = base.to_string_lossy().into_owned();
+    let target = target.to_string_lossy().into_owned();
+    let broken = broken.to_string_lossy().into_owned();
+
+    let output = run_cirup(&["--check", "--diagnostic-format", "github", "file-diff", &base, &target]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        stdout_string(&output),
+        format!("::error file={base},line=3,col=3,title=CIRUP010::lblBye: key is missing from {target}\n")
+    );
+
+    let output = run_cirup(&["--diagnostic-format", "msbuild", "file-diff", &base, &broken]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout_string(&output),
+        format!("{broken}(3,3): error CIRUP000: expected `,` or `}}`\n")
+    );
+
+    // the parse error is reported by the command loading the file
+    let output = run_cirup(&["--diagnostic-format", "msbuild", "lint", &base, &broken]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout_string(&output),
+        format!("{broken}(3,3): error CIRUP000: expected `,` or `}}`\n")
+    );
+
+    // records without diagnostics are printed as usual
+    let output = run_cirup(&["--diagnostic-format", "msbuild", "file-print", &base]);
+    assert!(output.status.success(), "{}", stderr_string(&output));
+    let stdout = stdout_string(&output);
+    assert!(stdout.contains("lblHello") && stdout.contains("Bye"), "{}", stdout);
+}
+
+#[test]
+fn load_errors_name_the_file_once() {
+    let dir = tempdir().expect("temp dir");
+    let base = dir.path().join("Strings.restext");
+    let broken = dir.path().join("Strings.fr.json");
+    fs::write(&base, "lblHello=Hello\r\n").expect("write base");
+    fs::write(
+        &broken,
+        "{\n  \"lblHello\": \"Bonjour\"\n  \"lblBye\": \"Au revoir\"\n}\n",
+    )
+    .expect("write broken");
+    let broken = broken.to_string_lossy().into_owned();
+
+    let output = run_cirup(&["lint", &base.to_string_lossy(), &broken]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = stderr_string(&output);
+    assert_eq!(stderr.matches(broken.as_str()).count(), 1, "{}", stderr);
+    assert!(stderr.contains("at line 3 column 3"), "{}", stderr);
+}
+
+#[test]
+fn stale_fails_on_missing_input_without_touching_the_lock() {
+    let temp = tempdir().expect("tempdir");
+    let base = temp.path().join("Strings.restext");
+    let target = temp.path().join("Strings.fr.restext");
+    let missing = temp.path().join("Missing.restext");
+    fs::write(&base, "lblBoat=I'm on a boat.\r\n").expect("write base");
+    fs::write(&target, "lblBoat=Je suis sur un bateau\r\n").expect("write target");
+
+    let base = base.to_string_lossy();
+    let target = target.to_string_lossy();
+    let missing = missing.to_string_lossy();
+    let output = run_cirup(&["stale", &base, &target, "--update"]);
+    assert_eq!(output.status.code(), Some(0), "stderr: {}", stderr_string(&output));
+    let lock_file = format!("{target}.cirup.lock");
+    let lock = fs::read_to_string(&lock_file).expect("read lock");
+
+    for (base, target) in [(&missing, &target), (&base, &missing)] {
+        let output = run_cirup(&["--check", "stale", base, target, "--lock", &lock_file]);
+        assert_eq!(output.status.code(), Some(1));
+        assert!(stderr_string(&output).contains("Missing.restext"));
+
+        let output = run_cirup(&["stale", base, target, "--lock", &lock_file, "--update"]);
+        assert_eq!(output.status.code(), Some(1));
+    }
+    assert_eq!(fs::read_to_string(&lock_file).expect("read lock"), lock);
+}
@@ -0,0 +1,530 @@
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use serde::Deserialize;
+
+use crate::file::OutputEncoding;
+use crate::query::{
+    CirupEngine, CirupQuery, QueryExecutionReport, QueryOperation, QueryResult, QueryRunOptions, render_records,
+};
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * Batch execution of a manifest of operations in one process. Operations are grouped in
+ * waves: an operation runs after every earlier operation that writes one of its files or
+ * reads its output, and the operations of a wave run in parallel, one engine per worker.
+ * Files are compared by their path relative to the manifest directory, with `.` and `..` removed.
+ */
+
+/// Removes the `.` and `..` components of `path` without touching the file system,
+/// as output files may not exist yet; symbolic links are not resolved.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(component),
+            },
+            _ => normalized.push(component),
+        }
+    }
+
+    if normalized.as_os_str().is_empty() {
+        normalized.push(Component::CurDir);
+    }
+
+    normalized
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "operation", rename_all = "kebab-case")]
+pub enum ManifestOperation {
+    Print {
+        file: String,
+        output: Option<String>,
+    },
+    Convert {
+        file: String,
+        output: String,
+    },
+    Sort {
+        file: String,
+        output: Option<String>,
+    },
+    Diff {
+        file1: String,
+        file2: String,
+        output: Option<String>,
+        #[serde(default)]
+        show_changes: bool,
+    },
+    Merge {
+        file1: String,
+        file2: String,
+        output: Option<String>,
+    },
+    Intersect {
+        file1: String,
+        file2: String,
+        output: Option<String>,
+    },
+    Subtract {
+        file1: String,
+        file2: String,
+        output: Option<String>,
+    },
+}
+
+impl ManifestOperation {
+    fn operation(&self) -> QueryOperation {
+        match self {
+            ManifestOperation::Print { .. } => QueryOperation::Print,
+            ManifestOperation::Convert { .. } => QueryOperation::Convert,
+            ManifestOperation::Sort { .. } => QueryOperation::Sort,
+            ManifestOperation::Diff {
+                show_changes: false, ..
+            } => QueryOperation::Diff,
+            ManifestOperation::Diff { show_changes: true, .. } => QueryOperation::Change,
+            ManifestOperation::Merge { .. } => QueryOperation::Merge,
+            ManifestOperation::Intersect { .. } => QueryOperation::Intersect,
+            ManifestOperation::Subtract { .. } => QueryOperation::Subtract,
+        }
+    }
+
+    fn input_files(&self) -> Vec<&str> {
+        match self {
+            ManifestOperation::Print { file, .. }
+            | ManifestOperation::Convert { file, .. }
+            | ManifestOperation::Sort { file, .. } => vec![file],
+            ManifestOperation::Diff { file1, file2, .. }
+            | ManifestOperation::Merge { file1, file2, .. }
+            | ManifestOperation::Intersect { file1, file2, .. }
+            | ManifestOperation::Subtract { file1, file2, .. } => vec![file1, file2],
+        }
+    }
+
+    fn output_file(&self) -> Option<&str> {
+        match self {
+            ManifestOperation::Convert { output, .. } => Some(output),
+            ManifestOperation::Sort { file, output } => Some(output.as_deref().unwrap_or(file)),
+            ManifestOperation::Print { output, .. }
+            | ManifestOperation::Diff { output, .. }
+            | ManifestOperation::Merge { output, .. }
+            | ManifestOperation::Intersect { output, .. }
+            | ManifestOperation::Subtract { output, .. } => output.as_deref(),
+        }
+    }
+
+    fn resolve(&mut self, base: &Path) {
+        let resolve = |file: &mut String| {
+            *file = normalize_path(&base.join(file.as_str())).to_string_lossy().into_owned();
+        };
+
+        match self {
+            ManifestOperation::Print { file, output } | ManifestOperation::Sort { file, output } => {
+                resolve(file);
+                if let Some(output) = output {
+                    resolve(output);
+                }
+            }
+            ManifestOperation::Convert { file, output } => {
+                resolve(file);
+                resolve(output);
+            }
+            ManifestOperation::Diff {
+                file1, file2, output, ..
+            }
+            | ManifestOperation::Merge { file1, file2, output }
+            | ManifestOperation::Intersect { file1, file2, output }
+            | ManifestOperation::Subtract { file1, file2, output } => {
+                resolve(file1);
+                resolve(file2);
+                if let Some(output) = output {
+                    resolve(output);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub operations: Vec<ManifestOperation>,
+}
+
+impl Manifest {
+    /// Parses a JSON or TOML manifest, depending on the extension of `filename`.
+    pub fn parse(text: &str, filename: &str) -> Result<Self, io::Error> {
+        let is_toml = Path::new(filename)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"));
+
+        if is_toml {
+            toml::from_str(text).map_err(|e| io::Error::other(format!("{}: {}", filename, e)))
+        } else {
+            serde_json::from_str(text).map_err(|e| io::Error::other(format!("{}: {}", filename, e)))
+        }
+    }
+
+    /// Loads a manifest, resolving relative paths against the directory of the manifest and normalizing them.
+    pub fn load(filename: &str) -> Result<Self, io::Error> {
+        let text = fs::read_to_string(filename).map_err(|e| io::Error::other(format!("{}: {}", filename, e)))?;
+        let mut manifest = Self::parse(&text, filename)?;
+
+        if let Some(base) = Path::new(filename).parent() {
+            for operation in &mut manifest.operations {
+                operation.resolve(base);
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Returns the wave of each operation: operations of the same wave do not depend on each other.
+    pub fn waves(&self) -> Vec<usize> {
+        let mut waves: Vec<usize> = Vec::with_capacity(self.operations.len());
+
+        // `./a.resx` and `a.resx` are the same file
+        let files = self
+            .operations
+            .iter()
+            .map(|operation| {
+                let inputs = operation
+                    .input_files()
+                    .into_iter()
+                    .map(|file| normalize_path(Path::new(file)))
+                    .collect::<Vec<_>>();
+                let output = operation.output_file().map(|file| normalize_path(Path::new(file)));
+                (inputs, output)
+            })
+            .collect::<Vec<_>>();
+
+        for (index, (inputs, output)) in files.iter().enumerate() {
+            let wave = files[..index]
+                .iter()
+                .zip(&waves)
+                .filter(|((earlier_inputs, earlier_output), _)| {
+                    earlier_output.as_ref().is_some_and(|earlier_output| {
+                        inputs.contains(earlier_output) || output.as_ref() == Some(earlier_output)
+                    }) || output.as_ref().is_some_and(|output| earlier_inputs.contains(output))
+                })
+                .map(|(_, wave)| wave + 1)
+                .max()
+                .unwrap_or(0);
+
+            waves.push(wave);
+        }
+
+        waves
+    }
+}
+
+/// Runs `operation` on `engine` and writes its output file, so that the next waves can read it.
+/// The operation fails without writing its output when an input file cannot be loaded.
+/// Returns the engine for the next operation of the worker.
+fn execute(
+    operation: &ManifestOperation,
+    engine: CirupEngine,
+    touch: bool,
+    output_encoding: OutputEncoding,
+    options: &QueryRunOptions,
+) -> (Result<QueryExecutionReport, io::Error>, CirupEngine) {
+    let input_files = operation.input_files();
+    let output_file = operation.output_file();
+    let query_operation = operation.operation();
+    let options = options
+        .clone()
+        .with_context(query_operation.name(), &input_files, output_file);
+
+    let query = match CirupQuery::try_with_engine(
+        engine,
+        query_operation.query(),
+        input_files[0],
+        input_files.get(1).copied(),
+        None,
+    ) {
+        Ok(query) => query,
+        Err((e, engine)) => return (Err(e), engine),
+    };
+    let report = query
+        .evaluate_with_options(output_file, touch, output_encoding, &options)
+        .and_then(|result| {
+            result.write_pending()?;
+            Ok(result.report)
+        });
+
+    (report, query.into_engine())
+}
+
+type OperationResult = (usize, Result<QueryExecutionReport, io::Error>);
+
+fn execute_wave(
+    operations: &[ManifestOperation],
+    indexes: &[usize],
+    jobs: usize,
+    touch: bool,
+    output_encoding: OutputEncoding,
+    options: &QueryRunOptions,
+) -> Vec<OperationResult> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(indexes.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(indexes.len()) {
+            scope.spawn(|| {
+                let mut engine = CirupEngine::new();
+
+                while let Some(&index) = indexes.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let (report, returned) = execute(&operations[index], engine, touch, output_encoding, options);
+                    engine = returned;
+                    results.lock().expect("batch results lock").push((index, report));
+                }
+            });
+        }
+    });
+
+    results.into_inner().expect("batch results lock")
+}
+
+pub struct BatchQuery {
+    manifest_file: String,
+    jobs: Option<NonZeroUsize>,
+}
+
+/// Runs the operations listed in `manifest_file`.
+pub fn query_batch(manifest_file: &str) -> BatchQuery {
+    BatchQuery {
+        manifest_file: manifest_file.to_owned(),
+        jobs: None,
+    }
+}
+
+impl BatchQuery {
+    /// Sets the number of operations run in parallel, the available parallelism by default.
+    #[must_use]
+    pub fn jobs(mut self, jobs: Option<usize>) -> Self {
+        self.jobs = jobs.and_then(NonZeroUsize::new);
+        self
+    }
+
+    /// Runs the operations and returns their reports, in manifest order.
+    /// The filters, limit and dry-run options apply to every operation.
+    pub fn run(
+        &self,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<Vec<QueryExecutionReport>, io::Error> {
+        let manifest = Manifest::load(&self.manifest_file)?;
+        let waves = manifest.waves();
+        let jobs = self
+            .jobs
+            .or_else(|| thread::available_parallelism().ok())
+            .map_or(1, NonZeroUsize::get);
+
+        let options = QueryRunOptions {
+            count_only: false,
+            summary: false,
+            ..options.clone()
+        };
+
+        let mut reports = Vec::with_capacity(manifest.operations.len());
+        reports.resize_with(manifest.operations.len(), || None);
+
+        for wave in 0..waves.iter().max().map_or(0, |wave| wave + 1) {
+            let indexes = (0..waves.len())
+                .filter(|index| waves[*index] == wave)
+                .collect::<Vec<_>>();
+            debug!("running wave {} with {} operations", wave, indexes.len());
+
+            for (index, report) in execute_wave(&manifest.operations, &indexes, jobs, touch, output_encoding, &options)
+            {
+                reports[index] = Some(report);
+            }
+        }
+
+        reports.into_iter().flatten().collect::<Result<Vec<_>, _>>()
+    }
+
+    /// Runs the operations, writing their output files, and returns their reports as rows
+    /// with a report aggregating them. Unlike other queries, there are no pending writes left.
+    pub fn run_with_options(
+        &self,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<QueryExecutionReport>, io::Error> {
+        let reports = self.run(touch, output_encoding, options)?;
+        let rendered = render_records(&reports, options.output_format);
+
+        let mut input_files = options.input_files.clone();
+        for report in &reports {
+            for input_file in &report.input_files {
+                if !input_files.contains(input_file) {
+                    input_files.push(input_file.clone());
+                }
+            }
+        }
+
+        let report = QueryExecutionReport {
+            operation: options.operation_name.clone(),
+            result_kind: String::from("report"),
+            input_files,
+            output_file: None,
+            matched_count: reports.len(),
+            filtered_count: reports.len(),
+            output_count: reports.iter().filter(|report| report.indicates_change()).count(),
+            truncated: false,
+            dry_run: options.dry_run,
+            check: options.check,
+            would_write: reports.iter().any(|report| report.would_write),
+            wrote_output: reports.iter().any(|report| report.wrote_output),
+            change_detected: reports.iter().any(QueryExecutionReport::indicates_change),
+            merge: None,
+        };
+
+        Ok(QueryResult {
+            rows: reports,
+            rendered,
+            report,
+            pending_writes: Vec::new(),
+        })
+    }
+}
+
+#[test]
+fn test_manifest_parse_json_and_toml() {
+    let json = r#"{"operations": [
+        {"operation": "sort", "file": "a.resx"},
+        {"operation": "diff", "file1": "a.resx", "file2": "a.fr.resx", "show_changes": true}
+    ]}"#;
+    let toml = r#"
+        [[operations]]
+        operation = "sort"
+        file = "a.resx"
+
+        [[operations]]
+        operation = "diff"
+        file1 = "a.resx"
+        file2 = "a.fr.resx"
+        show_changes = true
+    "#;
+
+    let expected = Manifest {
+        operations: vec![
+            ManifestOperation::Sort {
+                file: String::from("a.resx"),
+                output: None,
+            },
+            ManifestOperation::Diff {
+                file1: String::from("a.resx"),
+                file2: String::from("a.fr.resx"),
+                output: None,
+                show_changes: true,
+            },
+        ],
+    };
+
+    assert_eq!(Manifest::parse(json, "cirup.json").expect("json manifest"), expected);
+    assert_eq!(Manifest::parse(toml, "cirup.toml").expect("toml manifest"), expected);
+    assert!(Manifest::parse(r#"{"operations": [{"operation": "explode"}]}"#, "cirup.json").is_err());
+}
+
+#[test]
+fn test_manifest_waves_order_dependent_operations() {
+    let manifest = Manifest::parse(
+        r#"{"operations": [
+            {"operation": "sort", "file": "a.resx"},
+            {"operation": "sort", "file": "b.resx"},
+            {"operation": "merge", "file1": "a.resx", "file2": "b.resx", "output": "c.resx"},
+            {"operation": "convert", "file": "d.resx", "output": "d.json"},
+            {"operation": "convert", "file": "c.resx", "output": "c.json"},
+            {"operation": "sort", "file": "d.resx"}
+        ]}"#,
+        "cirup.json",
+    )
+    .expect("manifest");
+
+    assert_eq!(manifest.waves(), vec![0, 0, 1, 0, 2, 1]);
+}
+
+#[test]
+fn test_manifest_waves_compare_normalized_paths() {
+    let manifest = Manifest::parse(
+        r#"{"operations": [
+            {"operation": "sort", "file": "./a.resx"},
+            {"operation": "convert", "file": "a.resx", "output": "out/a.json"},
+            {"operation": "print", "file": "out/../out/./a.json"}
+        ]}"#,
+        "cirup.json",
+    )
+    .expect("manifest");
+
+    assert_eq!(manifest.waves(), vec![0, 1, 2]);
+    assert_eq!(normalize_path(Path::new("./a.resx")), PathBuf::from("a.resx"));
+    assert_eq!(normalize_path(Path::new("../x/../a.resx")), PathBuf::from("../a.resx"));
+    assert_eq!(normalize_path(Path::new("/x/../../a.resx")), PathBuf::from("/a.resx"));
+    assert_eq!(normalize_path(Path::new(".")), PathBuf::from("."));
+}
+
+#[test]
+fn test_batch_runs_operations_and_reports_in_order() {
+    let files = TempFiles::new();
+
+    fs::write(files.path("a.restext"), "lblB=B\r\nlblA=A\r\n").expect("failed to write resources");
+    fs::write(files.path("a.fr.restext"), "lblA=A fr\r\n").expect("failed to write resources");
+    fs::write(
+        files.path("cirup.toml"),
+        r#"
+            [[operations]]
+            operation = "sort"
+            file = "a.restext"
+
+            [[operations]]
+            operation = "convert"
+            file = "a.restext"
+            output = "a.json"
+
+            [[operations]]
+            operation = "diff"
+            file1 = "a.restext"
+            file2 = "a.fr.restext"
+        "#,
+    )
+    .expect("failed to write manifest");
+
+    let reports = query_batch(&files.path("cirup.toml"))
+        .jobs(Some(2))
+        .run(false, OutputEncoding::Utf8NoBom, &QueryRunOptions::default())
+        .expect("batch failed");
+
+    let operations = reports
+        .iter()
+        .map(|report| report.operation.as_deref().unwrap_or_default())
+        .collect::<Vec<_>>();
+    assert_eq!(operations, vec!["file-sort", "file-convert", "file-diff"]);
+    assert!(reports[0].wrote_output);
+    assert_eq!(reports[2].output_count, 1);
+    assert_eq!(
+        fs::read_to_string(files.path("a.restext")).expect("sorted file"),
+        "lblA=A\r\nlblB=B\r\n"
+    );
+    assert!(
+        fs::read_to_string(files.path("a.json"))
+            .expect("converted file")
+            .contains("lblB")
+    );
+}
@@ -0,0 +1,309 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use serde::Serialize;
+
+use crate::Resource;
+use crate::file::load_resources;
+use crate::query::{QueryRecord, QueryResult, QueryRunOptions, RecordOutput, evaluate_records};
+
+/*
+ * Change log between two versions of a resource file: every key is added, removed, modified or renamed.
+ * A removed and an added key with the same non-empty value are taken as a rename.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangelogKind {
+    Added,
+    Removed,
+    Modified,
+    Renamed,
+}
+
+impl ChangelogKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ChangelogKind::Added => "added",
+            ChangelogKind::Removed => "removed",
+            ChangelogKind::Modified => "modified",
+            ChangelogKind::Renamed => "renamed",
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            ChangelogKind::Added => "Added",
+            ChangelogKind::Removed => "Removed",
+            ChangelogKind::Modified => "Modified",
+            ChangelogKind::Renamed => "Renamed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangelogEntry {
+    /// The key in the new file, or the removed key.
+    pub name: String,
+    pub kind: ChangelogKind,
+    /// The key in the old file of renamed keys.
+    pub old_name: Option<String>,
+    /// The value in the old file of removed and modified keys.
+    pub old_value: Option<String>,
+    /// The value in the new file, except for removed keys.
+    pub value: Option<String>,
+}
+
+impl QueryRecord for ChangelogEntry {
+    const COLUMNS: &'static [&'static str] = &["name", "kind", "old_name", "old_value", "value"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.kind.as_str().to_owned(),
+            self.old_name.clone().unwrap_or_default(),
+            self.old_value.clone().unwrap_or_default(),
+            self.value.clone().unwrap_or_default(),
+        ]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.name
+    }
+
+    fn filter_value(&self) -> &str {
+        self.value.as_deref().or(self.old_value.as_deref()).unwrap_or_default()
+    }
+}
+
+/// Classifies the keys of `old` and `new`, in the order of `new` followed by the removed keys in the order of `old`.
+pub fn changelog_resources(old: &[Resource], new: &[Resource]) -> Vec<ChangelogEntry> {
+    let mut old_values = HashMap::with_capacity(old.len());
+    for resource in old {
+        old_values
+            .entry(resource.name.as_str())
+            .or_insert(resource.value.as_str());
+    }
+    let new_keys = new
+        .iter()
+        .map(|resource| resource.name.as_str())
+        .collect::<HashSet<_>>();
+
+    // removed keys, in their order, that an added key with the same value can claim as a rename
+    let mut removed = old
+        .iter()
+        .filter(|resource| !new_keys.contains(resource.name.as_str()))
+        .map(Some)
+        .collect::<Vec<_>>();
+
+    let mut entries = Vec::new();
+    let mut seen = HashSet::new();
+    for resource in new {
+        if !seen.insert(resource.name.as_str()) {
+            continue;
+        }
+
+        let entry = |kind: ChangelogKind, old_name: Option<&str>, old_value: Option<&str>| ChangelogEntry {
+            name: resource.name.clone(),
+            kind,
+            old_name: old_name.map(str::to_owned),
+            old_value: old_value.map(str::to_owned),
+            value: Some(resource.value.clone()),
+        };
+
+        match old_values.get(resource.name.as_str()) {
+            Some(value) if *value == resource.value => {}
+            Some(value) => entries.push(entry(ChangelogKind::Modified, None, Some(value))),
+            None => {
+                let renamed = removed.iter_mut().find(|candidate| {
+                    candidate.is_some_and(|candidate| !resource.value.is_empty() && candidate.value == resource.value)
+                });
+                match renamed.and_then(Option::take) {
+                    Some(old_resource) => entries.push(entry(ChangelogKind::Renamed, Some(&old_resource.name), None)),
+                    None => entries.push(entry(ChangelogKind::Added, None, None)),
+                }
+            }
+        }
+    }
+
+    let mut removed_keys = HashSet::new();
+    entries.extend(
+        removed
+            .into_iter()
+            .flatten()
+            .filter(|resource| removed_keys.insert(resource.name.as_str()))
+            .map(|resource| ChangelogEntry {
+                name: resource.name.clone(),
+                kind: ChangelogKind::Removed,
+                old_name: None,
+                old_value: Some(resource.value.clone()),
+                value: None,
+            }),
+    );
+
+    entries
+}
+
+/// Escapes the Markdown syntax of a value, on a single line.
+fn markdown_text(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\r' => {}
+            '\n' => output.push(' '),
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#' => {
+                output.push('\\');
+                output.push(c);
+            }
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+/// Renders the entries as Markdown sections by kind, for release notes and translator briefs.
+pub fn render_markdown(entries: &[ChangelogEntry]) -> String {
+    if entries.is_empty() {
+        return String::from("No changes.\n");
+    }
+
+    let mut output = String::new();
+    for kind in [
+        ChangelogKind::Added,
+        ChangelogKind::Modified,
+        ChangelogKind::Renamed,
+        ChangelogKind::Removed,
+    ] {
+        let kind_entries = entries.iter().filter(|entry| entry.kind == kind).collect::<Vec<_>>();
+        if kind_entries.is_empty() {
+            continue;
+        }
+
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&format!("## {} ({})\n\n", kind.heading(), kind_entries.len()));
+
+        for entry in kind_entries {
+            let value = markdown_text(entry.value.as_deref().unwrap_or_default());
+            let old_value = markdown_text(entry.old_value.as_deref().unwrap_or_default());
+            let line = match kind {
+                ChangelogKind::Added => format!("- `{}`: {}\n", entry.name, value),
+                ChangelogKind::Modified => format!("- `{}`: {} → {}\n", entry.name, old_value, value),
+                ChangelogKind::Renamed => format!(
+                    "- `{}` → `{}`: {}\n",
+                    entry.old_name.as_deref().unwrap_or_default(),
+                    entry.name,
+                    value
+                ),
+                ChangelogKind::Removed => format!("- `{}`: {}\n", entry.name, old_value),
+            };
+            output.push_str(&line);
+        }
+    }
+
+    output
+}
+
+pub struct Changelog {
+    old: String,
+    new: String,
+}
+
+/// The change log from `old` to `new`, two versions of a resource file.
+pub fn changelog(old: &str, new: &str) -> Changelog {
+    Changelog {
+        old: old.to_owned(),
+        new: new.to_owned(),
+    }
+}
+
+impl Changelog {
+    pub fn run(&self) -> Result<Vec<ChangelogEntry>, io::Error> {
+        let old = load_resources(&self.old)?;
+        let new = load_resources(&self.new)?;
+        Ok(changelog_resources(&old, &new))
+    }
+
+    /// Returns the entries, and their Markdown rendering to write to `markdown_file`.
+    pub fn evaluate_with_options(
+        &self,
+        markdown_file: Option<&str>,
+        touch: bool,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<ChangelogEntry>, io::Error> {
+        let entries = self.run()?;
+        info!(
+            "changelog: {} change(s) from {} to {}",
+            entries.len(),
+            self.old,
+            self.new
+        );
+
+        let output = markdown_file.map(|path| RecordOutput {
+            path,
+            render: Box::new(|entries: &[ChangelogEntry]| Ok(render_markdown(entries).into_bytes())),
+            print_records: true,
+        });
+
+        evaluate_records(entries, "changelog-entry", output, touch, options)
+    }
+}
+
+#[test]
+fn test_changelog_classifies_keys() {
+    let old = vec![
+        Resource::new("lblTitle", "Title"),
+        Resource::new("lblSave", "Save"),
+        Resource::new("lblOpen", "Open"),
+        Resource::new("lblGone", "Gone"),
+    ];
+    let new = vec![
+        Resource::new("lblTitle", "Main title"),
+        Resource::new("menuOpen", "Open"),
+        Resource::new("lblSave", "Save"),
+        Resource::new("lblNew", "New"),
+    ];
+
+    let entries = changelog_resources(&old, &new);
+    let kinds = entries
+        .iter()
+        .map(|entry| (entry.name.as_str(), entry.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            ("lblTitle", ChangelogKind::Modified),
+            ("menuOpen", ChangelogKind::Renamed),
+            ("lblNew", ChangelogKind::Added),
+            ("lblGone", ChangelogKind::Removed),
+        ]
+    );
+    assert_eq!(entries[0].old_value.as_deref(), Some("Title"));
+    assert_eq!(entries[1].old_name.as_deref(), Some("lblOpen"));
+    assert_eq!(entries[3].value, None);
+}
+
+#[test]
+fn test_changelog_renders_markdown() {
+    let old = vec![Resource::new("lblTitle", "Title"), Resource::new("lblOpen", "Open")];
+    let new = vec![
+        Resource::new("lblTitle", "*Main* title"),
+        Resource::new("menuOpen", "Open"),
+        Resource::new("lblNew", "New"),
+    ];
+
+    let markdown = render_markdown(&changelog_resources(&old, &new));
+    assert_eq!(
+        markdown,
+        concat!(
+            "## Added (1)\n\n",
+            "- `lblNew`: New\n",
+            "\n## Modified (1)\n\n",
+            "- `lblTitle`: Title → \\*Main\\* title\n",
+            "\n## Renamed (1)\n\n",
+            "- `lblOpen` → `menuOpen`: Open\n",
+        )
+    );
+    assert_eq!(render_markdown(&[]), "No changes.\n");
+}
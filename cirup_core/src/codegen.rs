@@ -0,0 +1,517 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+
+use crate::Resource;
+use crate::file::{OutputEncoding, encode_utf8_owned, load_error, load_resource_file};
+use crate::placeholder::extract_placeholders;
+use crate::query::{QueryResult, QueryRunOptions, RecordOutput, evaluate_records};
+use crate::resx::push_escaped_xml_text;
+
+/*
+ * Strongly-typed resource accessors:
+ * C#: a static class with one property per key, backed by System.Resources.ResourceManager
+ * TypeScript: an `as const` key map and a parameter type per key derived from its placeholders
+ */
+
+const GENERATED_HEADER: &str =
+    "This code was generated by cirup. Changes to this file will be lost when the code is regenerated.";
+
+const CSHARP_KEYWORDS: &[&str] = &[
+    "abstract",
+    "as",
+    "base",
+    "bool",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "checked",
+    "class",
+    "const",
+    "continue",
+    "decimal",
+    "default",
+    "delegate",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "event",
+    "explicit",
+    "extern",
+    "false",
+    "finally",
+    "fixed",
+    "float",
+    "for",
+    "foreach",
+    "goto",
+    "if",
+    "implicit",
+    "in",
+    "int",
+    "interface",
+    "internal",
+    "is",
+    "lock",
+    "long",
+    "namespace",
+    "new",
+    "null",
+    "object",
+    "operator",
+    "out",
+    "override",
+    "params",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "ref",
+    "return",
+    "sbyte",
+    "sealed",
+    "short",
+    "sizeof",
+    "stackalloc",
+    "static",
+    "string",
+    "struct",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "uint",
+    "ulong",
+    "unchecked",
+    "unsafe",
+    "ushort",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+];
+
+/// Members of the generated C# class that resource keys must not collide with.
+const CSHARP_RESERVED_MEMBERS: &[&str] = &["ResourceManager", "Culture"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodegenLanguage {
+    CSharp,
+    TypeScript,
+}
+
+impl CodegenLanguage {
+    /// Infers the language from the extension of the output file.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let extension = Path::new(filename).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "cs" => Some(CodegenLanguage::CSharp),
+            "ts" | "mts" | "cts" => Some(CodegenLanguage::TypeScript),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CodegenOptions {
+    /// Class name for C#, or constant name for TypeScript; defaults to the input file name up to the first dot.
+    pub class_name: Option<String>,
+    /// C# namespace; the class is emitted in the global namespace when unset.
+    pub namespace: Option<String>,
+    /// Base name passed to the C# `ResourceManager`; defaults to `<namespace>.<class>`.
+    pub resource_name: Option<String>,
+    /// Emit a `public` instead of an `internal` C# class.
+    pub public: bool,
+}
+
+/// Converts `name` to a valid identifier, replacing invalid characters with underscores.
+fn sanitize_identifier(name: &str) -> String {
+    let mut identifier = name
+        .chars()
+        .map(|ch| if ch.is_alphanumeric() || ch == '_' { ch } else { '_' })
+        .collect::<String>();
+
+    if identifier.is_empty() || identifier.starts_with(|ch: char| ch.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+
+    identifier
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|ch: char| ch.is_ascii_digit())
+        && name.chars().all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '$')
+}
+
+fn default_class_name(file: &str) -> String {
+    let stem = Path::new(file)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.split('.').next())
+        .unwrap_or("Resources");
+    sanitize_identifier(stem)
+}
+
+fn push_string_literal(output: &mut String, value: &str) {
+    output.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            ch if ch.is_control() => {
+                let _ = write!(output, "\\u{:04x}", u32::from(ch));
+            }
+            ch => output.push(ch),
+        }
+    }
+    output.push('"');
+}
+
+fn push_summary(output: &mut String, indent: &str, value: &str) {
+    let _ = writeln!(output, "{indent}/// <summary>");
+    let mut lines = value.lines();
+    let first = lines.next().unwrap_or_default();
+    let _ = write!(output, "{indent}///   Looks up a localized string similar to ");
+    push_escaped_xml_text(output, first);
+    output.push('\n');
+    for line in lines {
+        let _ = write!(output, "{indent}///   ");
+        push_escaped_xml_text(output, line);
+        output.push('\n');
+    }
+    let _ = writeln!(output, "{indent}/// </summary>");
+}
+
+/// Returns the C# property names of `resources`, skipping keys that collide after sanitizing.
+/// A key named after the class gets a trailing underscore, as members cannot have the name of their type (CS0542).
+fn csharp_members<'a>(resources: &'a [Resource], class_name: &str) -> Vec<(String, &'a Resource)> {
+    let mut used = CSHARP_RESERVED_MEMBERS
+        .iter()
+        .map(|member| (*member).to_owned())
+        .collect::<HashSet<_>>();
+    let mut members = Vec::with_capacity(resources.len());
+
+    for resource in resources {
+        let mut identifier = sanitize_identifier(&resource.name);
+        if identifier == class_name {
+            warn!(
+                "renaming key '{}' to '{}_': members cannot have the name of the class",
+                resource.name, identifier
+            );
+            identifier.push('_');
+        }
+
+        if !used.insert(identifier.clone()) {
+            warn!(
+                "skipping key '{}': identifier '{}' is already used",
+                resource.name, identifier
+            );
+            continue;
+        }
+
+        if CSHARP_KEYWORDS.contains(&identifier.as_str()) {
+            identifier.insert(0, '@');
+        }
+
+        members.push((identifier, resource));
+    }
+
+    members
+}
+
+pub fn generate_csharp(resources: &[Resource], class_name: &str, options: &CodegenOptions) -> String {
+    let access = if options.public { "public" } else { "internal" };
+    let resource_name = options
+        .resource_name
+        .clone()
+        .unwrap_or_else(|| match &options.namespace {
+            Some(namespace) => format!("{namespace}.{class_name}"),
+            None => class_name.to_owned(),
+        });
+    let indent = if options.namespace.is_some() { "    " } else { "" };
+
+    let mut output = String::with_capacity(1024 + resources.len() * 256);
+    output.push_str("//------------------------------------------------------------------------------\n");
+    output.push_str("// <auto-generated>\n");
+    let _ = writeln!(output, "//     {GENERATED_HEADER}");
+    output.push_str("// </auto-generated>\n");
+    output.push_str("//------------------------------------------------------------------------------\n\n");
+
+    if let Some(namespace) = &options.namespace {
+        let _ = writeln!(output, "namespace {namespace}\n{{");
+    }
+
+    let _ = writeln!(output, "{indent}using System.Globalization;");
+    let _ = writeln!(output, "{indent}using System.Resources;\n");
+    let _ = writeln!(output, "{indent}{access} static class {class_name}\n{indent}{{");
+    let _ = writeln!(output, "{indent}    private static ResourceManager resourceManager;\n");
+    let _ = writeln!(output, "{indent}    {access} static ResourceManager ResourceManager");
+    let _ = writeln!(output, "{indent}    {{");
+    let _ = writeln!(output, "{indent}        get");
+    let _ = writeln!(output, "{indent}        {{");
+    let _ = writeln!(output, "{indent}            if (resourceManager == null)");
+    let _ = writeln!(output, "{indent}            {{");
+    let _ = write!(output, "{indent}                resourceManager = new ResourceManager(");
+    push_string_literal(&mut output, &resource_name);
+    let _ = writeln!(output, ", typeof({class_name}).Assembly);");
+    let _ = writeln!(output, "{indent}            }}\n");
+    let _ = writeln!(output, "{indent}            return resourceManager;");
+    let _ = writeln!(output, "{indent}        }}");
+    let _ = writeln!(output, "{indent}    }}\n");
+    let _ = writeln!(
+        output,
+        "{indent}    {access} static CultureInfo Culture {{ get; set; }}"
+    );
+
+    let member_indent = format!("{indent}    ");
+    for (identifier, resource) in csharp_members(resources, class_name) {
+        output.push('\n');
+        push_summary(&mut output, &member_indent, &resource.value);
+        let _ = write!(
+            output,
+            "{member_indent}{access} static string {identifier} => ResourceManager.GetString("
+        );
+        push_string_literal(&mut output, &resource.name);
+        output.push_str(", Culture);\n");
+    }
+
+    let _ = writeln!(output, "{indent}}}");
+
+    if options.namespace.is_some() {
+        output.push_str("}\n");
+    }
+
+    output
+}
+
+/// Returns the TypeScript parameters of `value` as (name, type) pairs.
+/// Brace placeholders are named after their content, printf conversions after their position.
+fn typescript_params(value: &str) -> Vec<(String, &'static str)> {
+    let mut params: Vec<(String, &'static str)> = Vec::new();
+    let mut position = 0usize;
+
+    for placeholder in extract_placeholders(value) {
+        let (name, ty) = if let Some(inner) = placeholder.strip_prefix('{') {
+            let inner = inner.trim_start_matches('{').trim_end_matches('}');
+            let name = inner.split([':', ',']).next().unwrap_or(inner).trim();
+            (name.to_owned(), "string | number")
+        } else {
+            let digits = placeholder[1..]
+                .split_once('$')
+                .map(|(digits, _)| digits)
+                .filter(|digits| digits.chars().all(|ch| ch.is_ascii_digit()));
+            let name = match digits.and_then(|digits| digits.parse::<usize>().ok()) {
+                Some(index) => index.saturating_sub(1).to_string(),
+                None => {
+                    position += 1;
+                    (position - 1).to_string()
+                }
+            };
+            let ty = match placeholder.chars().last() {
+                Some('s' | '@' | 'c') => "string",
+                _ => "number",
+            };
+            (name, ty)
+        };
+
+        if !params.iter().any(|(existing, _)| *existing == name) {
+            params.push((name, ty));
+        }
+    }
+
+    params
+}
+
+fn push_property_name(output: &mut String, name: &str) {
+    if is_identifier(name) {
+        output.push_str(name);
+    } else {
+        push_string_literal(output, name);
+    }
+}
+
+pub fn generate_typescript(resources: &[Resource], name: &str) -> String {
+    let mut output = String::with_capacity(512 + resources.len() * 128);
+    let _ = writeln!(
+        output,
+        "// <auto-generated>\n// {GENERATED_HEADER}\n// </auto-generated>\n"
+    );
+
+    let _ = writeln!(output, "export const {name} = {{");
+    for resource in resources {
+        output.push_str("  ");
+        push_property_name(&mut output, &resource.name);
+        output.push_str(": ");
+        push_string_literal(&mut output, &resource.name);
+        output.push_str(",\n");
+    }
+    output.push_str("} as const;\n\n");
+
+    let _ = writeln!(
+        output,
+        "export type {name}Key = (typeof {name})[keyof typeof {name}];\n"
+    );
+
+    let _ = writeln!(output, "export interface {name}Params {{");
+    for resource in resources {
+        output.push_str("  ");
+        push_property_name(&mut output, &resource.name);
+        output.push_str(": {");
+        let params = typescript_params(&resource.value);
+        for (index, (param, ty)) in params.iter().enumerate() {
+            output.push_str(if index == 0 { " " } else { "; " });
+            push_property_name(&mut output, param);
+            let _ = write!(output, ": {ty}");
+        }
+        output.push_str(if params.is_empty() { "};\n" } else { " };\n" });
+    }
+    output.push_str("}\n");
+
+    output
+}
+
+pub struct CodegenQuery {
+    file: String,
+    language: CodegenLanguage,
+    options: CodegenOptions,
+}
+
+/// Generates accessors for the keys of `file`.
+pub fn query_codegen(file: &str, language: CodegenLanguage, options: CodegenOptions) -> CodegenQuery {
+    CodegenQuery {
+        file: file.to_owned(),
+        language,
+        options,
+    }
+}
+
+impl CodegenQuery {
+    pub fn generate(&self, resources: &[Resource]) -> String {
+        let class_name = self
+            .options
+            .class_name
+            .clone()
+            .unwrap_or_else(|| default_class_name(&self.file));
+
+        match self.language {
+            CodegenLanguage::CSharp => generate_csharp(resources, &class_name, &self.options),
+            CodegenLanguage::TypeScript => generate_typescript(resources, &class_name),
+        }
+    }
+
+    /// Generates the code to write to `out_file`; with `--check`, reports whether it is out of date.
+    pub fn evaluate_with_options(
+        &self,
+        out_file: &str,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<Resource>, io::Error> {
+        // generated code with some keys left out would silently break the code using the others
+        if !options.key_filters.is_empty() || !options.value_filters.is_empty() || options.limit.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "codegen generates every key and cannot be combined with --key-filter, --value-filter or --limit",
+            ));
+        }
+
+        let resources = load_resource_file(&self.file).map_err(|e| load_error(&self.file, e))?;
+
+        let output = RecordOutput {
+            path: out_file,
+            render: Box::new(move |resources: &[Resource]| {
+                Ok(encode_utf8_owned(self.generate(resources), output_encoding))
+            }),
+            print_records: false,
+        };
+
+        evaluate_records(resources, "resource", Some(output), touch, options)
+    }
+}
+
+#[test]
+fn test_generate_csharp_class() {
+    let resources = vec![
+        Resource::new("lblHello", "Hello <b>{0}</b>"),
+        Resource::new("page.title", "Title"),
+        Resource::new("class", "Class"),
+        Resource::new("Resources", "Resources"),
+    ];
+    let options = CodegenOptions {
+        namespace: Some(String::from("MyApp.Properties")),
+        ..CodegenOptions::default()
+    };
+
+    let code = generate_csharp(&resources, "Resources", &options);
+    assert!(code.contains("namespace MyApp.Properties\n{"));
+    assert!(code.contains("    internal static class Resources\n    {"));
+    assert!(code.contains("new ResourceManager(\"MyApp.Properties.Resources\", typeof(Resources).Assembly);"));
+    assert!(code.contains("        ///   Looks up a localized string similar to Hello &lt;b&gt;{0}&lt;/b&gt;\n"));
+    assert!(code.contains("internal static string lblHello => ResourceManager.GetString(\"lblHello\", Culture);"));
+    assert!(code.contains("internal static string page_title => ResourceManager.GetString(\"page.title\", Culture);"));
+    assert!(code.contains("internal static string @class => ResourceManager.GetString(\"class\", Culture);"));
+    assert!(code.contains("internal static string Resources_ => ResourceManager.GetString(\"Resources\", Culture);"));
+}
+
+#[test]
+fn test_codegen_rejects_filters() {
+    let query = query_codegen("Strings.resx", CodegenLanguage::CSharp, CodegenOptions::default());
+
+    for options in [
+        QueryRunOptions {
+            key_filters: vec![String::from("lbl*")],
+            ..QueryRunOptions::default()
+        },
+        QueryRunOptions {
+            limit: Some(1),
+            ..QueryRunOptions::default()
+        },
+    ] {
+        let error = query
+            .evaluate_with_options("Strings.Designer.cs", false, OutputEncoding::Utf8NoBom, &options)
+            .expect_err("filters should be rejected");
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn test_generate_typescript_key_map() {
+    let resources = vec![
+        Resource::new("lblHello", "Hello {0}, you have {count:N0} items"),
+        Resource::new("page.title", "%s of %d"),
+        Resource::new("plain", "Plain"),
+    ];
+
+    let code = generate_typescript(&resources, "Strings");
+    assert!(code.contains("export const Strings = {\n  lblHello: \"lblHello\",\n  \"page.title\": \"page.title\",\n"));
+    assert!(code.contains("} as const;\n"));
+    assert!(code.contains("export type StringsKey = (typeof Strings)[keyof typeof Strings];"));
+    assert!(code.contains("  lblHello: { \"0\": string | number; count: string | number };\n"));
+    assert!(code.contains("  \"page.title\": { \"0\": string; \"1\": number };\n"));
+    assert!(code.contains("  plain: {};\n"));
+}
+
+#[test]
+fn test_codegen_language_from_filename() {
+    assert_eq!(
+        CodegenLanguage::from_filename("Resources.Designer.cs"),
+        Some(CodegenLanguage::CSharp)
+    );
+    assert_eq!(
+        CodegenLanguage::from_filename("strings.ts"),
+        Some(CodegenLanguage::TypeScript)
+    );
+    assert_eq!(CodegenLanguage::from_filename("strings.json"), None);
+    assert_eq!(default_class_name("Properties/Resources.fr.resx"), "Resources");
+}
@@ -0,0 +1,194 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use serde::Serialize;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::file::{load_error, load_resource_file};
+use crate::query::{QueryRecord, QueryResult, QueryRunOptions, evaluate_records};
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+pub const COVERAGE_CODE: &str = "CIRUP100";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoverageRow {
+    pub file: String,
+    /// Number of keys in the base file.
+    pub total: usize,
+    /// Number of base keys with a non-empty value in the file.
+    pub translated: usize,
+    pub missing: usize,
+    /// Percentage of translated keys, 100 for an empty base file.
+    pub coverage: f64,
+    /// Whether the coverage reaches the minimum, if any.
+    pub passed: bool,
+    #[serde(skip)]
+    base: String,
+    #[serde(skip)]
+    min_coverage: Option<f64>,
+}
+
+impl QueryRecord for CoverageRow {
+    const COLUMNS: &'static [&'static str] = &["file", "total", "translated", "missing", "coverage", "passed"];
+    const HAS_DIAGNOSTICS: bool = true;
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.file.clone(),
+            self.total.to_string(),
+            self.translated.to_string(),
+            self.missing.to_string(),
+            format!("{:.1}", self.coverage),
+            self.passed.to_string(),
+        ]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.file
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.file
+    }
+
+    fn diagnostic(&self) -> Option<Diagnostic> {
+        let min_coverage = self.min_coverage.filter(|_| !self.passed)?;
+
+        Some(Diagnostic {
+            file: self.file.clone(),
+            line: 0,
+            column: 0,
+            severity: Severity::Error,
+            code: COVERAGE_CODE.to_owned(),
+            message: format!(
+                "{:.1}% of the keys of {} are translated ({} missing), below the minimum of {}%",
+                self.coverage, self.base, self.missing, min_coverage
+            ),
+        })
+    }
+}
+
+pub struct CoverageQuery {
+    base: String,
+    targets: Vec<String>,
+    min_coverage: Option<f64>,
+}
+
+/// Measures the share of the keys of `base` translated in each of `targets`.
+pub fn query_coverage(base: &str, targets: &[&str]) -> CoverageQuery {
+    CoverageQuery {
+        base: base.to_owned(),
+        targets: targets.iter().map(|target| (*target).to_owned()).collect(),
+        min_coverage: None,
+    }
+}
+
+fn load(file: &str) -> Result<Vec<crate::Resource>, io::Error> {
+    load_resource_file(file).map_err(|e| load_error(file, e))
+}
+
+impl CoverageQuery {
+    /// Sets the minimum coverage percentage, below which a file fails.
+    #[must_use]
+    pub fn min_coverage(mut self, min_coverage: Option<f64>) -> Self {
+        self.min_coverage = min_coverage;
+        self
+    }
+
+    pub fn run(&self) -> Result<Vec<CoverageRow>, io::Error> {
+        let base_resources = load(&self.base)?;
+        let base_keys = base_resources
+            .iter()
+            .map(|resource| resource.name.as_str())
+            .collect::<HashSet<_>>();
+        let total = base_keys.len();
+
+        let mut rows = Vec::with_capacity(self.targets.len());
+        for target in &self.targets {
+            let resources = load(target)?;
+            let values = resources
+                .iter()
+                .map(|resource| (resource.name.as_str(), resource.value.as_str()))
+                .collect::<HashMap<_, _>>();
+
+            let translated = base_keys
+                .iter()
+                .filter(|key| values.get(*key).is_some_and(|value| !value.is_empty()))
+                .count();
+            let coverage = if total == 0 {
+                100.0
+            } else {
+                translated as f64 * 100.0 / total as f64
+            };
+
+            rows.push(CoverageRow {
+                file: target.clone(),
+                total,
+                translated,
+                missing: total - translated,
+                coverage,
+                passed: self.min_coverage.is_none_or(|min_coverage| coverage >= min_coverage),
+                base: self.base.clone(),
+                min_coverage: self.min_coverage,
+            });
+        }
+
+        Ok(rows)
+    }
+
+    /// Returns one row per file; with `--check`, a change is reported when a file is below the minimum.
+    pub fn evaluate_with_options(&self, options: &QueryRunOptions) -> Result<QueryResult<CoverageRow>, io::Error> {
+        let mut result = evaluate_records(self.run()?, "coverage", None, false, options)?;
+        result.report.change_detected = result.rows.iter().any(|row| !row.passed);
+        Ok(result)
+    }
+}
+
+#[test]
+fn test_coverage_with_minimum() {
+    use std::fs;
+
+    let files = TempFiles::new();
+
+    fs::write(
+        files.path("Strings.restext"),
+        "lblOne=One\r\nlblTwo=Two\r\nlblThree=Three\r\nlblFour=Four\r\n",
+    )
+    .expect("failed to write base");
+    fs::write(
+        files.path("Strings.fr.restext"),
+        "lblOne=Un\r\nlblTwo=Deux\r\nlblThree=\r\n",
+    )
+    .expect("failed to write fr");
+    fs::write(
+        files.path("Strings.de.restext"),
+        "lblOne=Eins\r\nlblTwo=Zwei\r\nlblThree=Drei\r\nlblFour=Vier\r\n",
+    )
+    .expect("failed to write de");
+
+    let query = query_coverage(
+        &files.path("Strings.restext"),
+        &[
+            files.path("Strings.fr.restext").as_str(),
+            files.path("Strings.de.restext").as_str(),
+        ],
+    )
+    .min_coverage(Some(75.0));
+    let result = query
+        .evaluate_with_options(&QueryRunOptions::default())
+        .expect("coverage failed");
+
+    let summary = result
+        .rows
+        .iter()
+        .map(|row| (row.translated, row.missing, row.passed))
+        .collect::<Vec<_>>();
+    assert_eq!(summary, vec![(2, 2, false), (4, 0, true)]);
+    assert!(result.report.change_detected);
+
+    let diagnostic = result.rows[0].diagnostic().expect("failing rows have a diagnostic");
+    assert_eq!(diagnostic.code, COVERAGE_CODE);
+    assert!(diagnostic.message.starts_with("50.0% of the keys"));
+    assert!(result.rows[1].diagnostic().is_none());
+}
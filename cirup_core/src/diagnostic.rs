@@ -0,0 +1,155 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/*
+ * Diagnostics are findings attached to a position in a resource file, rendered in the canonical
+ * MSBuild format understood by Visual Studio and most CI log parsers, or as GitHub Actions workflow
+ * commands, shown as annotations on pull requests:
+ * https://learn.microsoft.com/en-us/visualstudio/msbuild/msbuild-diagnostic-format-for-tasks
+ * https://docs.github.com/en/actions/reference/workflow-commands-for-github-actions
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiagnosticFormat {
+    /// `file(line,col): error CODE: message`
+    #[default]
+    Msbuild,
+    /// `::error file=file,line=line,col=col,title=CODE::message`
+    Github,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub file: String,
+    /// 1-based line, or 0 when the diagnostic applies to the whole file.
+    pub line: usize,
+    /// 1-based column, or 0 when the diagnostic applies to the whole line or file.
+    pub column: usize,
+    pub severity: Severity,
+    pub code: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn render(&self, format: DiagnosticFormat) -> String {
+        match format {
+            DiagnosticFormat::Msbuild => self.to_msbuild(),
+            DiagnosticFormat::Github => self.to_github(),
+        }
+    }
+
+    fn to_msbuild(&self) -> String {
+        let origin = match (self.line, self.column) {
+            (0, _) => self.file.clone(),
+            (line, 0) => format!("{}({})", self.file, line),
+            (line, column) => format!("{}({},{})", self.file, line, column),
+        };
+
+        format!("{}: {} {}: {}", origin, self.severity, self.code, self.message)
+    }
+
+    fn to_github(&self) -> String {
+        let mut properties = format!("file={}", escape_github_property(&self.file));
+        if self.line > 0 {
+            properties.push_str(&format!(",line={}", self.line));
+            if self.column > 0 {
+                properties.push_str(&format!(",col={}", self.column));
+            }
+        }
+        properties.push_str(&format!(",title={}", escape_github_property(&self.code)));
+
+        format!(
+            "::{} {}::{}",
+            self.severity,
+            properties,
+            escape_github_data(&self.message)
+        )
+    }
+}
+
+fn escape_github_data(value: &str) -> String {
+    value.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_github_property(value: &str) -> String {
+    escape_github_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+pub(crate) fn render_diagnostics(diagnostics: &[Diagnostic], format: DiagnosticFormat) -> String {
+    let mut output = String::new();
+    for diagnostic in diagnostics {
+        output.push_str(&diagnostic.render(format));
+        output.push('\n');
+    }
+    output
+}
+
+#[test]
+fn test_diagnostic_msbuild_format() {
+    let mut diagnostic = Diagnostic {
+        file: String::from("Strings.fr.resx"),
+        line: 12,
+        column: 5,
+        severity: Severity::Error,
+        code: String::from("CIRUP001"),
+        message: String::from("placeholder mismatch"),
+    };
+    assert_eq!(
+        diagnostic.render(DiagnosticFormat::Msbuild),
+        "Strings.fr.resx(12,5): error CIRUP001: placeholder mismatch"
+    );
+
+    diagnostic.line = 0;
+    diagnostic.severity = Severity::Warning;
+    assert_eq!(
+        diagnostic.render(DiagnosticFormat::Msbuild),
+        "Strings.fr.resx: warning CIRUP001: placeholder mismatch"
+    );
+}
+
+#[test]
+fn test_diagnostic_github_format() {
+    let mut diagnostic = Diagnostic {
+        file: String::from("res/Strings.fr.resx"),
+        line: 12,
+        column: 5,
+        severity: Severity::Error,
+        code: String::from("CIRUP001"),
+        message: String::from("50% done,\nplaceholder mismatch"),
+    };
+    assert_eq!(
+        diagnostic.render(DiagnosticFormat::Github),
+        "::error file=res/Strings.fr.resx,line=12,col=5,title=CIRUP001::50%25 done,%0Aplaceholder mismatch"
+    );
+
+    diagnostic.line = 0;
+    diagnostic.severity = Severity::Warning;
+    diagnostic.file = String::from("C:\\a,b.resx");
+    assert_eq!(
+        diagnostic.render(DiagnosticFormat::Github),
+        "::warning file=C%3A\\a%2Cb.resx,title=CIRUP001::50%25 done,%0Aplaceholder mismatch"
+    );
+}
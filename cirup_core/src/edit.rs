@@ -0,0 +1,315 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Resource;
+use crate::file::{OutputEncoding, load_resources, output_bytes_for_file};
+use crate::query::{PendingWrite, QueryRecord, QueryResult, QueryRunOptions, evaluate_records_with_outputs};
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * Scripted key edits: each edit sets, adds or deletes a key in one file. The files are parsed,
+ * edited and written again, and no file is written unless every edit applies.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEditOperation {
+    /// Adds the key, or updates its value.
+    Set,
+    /// Adds the key, which must not exist yet.
+    Add,
+    /// Deletes the key, which must exist.
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEdit {
+    pub operation: KeyEditOperation,
+    pub file: String,
+    pub key: String,
+    /// The new value; empty for deletions.
+    pub value: String,
+}
+
+/// A line of a JSONL stream of edits.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyEditLine {
+    file: String,
+    key: String,
+    value: Option<String>,
+}
+
+/// Reads a JSONL stream of edits from `filename`, or from stdin for `-`. Each line is an object
+/// with the `file` and `key` to edit, and the `value` to set unless the operation is a deletion.
+pub fn load_key_edits(operation: KeyEditOperation, filename: &str) -> Result<Vec<KeyEdit>, io::Error> {
+    let mut text = String::new();
+    if filename == "-" {
+        io::stdin().read_to_string(&mut text)?;
+    } else {
+        text = fs::read_to_string(filename).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", filename, e)))?;
+    }
+
+    let error = |index: usize, message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} line {}: {}", filename, index + 1, message),
+        )
+    };
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let line: KeyEditLine = serde_json::from_str(line).map_err(|e| error(index, e.to_string()))?;
+            let value = match (operation, line.value) {
+                (KeyEditOperation::Delete, _) => String::new(),
+                (_, Some(value)) => value,
+                (_, None) => return Err(error(index, format!("no value for '{}'", line.key))),
+            };
+
+            Ok(KeyEdit {
+                operation,
+                file: line.file,
+                key: line.key,
+                value,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyChangeKind {
+    Added,
+    Updated,
+    Deleted,
+}
+
+impl KeyChangeKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            KeyChangeKind::Added => "added",
+            KeyChangeKind::Updated => "updated",
+            KeyChangeKind::Deleted => "deleted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyChange {
+    pub file: String,
+    pub name: String,
+    pub kind: KeyChangeKind,
+    /// The new value, or the deleted value.
+    pub value: String,
+}
+
+impl QueryRecord for KeyChange {
+    const COLUMNS: &'static [&'static str] = &["file", "name", "kind", "value"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.file.clone(),
+            self.name.clone(),
+            self.kind.as_str().to_owned(),
+            self.value.clone(),
+        ]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.name
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.value
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEditResult {
+    /// The edits that change a file; setting a key to its current value changes nothing.
+    pub changes: Vec<KeyChange>,
+    /// The new content of the changed files.
+    pub outputs: Vec<PendingWrite>,
+}
+
+pub struct KeyEditing {
+    edits: Vec<KeyEdit>,
+}
+
+/// Applies `edits` in order, grouped by file.
+pub fn edit_keys(edits: Vec<KeyEdit>) -> KeyEditing {
+    KeyEditing { edits }
+}
+
+/// Applies an edit to the resources of its file, returning the change it makes.
+fn apply_edit(resources: &mut Vec<Resource>, edit: &KeyEdit) -> Result<Option<KeyChange>, String> {
+    let position = resources.iter().position(|resource| resource.name == edit.key);
+    let change = |kind: KeyChangeKind, value: &str| {
+        Some(KeyChange {
+            file: edit.file.clone(),
+            name: edit.key.clone(),
+            kind,
+            value: value.to_owned(),
+        })
+    };
+
+    match (edit.operation, position) {
+        (KeyEditOperation::Delete, Some(position)) => {
+            let resource = resources.remove(position);
+            Ok(change(KeyChangeKind::Deleted, &resource.value))
+        }
+        (KeyEditOperation::Delete, None) => Err(format!("{}: '{}' does not exist", edit.file, edit.key)),
+        (KeyEditOperation::Add, Some(_)) => Err(format!("{}: '{}' already exists", edit.file, edit.key)),
+        (KeyEditOperation::Set, Some(position)) => {
+            let resource = &mut resources[position];
+            if resource.value == edit.value {
+                return Ok(None);
+            }
+            resource.value.clone_from(&edit.value);
+            Ok(change(KeyChangeKind::Updated, &edit.value))
+        }
+        (KeyEditOperation::Add | KeyEditOperation::Set, None) => {
+            resources.push(Resource::new(&edit.key, &edit.value));
+            Ok(change(KeyChangeKind::Added, &edit.value))
+        }
+    }
+}
+
+impl KeyEditing {
+    /// The edited files, in the order of their first edit.
+    pub fn files(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        self.edits
+            .iter()
+            .map(|edit| edit.file.as_str())
+            .filter(|file| seen.insert(*file))
+            .collect()
+    }
+
+    /// Applies the edits without writing anything. Fails without editing any file when an edit does not apply.
+    pub fn run(&self, output_encoding: OutputEncoding) -> Result<KeyEditResult, io::Error> {
+        let mut changes = Vec::new();
+        let mut outputs = Vec::new();
+        let mut errors = Vec::new();
+
+        for file in self.files() {
+            // files are created by their first added key
+            let mut resources = if Path::new(file).exists() {
+                load_resources(file)?
+            } else {
+                Vec::new()
+            };
+            let mut file_changes = Vec::new();
+
+            for edit in self.edits.iter().filter(|edit| edit.file == file) {
+                match apply_edit(&mut resources, edit) {
+                    Ok(change) => file_changes.extend(change),
+                    Err(e) => errors.push(e),
+                }
+            }
+
+            if file_changes.is_empty() {
+                continue;
+            }
+
+            let bytes = output_bytes_for_file(file, &resources, output_encoding)?;
+            outputs.push(PendingWrite {
+                path: file.to_owned(),
+                bytes,
+            });
+            changes.extend(file_changes);
+        }
+
+        if !errors.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, errors.join("\n")));
+        }
+
+        Ok(KeyEditResult { changes, outputs })
+    }
+
+    /// Returns the changes and the edited files to write, which are written together.
+    pub fn evaluate_with_options(
+        &self,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<KeyChange>, io::Error> {
+        let result = self.run(output_encoding)?;
+        info!(
+            "{} key change(s) in {} file(s)",
+            result.changes.len(),
+            result.outputs.len()
+        );
+
+        evaluate_records_with_outputs(result.changes, "key-change", &result.outputs, touch, options)
+    }
+}
+
+#[test]
+fn test_edit_keys_across_files() {
+    let files = TempFiles::new();
+    let neutral = files.path("Strings.restext");
+    let french = files.path("Strings.fr.restext");
+    fs::write(&neutral, "lblHello=Hello\r\nlblBye=Bye\r\n").expect("failed to write neutral file");
+
+    let edit = |operation: KeyEditOperation, file: &str, key: &str, value: &str| KeyEdit {
+        operation,
+        file: file.to_owned(),
+        key: key.to_owned(),
+        value: value.to_owned(),
+    };
+
+    let result = edit_keys(vec![
+        edit(KeyEditOperation::Set, &neutral, "lblHello", "Hello"),
+        edit(KeyEditOperation::Set, &neutral, "lblBye", "Goodbye"),
+        edit(KeyEditOperation::Add, &french, "lblHello", "Bonjour"),
+    ])
+    .run(OutputEncoding::Utf8NoBom)
+    .expect("edits failed");
+
+    let changes = result
+        .changes
+        .iter()
+        .map(|change| (change.name.as_str(), change.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        vec![("lblBye", KeyChangeKind::Updated), ("lblHello", KeyChangeKind::Added)]
+    );
+    assert_eq!(result.outputs[0].bytes, b"lblHello=Hello\r\nlblBye=Goodbye\r\n");
+    assert_eq!(result.outputs[1].path, french);
+    assert_eq!(result.outputs[1].bytes, b"lblHello=Bonjour\r\n");
+
+    let result = edit_keys(vec![
+        edit(KeyEditOperation::Delete, &neutral, "lblBye", ""),
+        edit(KeyEditOperation::Add, &neutral, "lblHello", "Hi"),
+    ])
+    .run(OutputEncoding::Utf8NoBom);
+    let error = result.expect_err("lblHello already exists");
+    assert!(error.to_string().ends_with("'lblHello' already exists"));
+}
+
+#[test]
+fn test_load_key_edits_from_jsonl() {
+    let files = TempFiles::new();
+    let edits_file = files.path("edits.jsonl");
+    fs::write(
+        &edits_file,
+        "{\"file\": \"Strings.resx\", \"key\": \"lblHello\", \"value\": \"Hello\"}\n\n{\"file\": \"Strings.fr.resx\", \"key\": \"lblHello\"}\n",
+    )
+    .expect("failed to write edits");
+
+    let edits = load_key_edits(KeyEditOperation::Delete, &edits_file).expect("failed to load edits");
+    assert_eq!(edits.len(), 2);
+    assert_eq!(edits[1].file, "Strings.fr.resx");
+    assert!(edits.iter().all(|edit| edit.value.is_empty()));
+
+    let error = load_key_edits(KeyEditOperation::Set, &edits_file).expect_err("the second edit has no value");
+    assert!(error.to_string().contains("line 3: no value for 'lblHello'"));
+}
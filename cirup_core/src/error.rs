@@ -2,23 +2,68 @@ use std::error;
 use std::fmt;
 use std::io;
 
+use crate::file::ParseError;
+
+/// An error building a query, by kind of failure, for callers that handle them differently.
 #[derive(Debug)]
 pub enum CirupError {
+    /// The file could not be read.
     Io(io::Error),
+    /// No format handles the extension of the file.
+    UnsupportedFormat { file: String, extension: String },
+    /// The content of the file is invalid, at the position where parsing stopped.
+    Parse(ParseError),
+    /// The operation was given another number of inputs than it compares.
+    InputCount {
+        operation: &'static str,
+        expected: usize,
+        actual: usize,
+    },
 }
 
 impl fmt::Display for CirupError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             CirupError::Io(ref err) => write!(f, "IO error: {}", err),
+            CirupError::UnsupportedFormat {
+                ref file,
+                ref extension,
+            } => write!(f, "{}: unsupported resource format '{}'", file, extension),
+            CirupError::Parse(ref err) => write!(f, "{}", err),
+            CirupError::InputCount {
+                operation,
+                expected,
+                actual,
+            } => write!(f, "{} expects {} inputs, got {}", operation, expected, actual),
         }
     }
 }
 
 impl error::Error for CirupError {
-    fn cause(&self) -> Option<&dyn error::Error> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             CirupError::Io(ref err) => Some(err),
+            CirupError::Parse(ref err) => Some(err),
+            CirupError::UnsupportedFormat { .. } | CirupError::InputCount { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for CirupError {
+    fn from(err: io::Error) -> Self {
+        CirupError::Io(err)
+    }
+}
+
+impl From<CirupError> for io::Error {
+    /// Keeps the `ParseError` of parse errors, for callers reporting their position.
+    fn from(err: CirupError) -> Self {
+        match err {
+            CirupError::Io(err) => err,
+            CirupError::Parse(err) => io::Error::new(io::ErrorKind::InvalidData, err),
+            CirupError::UnsupportedFormat { .. } | CirupError::InputCount { .. } => {
+                io::Error::new(io::ErrorKind::InvalidInput, err.to_string())
+            }
         }
     }
 }
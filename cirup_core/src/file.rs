@@ -1,21 +1,28 @@
+use std::fmt;
 use std::fs;
+use std::io;
 use std::io::Read;
 use std::path::Path;
 
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, RwLock};
 #[cfg(test)]
 use std::time::Instant;
 
 use sha2::{Digest, Sha256};
 
 use crate::Resource;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::error::CirupError;
 use crate::json::JsonFileFormat;
+use crate::resources::ResourcesFileFormat;
 use crate::restext::RestextFileFormat;
 use crate::resx::ResxFileFormat;
+#[cfg(test)]
+use crate::test_util::TempFiles;
 use std::error::Error;
 
-const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+pub(crate) const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
 pub enum OutputEncoding {
@@ -24,30 +31,132 @@ pub enum OutputEncoding {
     Utf8Bom,
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
-pub(crate) enum FormatType {
-    Unknown,
-    Json,
-    Resx,
-    Restext,
+pub const PARSE_ERROR_CODE: &str = "CIRUP000";
+
+/// An error in the content of a resource file, at the 1-based line and column where parsing stopped.
+///
+/// Formats return it without a file name, which is filled in when the file is loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            file: String::new(),
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic {
+            file: self.file.clone(),
+            line: self.line,
+            column: self.column,
+            severity: Severity::Error,
+            code: PARSE_ERROR_CODE.to_owned(),
+            message: self.message.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.file.is_empty() {
+            write!(f, "{}: ", self.file)?;
+        }
+
+        write!(f, "{} at line {} column {}", self.message, self.line, self.column)
+    }
+}
+
+impl Error for ParseError {}
+
+/// A resource file format, looked up in the format registry by file extension or by name.
+///
+/// Formats other than the built-in `json`, `resx`, `restext` and `resources` formats can be
+/// added with [`register_format`], after which they are used to load, save and query files.
+pub trait FileFormat: Send + Sync {
+    /// Name of the format, such as `resx`.
+    fn name(&self) -> &str;
+
+    /// File extensions handled by the format, without the leading dot.
+    fn extensions(&self) -> &[&str];
+
+    /// Parses resources from the content of a file. Text formats can use [`decode_utf8_text`].
+    fn parse_from_bytes(&self, bytes: &[u8]) -> Result<Vec<Resource>, Box<dyn Error>>;
+
+    /// Renders resources as the content of a file. Text formats can use [`encode_utf8_owned`].
+    fn write_to_bytes(&self, resources: &[Resource], output_encoding: OutputEncoding) -> Result<Vec<u8>, io::Error>;
+
+    fn parse_from_file(&self, filename: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
+        if let Some(text) = vfile_get(filename) {
+            return self.parse_from_bytes(text.as_bytes());
+        }
+
+        self.parse_from_bytes(&fs::read(filename)?)
+    }
 }
 
-pub(crate) trait FileFormat {
-    const EXTENSION: &'static str;
-    fn parse_from_str(&self, text: &str) -> Result<Vec<Resource>, Box<dyn Error>>;
-    fn parse_from_file(&self, filename: &str) -> Result<Vec<Resource>, Box<dyn Error>>;
-    fn write_to_str(&self, resources: &[Resource]) -> String;
+lazy_static! {
+    static ref FORMATS: RwLock<Vec<Arc<dyn FileFormat>>> = RwLock::new(vec![
+        Arc::new(JsonFileFormat {}),
+        Arc::new(ResxFileFormat {}),
+        Arc::new(RestextFileFormat {}),
+        Arc::new(ResourcesFileFormat),
+    ]);
 }
 
-pub(crate) fn get_format_type_from_extension(extension: &str) -> FormatType {
-    match extension {
-        JsonFileFormat::EXTENSION => FormatType::Json,
-        ResxFileFormat::EXTENSION => FormatType::Resx,
-        RestextFileFormat::EXTENSION => FormatType::Restext,
-        _ => FormatType::Unknown,
+/// Registers a file format, replacing any format with the same name. Formats registered last
+/// take precedence when several formats handle the same extension.
+pub fn register_format<F: FileFormat + 'static>(format: F) {
+    if let Ok(mut formats) = FORMATS.write() {
+        formats.retain(|registered| registered.name() != format.name());
+        formats.push(Arc::new(format));
     }
 }
 
+/// Returns the registered formats, in registration order.
+pub fn formats() -> Vec<Arc<dyn FileFormat>> {
+    FORMATS.read().map(|formats| formats.clone()).unwrap_or_default()
+}
+
+/// Returns the format handling `extension`, with or without the leading dot.
+pub fn format_for_extension(extension: &str) -> Option<Arc<dyn FileFormat>> {
+    let extension = extension.trim_start_matches('.');
+    let formats = FORMATS.read().ok()?;
+    formats
+        .iter()
+        .rev()
+        .find(|format| format.extensions().contains(&extension))
+        .cloned()
+}
+
+/// Returns the format registered as `name`.
+pub fn format_by_name(name: &str) -> Option<Arc<dyn FileFormat>> {
+    let formats = FORMATS.read().ok()?;
+    formats.iter().find(|format| format.name() == name).cloned()
+}
+
+fn format_for_file(filename: &str) -> Option<Arc<dyn FileFormat>> {
+    Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(format_for_extension)
+}
+
+/// Decodes the content of a text resource file as UTF-8, without its byte order mark.
+pub fn decode_utf8_text(bytes: &[u8]) -> Result<&str, io::Error> {
+    let bytes = bytes.strip_prefix(&UTF8_BOM).unwrap_or(bytes);
+    std::str::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
 pub(crate) fn load_string_from_file(filename: &str) -> Result<String, Box<dyn Error>> {
     if let Some(text) = vfile_get(filename) {
         return Ok(text);
@@ -82,7 +191,8 @@ fn should_write_output(output_hash: [u8; 32], existing_bytes: Option<&[u8]>, tou
     output_hash != sha256_hash(existing_bytes)
 }
 
-fn encode_utf8_owned(text: String, output_encoding: OutputEncoding) -> Vec<u8> {
+/// Encodes the content of a text resource file, with a byte order mark if configured.
+pub fn encode_utf8_owned(text: String, output_encoding: OutputEncoding) -> Vec<u8> {
     match output_encoding {
         OutputEncoding::Utf8NoBom => text.into_bytes(),
         OutputEncoding::Utf8Bom => {
@@ -95,86 +205,120 @@ fn encode_utf8_owned(text: String, output_encoding: OutputEncoding) -> Vec<u8> {
     }
 }
 
-fn output_bytes_for_format(
-    format_type: FormatType,
+pub(crate) fn output_bytes_for_file(
+    filename: &str,
     resources: &[Resource],
     output_encoding: OutputEncoding,
-) -> Vec<u8> {
-    match format_type {
-        FormatType::Json => {
-            let file_format = JsonFileFormat {};
-            let text = file_format.write_to_str(resources);
-            encode_utf8_owned(text, output_encoding)
-        }
-        FormatType::Resx => {
-            let file_format = ResxFileFormat {};
-            let text = file_format.write_to_str(resources);
-            encode_utf8_owned(text, output_encoding)
-        }
-        FormatType::Restext => {
-            let file_format = RestextFileFormat {};
-            let text = file_format.write_to_str(resources);
-            encode_utf8_owned(text, output_encoding)
-        }
-        FormatType::Unknown => Vec::new(),
+) -> Result<Vec<u8>, io::Error> {
+    let format = format_for_file(filename).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported output file '{}'", filename),
+        )
+    })?;
+    format.write_to_bytes(resources, output_encoding)
+}
+
+#[cfg(test)]
+pub(crate) fn load_resource_str(text: &str, extension: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
+    match format_for_extension(extension) {
+        Some(format) => format.parse_from_bytes(text.as_bytes()),
+        None => Ok(Vec::new()),
     }
 }
 
-fn output_bytes_for_file(filename: &str, resources: &[Resource], output_encoding: OutputEncoding) -> Option<Vec<u8>> {
+pub(crate) fn load_resource_file(filename: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
     let path = Path::new(filename);
     let extension = path
         .extension()
         .and_then(|extension| extension.to_str())
-        .unwrap_or_default();
-    let format_type = get_format_type_from_extension(extension);
+        .ok_or_else(|| format!("file '{}' has no valid extension", filename))?;
+    let Some(format) = format_for_extension(extension) else {
+        return Ok(Vec::new());
+    };
 
-    if format_type == FormatType::Unknown {
-        return None;
+    match format.parse_from_file(filename) {
+        Ok(mut resources) => {
+            for span in resources.iter_mut().filter_map(|resource| resource.span.as_mut()) {
+                span.file = filename.to_owned();
+            }
+            Ok(resources)
+        }
+        Err(e) => Err(match e.downcast::<ParseError>() {
+            Ok(mut error) => {
+                error.file = filename.to_owned();
+                error
+            }
+            Err(e) => e,
+        }),
     }
+}
 
-    Some(output_bytes_for_format(format_type, resources, output_encoding))
+fn supported_format(extension: &str) -> Result<Arc<dyn FileFormat>, io::Error> {
+    format_for_extension(extension).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported resource format '{}'", extension),
+        )
+    })
 }
 
-#[cfg(test)]
-pub(crate) fn load_resource_str(text: &str, extension: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
-    match extension {
-        JsonFileFormat::EXTENSION => {
-            let file_format = JsonFileFormat {};
-            file_format.parse_from_str(text)
-        }
-        ResxFileFormat::EXTENSION => {
-            let file_format = ResxFileFormat {};
-            file_format.parse_from_str(text)
-        }
-        RestextFileFormat::EXTENSION => {
-            let file_format = RestextFileFormat {};
-            file_format.parse_from_str(text)
-        }
-        _ => Ok(Vec::new()),
+fn invalid_data(error: Box<dyn Error>) -> io::Error {
+    match error.downcast::<io::Error>() {
+        Ok(error) => *error,
+        Err(error) => io::Error::new(io::ErrorKind::InvalidData, error.to_string()),
     }
 }
 
-pub(crate) fn load_resource_file(filename: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
-    let path = Path::new(filename);
-    let extension = path
+/// Parses in-memory resources in the format given by `extension`, such as `resx` or `.json`.
+pub fn parse_resources(data: impl AsRef<[u8]>, extension: &str) -> Result<Vec<Resource>, io::Error> {
+    supported_format(extension)?
+        .parse_from_bytes(data.as_ref())
+        .map_err(invalid_data)
+}
+
+/// Loads a resource file, failing on unsupported extensions and parse errors.
+pub fn load_resources(filename: &str) -> Result<Vec<Resource>, io::Error> {
+    Ok(read_resources(filename)?)
+}
+
+/// Loads a resource file as `load_resources`, with the kind of failure in the error.
+pub(crate) fn read_resources(filename: &str) -> Result<Vec<Resource>, CirupError> {
+    let extension = Path::new(filename)
         .extension()
         .and_then(|extension| extension.to_str())
-        .ok_or_else(|| format!("file '{}' has no valid extension", filename))?;
-    match get_format_type_from_extension(extension) {
-        FormatType::Json => {
-            let file_format = JsonFileFormat {};
-            file_format.parse_from_file(filename)
-        }
-        FormatType::Resx => {
-            let file_format = ResxFileFormat {};
-            file_format.parse_from_file(filename)
-        }
-        FormatType::Restext => {
-            let file_format = RestextFileFormat {};
-            file_format.parse_from_file(filename)
-        }
-        FormatType::Unknown => Ok(Vec::new()),
+        .unwrap_or_default();
+    if format_for_extension(extension).is_none() {
+        return Err(CirupError::UnsupportedFormat {
+            file: filename.to_owned(),
+            extension: extension.to_owned(),
+        });
     }
+
+    load_resource_file(filename).map_err(|e| match e.downcast::<ParseError>() {
+        Ok(error) => CirupError::Parse(*error),
+        Err(e) => CirupError::Io(load_error(filename, e)),
+    })
+}
+
+/// Converts an error loading `filename` into an `io::Error` that names the file once.
+pub(crate) fn load_error(filename: &str, e: Box<dyn Error>) -> io::Error {
+    // parse errors already name the file, and are kept for callers reporting their position
+    let e = match e.downcast::<ParseError>() {
+        Ok(error) => return io::Error::new(io::ErrorKind::InvalidData, *error),
+        Err(e) => e,
+    };
+    let error = invalid_data(e);
+    io::Error::new(error.kind(), format!("{}: {}", filename, error))
+}
+
+/// Renders resources in the format given by `extension`, as they would be written to a file.
+pub fn resources_to_bytes(
+    resources: &[Resource],
+    extension: &str,
+    output_encoding: OutputEncoding,
+) -> Result<Vec<u8>, io::Error> {
+    supported_format(extension)?.write_to_bytes(resources, output_encoding)
 }
 
 pub(crate) fn save_resource_file(filename: &str, resources: &[Resource], touch: bool) {
@@ -187,32 +331,148 @@ pub(crate) fn save_resource_file_with_encoding(
     touch: bool,
     output_encoding: OutputEncoding,
 ) {
-    let Some(output_bytes) = output_bytes_for_file(filename, resources, output_encoding) else {
-        return;
+    let output_bytes = match output_bytes_for_file(filename, resources, output_encoding) {
+        Ok(output_bytes) => output_bytes,
+        Err(e) => {
+            error!("failed to write {}: {}", filename, e);
+            return;
+        }
     };
-    let output_hash = sha256_hash(&output_bytes);
-    let existing_bytes = fs::read(filename).ok();
 
-    if should_write_output(output_hash, existing_bytes.as_deref(), touch) {
-        fs::write(filename, output_bytes).expect("failed to write output file");
-    }
+    write_output_bytes(filename, &output_bytes, touch);
 }
 
+#[cfg(test)]
 pub(crate) fn would_save_resource_file_with_encoding(
     filename: &str,
     resources: &[Resource],
     touch: bool,
     output_encoding: OutputEncoding,
 ) -> bool {
-    let Some(output_bytes) = output_bytes_for_file(filename, resources, output_encoding) else {
+    let Ok(output_bytes) = output_bytes_for_file(filename, resources, output_encoding) else {
         return false;
     };
 
-    let output_hash = sha256_hash(&output_bytes);
+    would_write_output_bytes(filename, &output_bytes, touch)
+}
+
+pub(crate) fn would_write_output_bytes(filename: &str, output_bytes: &[u8], touch: bool) -> bool {
+    let output_hash = sha256_hash(output_bytes);
     let existing_bytes = fs::read(filename).ok();
     should_write_output(output_hash, existing_bytes.as_deref(), touch)
 }
 
+/// Writes `output_bytes` unless the existing file already has the same content, returning whether it wrote.
+pub(crate) fn write_output_bytes(filename: &str, output_bytes: &[u8], touch: bool) -> bool {
+    if !would_write_output_bytes(filename, output_bytes, touch) {
+        return false;
+    }
+
+    fs::write(filename, output_bytes).expect("failed to write output file");
+    true
+}
+
+/// ISO 639-1 languages, and the ISO 639-2/3 languages of .NET cultures without one, in sorted order.
+const CULTURE_LANGUAGES: &[&str] = &[
+    "aa", "ab", "ae", "af", "agq", "ak", "am", "an", "ar", "arn", "as", "asa", "ast", "av", "ay", "az", "ba", "bas",
+    "be", "bem", "bez", "bg", "bi", "bm", "bn", "bo", "br", "brx", "bs", "byn", "ca", "ccp", "ce", "ceb", "cgg", "ch",
+    "chr", "ckb", "co", "cr", "cs", "cu", "cv", "cy", "da", "dav", "de", "dje", "dsb", "dua", "dv", "dyo", "dz", "ebu",
+    "ee", "el", "en", "eo", "es", "et", "eu", "ewo", "fa", "ff", "fi", "fil", "fj", "fo", "fr", "fur", "fy", "ga",
+    "gd", "gl", "gn", "gsw", "gu", "guz", "gv", "ha", "haw", "he", "hi", "ho", "hr", "hsb", "ht", "hu", "hy", "hz",
+    "ia", "id", "ie", "ig", "ii", "ik", "io", "is", "it", "iu", "ja", "jgo", "jmc", "jv", "ka", "kab", "kam", "kde",
+    "kea", "kg", "khq", "ki", "kj", "kk", "kkj", "kl", "kln", "km", "kn", "ko", "kok", "kr", "ks", "ksb", "ksf", "ksh",
+    "ku", "kv", "kw", "ky", "la", "lag", "lb", "lg", "li", "lkt", "ln", "lo", "lrc", "lt", "lu", "luo", "luy", "lv",
+    "mas", "mer", "mfe", "mg", "mgh", "mgo", "mh", "mi", "mk", "ml", "mn", "moh", "mr", "ms", "mt", "mua", "my", "mzn",
+    "na", "naq", "nb", "nd", "nds", "ne", "ng", "nl", "nmg", "nn", "nnh", "no", "nqo", "nr", "nso", "nus", "nv", "ny",
+    "nyn", "oc", "oj", "om", "or", "os", "osa", "pa", "pi", "pl", "prg", "ps", "pt", "qps", "qu", "quc", "quz", "rm",
+    "rn", "ro", "rof", "ru", "rw", "rwk", "sa", "sah", "saq", "sbp", "sc", "sd", "se", "seh", "ses", "sg", "shi", "si",
+    "sk", "sl", "sm", "sma", "smj", "smn", "sms", "sn", "so", "sq", "sr", "ss", "st", "su", "sv", "sw", "syr", "ta",
+    "te", "teo", "tg", "th", "ti", "tig", "tk", "tl", "tn", "to", "tr", "ts", "tt", "tw", "twq", "ty", "tzm", "ug",
+    "uk", "ur", "uz", "vai", "ve", "vi", "vo", "vun", "wa", "wae", "wal", "wo", "xh", "yav", "yi", "yo", "yue", "za",
+    "zgh", "zh", "zu",
+];
+
+/// Returns whether `name` is a culture name: a known lowercase language, then an optional script (`Hans`),
+/// an optional region (`CA`, `419`) and variants (`valencia`), e.g. `fr`, `zh-Hans`, `sr-Latn-RS` or `qps-ploc`.
+fn is_culture_name(name: &str) -> bool {
+    let mut parts = name.split('-').peekable();
+    let Some(language) = parts.next() else {
+        return false;
+    };
+
+    if CULTURE_LANGUAGES.binary_search(&language).is_err() {
+        return false;
+    }
+
+    // the script and the region are optional, in that order
+    parts.next_if(|part| part.len() == 4 && part.chars().all(|ch| ch.is_ascii_alphabetic()));
+    parts.next_if(|part| {
+        (part.len() == 2 && part.chars().all(|ch| ch.is_ascii_alphabetic()))
+            || (part.len() == 3 && part.chars().all(|ch| ch.is_ascii_digit()))
+    });
+
+    parts.all(|part| (5..=8).contains(&part.len()) && part.chars().all(|ch| ch.is_ascii_alphanumeric()))
+}
+
+/// Returns the culture suffix of a resource file name, e.g. `fr` for `Strings.fr.resx`,
+/// or `None` when the last segment of the stem is not a culture name, as in `Strings.Web.resx`.
+pub(crate) fn culture_from_filename(filename: &str) -> Option<String> {
+    let stem = Path::new(filename).file_stem()?.to_str()?;
+    let (_, culture) = stem.rsplit_once('.')?;
+
+    is_culture_name(culture).then(|| culture.to_owned())
+}
+
+/// Returns the files of the family of a resource file: the neutral file and its culture files with the
+/// same extension, e.g. `Strings.resx`, `Strings.de.resx` and `Strings.fr.resx`, in that order.
+pub(crate) fn culture_family(filename: &str) -> Vec<String> {
+    let path = Path::new(filename);
+    let (Some(stem), Some(extension)) = (
+        path.file_stem().and_then(|stem| stem.to_str()),
+        path.extension().and_then(|extension| extension.to_str()),
+    ) else {
+        return vec![filename.to_owned()];
+    };
+    let neutral_stem = match culture_from_filename(filename) {
+        Some(culture) => &stem[..stem.len() - culture.len() - 1],
+        None => stem,
+    };
+    let neutral_name = format!("{}.{}", neutral_stem, extension);
+
+    let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty());
+    let search_directory = directory.unwrap_or_else(|| Path::new("."));
+    let Ok(entries) = fs::read_dir(search_directory) else {
+        return vec![filename.to_owned()];
+    };
+
+    let mut cultures = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| {
+            let culture = name
+                .strip_suffix(extension)
+                .and_then(|name| name.strip_suffix('.'))
+                .and_then(|name| name.strip_prefix(neutral_stem))
+                .and_then(|name| name.strip_prefix('.'));
+            culture.is_some() && culture_from_filename(name).as_deref() == culture
+        })
+        .collect::<Vec<_>>();
+    cultures.sort();
+
+    let mut names = Vec::with_capacity(cultures.len() + 1);
+    if search_directory.join(&neutral_name).is_file() {
+        names.push(neutral_name);
+    }
+    names.extend(cultures);
+
+    names
+        .into_iter()
+        .map(|name| match directory {
+            Some(directory) => directory.join(name).to_string_lossy().into_owned(),
+            None => name,
+        })
+        .collect()
+}
+
 lazy_static! {
     static ref HASHMAP: Mutex<HashMap<String, String>> = {
         let map = HashMap::new();
@@ -257,11 +517,146 @@ fn test_vfile() {
 }
 
 #[test]
-fn format_type_from_extension() {
-    assert_eq!(get_format_type_from_extension("json"), FormatType::Json);
-    assert_eq!(get_format_type_from_extension("resx"), FormatType::Resx);
-    assert_eq!(get_format_type_from_extension("restext"), FormatType::Restext);
-    assert_eq!(get_format_type_from_extension("txt"), FormatType::Unknown);
+fn format_from_extension() {
+    let name = |extension: &str| format_for_extension(extension).map(|format| format.name().to_owned());
+    assert_eq!(name("json").as_deref(), Some("json"));
+    assert_eq!(name("resx").as_deref(), Some("resx"));
+    assert_eq!(name(".restext").as_deref(), Some("restext"));
+    assert_eq!(name("resources").as_deref(), Some("resources"));
+    assert_eq!(name("txt"), None);
+    assert!(format_by_name("resx").is_some());
+}
+
+#[test]
+fn parse_errors_carry_file_and_position() {
+    let parse_error = |filename: &str, text: &str| {
+        vfile_set(filename, text);
+        let error = load_resources(filename).expect_err("parse should fail");
+        error
+            .get_ref()
+            .and_then(|error| error.downcast_ref::<ParseError>())
+            .cloned()
+            .expect("parse errors are kept")
+    };
+
+    let error = parse_error("broken.json", "{\n  \"lblBoat\": \"Boat\"\n  \"lblYolo\": \"Yolo\"\n}");
+    assert_eq!((error.file.as_str(), error.line, error.column), ("broken.json", 3, 3));
+    assert_eq!(error.to_string(), "broken.json: expected `,` or `}` at line 3 column 3");
+
+    let error = parse_error(
+        "broken.resx",
+        "<root>\n  <data name=\"lblBoat\">\n    <value>Boat</data>\n</root>",
+    );
+    assert_eq!((error.line, error.column), (3, 22));
+    assert_eq!(error.diagnostic().code, PARSE_ERROR_CODE);
+}
+
+#[cfg(test)]
+struct PropertiesFileFormat;
+
+#[cfg(test)]
+impl FileFormat for PropertiesFileFormat {
+    fn name(&self) -> &str {
+        "properties"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["properties"]
+    }
+
+    fn parse_from_bytes(&self, bytes: &[u8]) -> Result<Vec<Resource>, Box<dyn Error>> {
+        Ok(decode_utf8_text(bytes)?
+            .lines()
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(name, value)| Resource::new(name, value))
+            .collect())
+    }
+
+    fn write_to_bytes(&self, resources: &[Resource], output_encoding: OutputEncoding) -> Result<Vec<u8>, io::Error> {
+        let text = resources
+            .iter()
+            .map(|resource| format!("{} = {}\n", resource.name, resource.value))
+            .collect::<String>();
+        Ok(encode_utf8_owned(text, output_encoding))
+    }
+}
+
+#[test]
+fn registered_format_is_used_to_load_and_save_files() {
+    register_format(PropertiesFileFormat);
+    assert_eq!(
+        format_for_extension("properties")
+            .map(|format| format.name().to_owned())
+            .as_deref(),
+        Some("properties")
+    );
+
+    let filename = temp_output_file_path("properties");
+    let resources = vec![Resource::new("hello", "world")];
+    save_resource_file(&filename, &resources, false);
+    assert_eq!(
+        fs::read_to_string(&filename).expect("failed to read output"),
+        "hello = world\n"
+    );
+    assert_eq!(load_resources(&filename).expect("failed to load output"), resources);
+    assert_eq!(
+        parse_resources("lblBoat = I'm on a boat\n", "properties").expect("failed to parse"),
+        vec![Resource::new("lblBoat", "I'm on a boat")]
+    );
+
+    let _ = fs::remove_file(&filename);
+}
+
+#[test]
+fn culture_from_resource_filename() {
+    assert_eq!(culture_from_filename("Strings.fr.resx").as_deref(), Some("fr"));
+    assert_eq!(
+        culture_from_filename("dir/Strings.zh-Hans.json").as_deref(),
+        Some("zh-Hans")
+    );
+    assert_eq!(
+        culture_from_filename("Strings.sr-Latn-RS.resx").as_deref(),
+        Some("sr-Latn-RS")
+    );
+    assert_eq!(culture_from_filename("Strings.es-419.resx").as_deref(), Some("es-419"));
+    assert_eq!(
+        culture_from_filename("Strings.qps-ploc.resx").as_deref(),
+        Some("qps-ploc")
+    );
+    assert_eq!(culture_from_filename("Strings.resx"), None);
+    assert_eq!(culture_from_filename("UIResources.Designer.cs"), None);
+    assert_eq!(culture_from_filename("Strings.Web.resx"), None);
+    assert_eq!(culture_from_filename("Strings.App.resx"), None);
+    assert_eq!(culture_from_filename("Strings.web.resx"), None);
+    assert_eq!(culture_from_filename("Strings.FR.resx"), None);
+    assert_eq!(culture_from_filename("Strings.fr-Hans-CA-x.resx"), None);
+}
+
+#[test]
+fn culture_languages_are_sorted() {
+    assert!(CULTURE_LANGUAGES.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn culture_family_lists_neutral_then_culture_files() {
+    let files = TempFiles::new();
+    for name in [
+        "Strings.resx",
+        "Strings.fr.resx",
+        "Strings.de.resx",
+        "Strings.fr.json",
+        "Strings.Extra.fr.resx",
+        "Strings.Web.resx",
+        "Other.fr.resx",
+    ] {
+        fs::write(files.path(name), "").expect("failed to write family file");
+    }
+
+    let family = culture_family(&files.path("Strings.fr.resx"))
+        .into_iter()
+        .map(|file| file.rsplit(['/', '\\']).next().unwrap_or_default().to_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(family, vec!["Strings.resx", "Strings.de.resx", "Strings.fr.resx"]);
 }
 
 #[test]
@@ -289,14 +684,18 @@ fn should_write_when_touch_is_true_even_if_hashes_match() {
 #[test]
 fn restext_output_bytes_do_not_include_utf8_bom() {
     let resources = vec![Resource::new("hello", "world")];
-    let output = output_bytes_for_format(FormatType::Restext, &resources, OutputEncoding::Utf8NoBom);
+    let output = RestextFileFormat {}
+        .write_to_bytes(&resources, OutputEncoding::Utf8NoBom)
+        .expect("failed to write restext");
     assert!(!output.starts_with(&UTF8_BOM));
 }
 
 #[test]
 fn restext_output_bytes_include_utf8_bom_when_configured() {
     let resources = vec![Resource::new("hello", "world")];
-    let output = output_bytes_for_format(FormatType::Restext, &resources, OutputEncoding::Utf8Bom);
+    let output = RestextFileFormat {}
+        .write_to_bytes(&resources, OutputEncoding::Utf8Bom)
+        .expect("failed to write restext");
     assert!(output.starts_with(&UTF8_BOM));
 }
 
@@ -374,11 +773,15 @@ fn benchmark_output_bytes_for_format_large_input() {
         .collect::<Vec<_>>();
 
     let started = Instant::now();
-    let utf8_no_bom = output_bytes_for_format(FormatType::Json, &resources, OutputEncoding::Utf8NoBom);
+    let utf8_no_bom = JsonFileFormat {}
+        .write_to_bytes(&resources, OutputEncoding::Utf8NoBom)
+        .expect("failed to write json");
     let utf8_no_bom_elapsed = started.elapsed();
 
     let started = Instant::now();
-    let utf8_bom = output_bytes_for_format(FormatType::Json, &resources, OutputEncoding::Utf8Bom);
+    let utf8_bom = JsonFileFormat {}
+        .write_to_bytes(&resources, OutputEncoding::Utf8Bom)
+        .expect("failed to write json");
     let utf8_bom_elapsed = started.elapsed();
 
     assert!(utf8_no_bom.len() < utf8_bom.len());
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::io;
+use std::path::Path;
+
+use treexml::{Document, Element};
+
+use crate::file::load_string_from_file;
+use crate::handoff::{element_text, parse_csv_records};
+use crate::resx::without_bom;
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * Product glossaries of mandated translations and do-not-translate terms:
+ * CSV with a source term column, a column per language and an optional do-not-translate column
+ * TBX (ISO 30042): termEntry elements with a langSet per language, or in TBX v3, conceptEntry elements
+ * with a langSec per language, the source language being the xml:lang of the root
+ */
+
+const DO_NOT_TRANSLATE_COLUMNS: [&str; 3] = ["do-not-translate", "do_not_translate", "dnt"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlossaryTerm {
+    pub source: String,
+    /// Mandated translations, by language.
+    pub targets: HashMap<String, String>,
+    /// The term must appear verbatim in every translation.
+    pub do_not_translate: bool,
+}
+
+impl GlossaryTerm {
+    /// Returns the mandated translation for `language`, e.g. `fr-CA`, falling back to its neutral language `fr`.
+    pub fn target(&self, language: &str) -> Option<&str> {
+        let language = language.to_ascii_lowercase();
+        let neutral = language.split('-').next().unwrap_or_default();
+
+        self.targets
+            .get(&language)
+            .or_else(|| self.targets.get(neutral))
+            .map(String::as_str)
+            .filter(|target| !target.is_empty())
+    }
+}
+
+fn is_yes(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "x" | "y" | "yes" | "true"
+    )
+}
+
+fn parse_glossary_csv(text: &str) -> Result<Vec<GlossaryTerm>, Box<dyn Error>> {
+    let mut records = parse_csv_records(text)?.into_iter();
+    let header = records
+        .next()
+        .ok_or("csv parse error: missing header row")?
+        .into_iter()
+        .map(|column| column.trim().to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    let dnt_column = header
+        .iter()
+        .position(|column| DO_NOT_TRANSLATE_COLUMNS.contains(&column.as_str()));
+
+    let mut terms = Vec::new();
+    for record in records {
+        let Some(source) = record
+            .first()
+            .map(|source| source.trim())
+            .filter(|source| !source.is_empty())
+        else {
+            continue;
+        };
+
+        let targets = header
+            .iter()
+            .zip(&record)
+            .enumerate()
+            .skip(1)
+            .filter(|(index, (language, _))| Some(*index) != dnt_column && !language.is_empty())
+            .map(|(_, (language, target))| (language.clone(), target.trim().to_owned()))
+            .collect();
+
+        terms.push(GlossaryTerm {
+            source: source.to_owned(),
+            targets,
+            do_not_translate: dnt_column
+                .and_then(|index| record.get(index))
+                .is_some_and(|value| is_yes(value)),
+        });
+    }
+
+    Ok(terms)
+}
+
+fn collect_elements<'a>(element: &'a Element, name: &str, elements: &mut Vec<&'a Element>) {
+    for child in &element.children {
+        if child.name == name {
+            elements.push(child);
+        } else {
+            collect_elements(child, name, elements);
+        }
+    }
+}
+
+fn language_attribute(element: &Element) -> Option<String> {
+    element
+        .attributes
+        .get("xml:lang")
+        .or_else(|| element.attributes.get("lang"))
+        .map(|language| language.to_ascii_lowercase())
+}
+
+/// Returns whether an entry is marked as do-not-translate,
+/// with a `translatable` note of `no` or a `doNotTranslate` note of `yes`.
+fn is_do_not_translate(entry: &Element) -> bool {
+    let mut notes = Vec::new();
+    collect_elements(entry, "termNote", &mut notes);
+    collect_elements(entry, "descrip", &mut notes);
+
+    notes.into_iter().any(|note| {
+        let text = element_text(note);
+        match note.attributes.get("type").map(String::as_str) {
+            Some("translatable") => !is_yes(text),
+            Some("doNotTranslate") => is_yes(text),
+            _ => false,
+        }
+    })
+}
+
+fn parse_glossary_tbx(text: &str) -> Result<Vec<GlossaryTerm>, Box<dyn Error>> {
+    let doc = Document::parse(without_bom(text)).map_err(|e| format!("tbx parse error: {:?}", e))?;
+    let root = doc.root.ok_or("tbx root not found")?;
+    let source_language = language_attribute(&root).unwrap_or_else(|| String::from("en"));
+    let source_neutral = source_language.split('-').next().unwrap_or_default().to_owned();
+
+    // TBX v2 entries and language sets, then their TBX v3 names
+    let mut entries = Vec::new();
+    collect_elements(&root, "termEntry", &mut entries);
+    collect_elements(&root, "conceptEntry", &mut entries);
+
+    let mut terms = Vec::new();
+    for entry in entries {
+        let mut source = None;
+        let mut targets = HashMap::new();
+
+        for lang_set in entry
+            .children
+            .iter()
+            .filter(|child| child.name == "langSet" || child.name == "langSec")
+        {
+            let Some(language) = language_attribute(lang_set) else {
+                continue;
+            };
+            let mut term_elements = Vec::new();
+            collect_elements(lang_set, "term", &mut term_elements);
+            let Some(term) = term_elements
+                .first()
+                .map(|term| element_text(term).trim())
+                .filter(|term| !term.is_empty())
+            else {
+                continue;
+            };
+
+            if language == source_language || language == source_neutral {
+                source.get_or_insert_with(|| term.to_owned());
+            } else {
+                targets.entry(language).or_insert_with(|| term.to_owned());
+            }
+        }
+
+        if let Some(source) = source {
+            terms.push(GlossaryTerm {
+                source,
+                targets,
+                do_not_translate: is_do_not_translate(entry),
+            });
+        }
+    }
+
+    Ok(terms)
+}
+
+/// Loads the terms of a `.csv` or `.tbx` glossary, failing when it has none.
+pub fn load_glossary(filename: &str) -> Result<Vec<GlossaryTerm>, io::Error> {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let text = load_string_from_file(filename).map_err(|e| io::Error::other(format!("{}: {}", filename, e)))?;
+
+    let terms = match extension.as_deref() {
+        Some("csv") => parse_glossary_csv(&text),
+        Some("tbx") => parse_glossary_tbx(&text),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "unsupported glossary file '{}': expected a .csv or .tbx extension",
+                    filename
+                ),
+            ));
+        }
+    };
+
+    let terms = terms.map_err(|e| io::Error::other(format!("{}: {}", filename, e)))?;
+    if terms.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: the glossary has no terms", filename),
+        ));
+    }
+
+    Ok(terms)
+}
+
+/// Returns whether `text` contains `term` as whole words, so that `Data` does not match `Database`.
+pub fn contains_term(text: &str, term: &str, case_sensitive: bool) -> bool {
+    if term.is_empty() {
+        return false;
+    }
+
+    let (text, term) = if case_sensitive {
+        (text.to_owned(), term.to_owned())
+    } else {
+        (text.to_lowercase(), term.to_lowercase())
+    };
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+
+    text.match_indices(&term).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + term.len()..].chars().next();
+        let starts_inside_word = is_word(before) && term.starts_with(char::is_alphanumeric);
+        let ends_inside_word = is_word(after) && term.ends_with(char::is_alphanumeric);
+        !(starts_inside_word || ends_inside_word)
+    })
+}
+
+#[test]
+fn test_parse_glossary_csv() {
+    let terms = parse_glossary_csv(
+        "en,fr,de-DE,dnt\r\nData Source,Source de données,Datenquelle,\r\nDevolutions,,,yes\r\n,ignored,,\r\n",
+    )
+    .expect("csv glossary");
+
+    assert_eq!(terms.len(), 2);
+    assert_eq!(terms[0].source, "Data Source");
+    assert_eq!(terms[0].target("fr-CA"), Some("Source de données"));
+    assert_eq!(terms[0].target("de-DE"), Some("Datenquelle"));
+    assert_eq!(terms[0].target("it"), None);
+    assert!(!terms[0].do_not_translate);
+    assert!(terms[1].do_not_translate);
+    assert_eq!(terms[1].target("fr"), None);
+}
+
+#[test]
+fn test_parse_glossary_tbx() {
+    let terms = parse_glossary_tbx(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<martif type="TBX" xml:lang="en-US">
+  <text>
+    <body>
+      <termEntry id="1">
+        <langSet xml:lang="en"><tig><term>Data Source</term></tig></langSet>
+        <langSet xml:lang="fr"><tig><term>Source de données</term></tig></langSet>
+      </termEntry>
+      <termEntry id="2">
+        <langSet xml:lang="en-US">
+          <ntig><termGrp><term>Devolutions</term><termNote type="translatable">no</termNote></termGrp></ntig>
+        </langSet>
+      </termEntry>
+    </body>
+  </text>
+</martif>"#,
+    )
+    .expect("tbx glossary");
+
+    assert_eq!(terms.len(), 2);
+    assert_eq!(terms[0].source, "Data Source");
+    assert_eq!(terms[0].target("fr"), Some("Source de données"));
+    assert!(!terms[0].do_not_translate);
+    assert_eq!(terms[1].source, "Devolutions");
+    assert!(terms[1].do_not_translate);
+}
+
+#[test]
+fn test_parse_glossary_tbx_v3() {
+    let terms = parse_glossary_tbx(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<tbx type="TBX-Core" style="dca" xml:lang="en" xmlns="urn:iso:std:iso:30042:ed-2">
+  <text>
+    <body>
+      <conceptEntry id="c1">
+        <langSec xml:lang="en"><termSec><term>Data Source</term></termSec></langSec>
+        <langSec xml:lang="de"><termSec><term>Datenquelle</term></termSec></langSec>
+      </conceptEntry>
+      <conceptEntry id="c2">
+        <langSec xml:lang="en">
+          <termSec><term>Devolutions</term><termNote type="translatable">no</termNote></termSec>
+        </langSec>
+      </conceptEntry>
+    </body>
+  </text>
+</tbx>"#,
+    )
+    .expect("tbx v3 glossary");
+
+    assert_eq!(terms.len(), 2);
+    assert_eq!(terms[0].source, "Data Source");
+    assert_eq!(terms[0].target("de-DE"), Some("Datenquelle"));
+    assert_eq!(terms[1].source, "Devolutions");
+    assert!(terms[1].do_not_translate);
+}
+
+#[test]
+fn test_load_glossary_without_terms_fails() {
+    let files = TempFiles::new();
+    let path = files.path("glossary.tbx");
+    std::fs::write(
+        &path,
+        r#"<martif type="TBX" xml:lang="en"><text><body/></text></martif>"#,
+    )
+    .expect("failed to write glossary");
+
+    let error = load_glossary(&path).expect_err("empty glossary");
+    assert!(error.to_string().contains("no terms"), "{}", error);
+}
+
+#[test]
+fn test_contains_term() {
+    assert!(contains_term("Select a data source.", "Data Source", false));
+    assert!(!contains_term("Select a data source.", "Data Source", true));
+    assert!(!contains_term("Open the Database", "Data", false));
+    assert!(contains_term("Devolutions Server", "Devolutions", true));
+    assert!(contains_term("(C++)", "C++", true));
+    assert!(!contains_term("anything", "", false));
+}
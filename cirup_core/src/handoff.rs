@@ -0,0 +1,801 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+use treexml::{Document, Element};
+
+use crate::Resource;
+use crate::file::{
+    OutputEncoding, culture_from_filename, load_error, load_resource_file, load_resources, load_string_from_file,
+    output_bytes_for_file,
+};
+use crate::placeholder::placeholders_match;
+use crate::query::{
+    CirupEngine, DIFF_QUERY, MERGE_QUERY, PRINT_QUERY, QueryRecord, QueryResult, QueryRunOptions, RecordOutput,
+    evaluate_records,
+};
+use crate::resx::{push_escaped_xml_attr, push_escaped_xml_text, without_bom};
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * Translation hand-off files exchanged with translation vendors:
+ * XLIFF 1.2: http://docs.oasis-open.org/xliff/v1.2/os/xliff-core.html
+ * CSV (RFC 4180) with key, state, source and target columns
+ */
+
+const XLIFF_NAMESPACE: &str = "urn:oasis:names:tc:xliff:document:1.2";
+const CSV_HEADER: [&str; 4] = ["key", "state", "source", "target"];
+
+const EXPORT_CHANGED_QUERY: &str = "select a.key, a.val, b.val from a inner join b on a.key = b.key inner join c on a.key = c.key where a.val <> c.val";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HandoffState {
+    /// The key is missing from the target file.
+    New,
+    /// The source value changed since the previous base file.
+    Changed,
+    /// The unit carries a translation.
+    Translated,
+}
+
+impl HandoffState {
+    fn as_str(self) -> &'static str {
+        match self {
+            HandoffState::New => "new",
+            HandoffState::Changed => "changed",
+            HandoffState::Translated => "translated",
+        }
+    }
+
+    fn xliff_state(self) -> &'static str {
+        match self {
+            HandoffState::New => "needs-translation",
+            HandoffState::Changed => "needs-review-translation",
+            HandoffState::Translated => "translated",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "new" | "needs-translation" => HandoffState::New,
+            "changed" | "needs-review-translation" | "needs-adaptation" | "needs-l10n" => HandoffState::Changed,
+            _ => HandoffState::Translated,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HandoffUnit {
+    pub name: String,
+    pub state: HandoffState,
+    pub source: String,
+    pub target: String,
+}
+
+impl QueryRecord for HandoffUnit {
+    const COLUMNS: &'static [&'static str] = &["name", "state", "source", "target"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.state.as_str().to_owned(),
+            self.source.clone(),
+            self.target.clone(),
+        ]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.name
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.source
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HandoffFormat {
+    Xliff,
+    Csv,
+}
+
+fn handoff_format_from_filename(filename: &str) -> Result<HandoffFormat, io::Error> {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default();
+
+    match extension.to_ascii_lowercase().as_str() {
+        "xlf" | "xliff" => Ok(HandoffFormat::Xliff),
+        "csv" => Ok(HandoffFormat::Csv),
+        _ => Err(io::Error::other(format!(
+            "unsupported hand-off file '{}': expected a .xlf, .xliff or .csv extension",
+            filename
+        ))),
+    }
+}
+
+struct XliffHeader<'a> {
+    original: &'a str,
+    source_language: &'a str,
+    target_language: Option<&'a str>,
+}
+
+fn write_xliff(units: &[HandoffUnit], header: &XliffHeader<'_>) -> String {
+    let estimated_body_len = units
+        .iter()
+        .map(|unit| unit.name.len() * 2 + unit.source.len() + unit.target.len() + 160)
+        .sum::<usize>();
+    let mut output = String::with_capacity(320 + estimated_body_len);
+
+    output.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<xliff version=\"1.2\" xmlns=\"");
+    output.push_str(XLIFF_NAMESPACE);
+    output.push_str("\">\n  <file original=\"");
+    push_escaped_xml_attr(&mut output, header.original);
+    output.push_str("\" source-language=\"");
+    push_escaped_xml_attr(&mut output, header.source_language);
+    output.push('"');
+    if let Some(target_language) = header.target_language {
+        output.push_str(" target-language=\"");
+        push_escaped_xml_attr(&mut output, target_language);
+        output.push('"');
+    }
+    output.push_str(" datatype=\"plaintext\">\n    <body>");
+
+    for unit in units {
+        output.push_str("\n      <trans-unit id=\"");
+        push_escaped_xml_attr(&mut output, &unit.name);
+        output.push_str("\" resname=\"");
+        push_escaped_xml_attr(&mut output, &unit.name);
+        output.push_str("\" xml:space=\"preserve\">\n        <source>");
+        push_escaped_xml_text(&mut output, &unit.source);
+        output.push_str("</source>\n        <target state=\"");
+        output.push_str(unit.state.xliff_state());
+        output.push_str("\">");
+        push_escaped_xml_text(&mut output, &unit.target);
+        output.push_str("</target>\n      </trans-unit>");
+    }
+
+    output.push_str("\n    </body>\n  </file>\n</xliff>\n");
+    output
+}
+
+fn collect_trans_units<'a>(element: &'a Element, units: &mut Vec<&'a Element>) {
+    for child in &element.children {
+        if child.name == "trans-unit" {
+            units.push(child);
+        } else {
+            collect_trans_units(child, units);
+        }
+    }
+}
+
+pub(crate) fn element_text(element: &Element) -> &str {
+    element.text.as_deref().or(element.cdata.as_deref()).unwrap_or_default()
+}
+
+fn parse_xliff(text: &str) -> Result<Vec<HandoffUnit>, Box<dyn Error>> {
+    let doc = Document::parse(without_bom(text)).map_err(|e| format!("xliff parse error: {:?}", e))?;
+    let root = doc.root.ok_or("xliff root not found")?;
+
+    let mut elements = Vec::new();
+    collect_trans_units(&root, &mut elements);
+
+    let mut units = Vec::with_capacity(elements.len());
+    for element in elements {
+        let Some(name) = element
+            .attributes
+            .get("resname")
+            .or_else(|| element.attributes.get("id"))
+        else {
+            continue;
+        };
+
+        let source = element
+            .find_child(|child| child.name == "source")
+            .map(element_text)
+            .unwrap_or_default();
+        let target = element.find_child(|child| child.name == "target");
+        let target_text = target.map(element_text).unwrap_or_default();
+        let state = target
+            .and_then(|target| target.attributes.get("state"))
+            .map(|state| HandoffState::parse(state))
+            .unwrap_or(if target_text.is_empty() {
+                HandoffState::New
+            } else {
+                HandoffState::Translated
+            });
+
+        units.push(HandoffUnit {
+            name: name.clone(),
+            state,
+            source: source.to_owned(),
+            target: target_text.to_owned(),
+        });
+    }
+
+    Ok(units)
+}
+
+fn push_csv_field(output: &mut String, value: &str) {
+    if !value.contains([',', '"', '\r', '\n']) {
+        output.push_str(value);
+        return;
+    }
+
+    output.push('"');
+    for ch in value.chars() {
+        if ch == '"' {
+            output.push('"');
+        }
+        output.push(ch);
+    }
+    output.push('"');
+}
+
+fn push_csv_record(output: &mut String, fields: &[&str]) {
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            output.push(',');
+        }
+        push_csv_field(output, field);
+    }
+    output.push_str("\r\n");
+}
+
+fn write_csv(units: &[HandoffUnit]) -> String {
+    let mut output = String::new();
+    push_csv_record(&mut output, &CSV_HEADER);
+
+    for unit in units {
+        push_csv_record(
+            &mut output,
+            &[&unit.name, unit.state.as_str(), &unit.source, &unit.target],
+        );
+    }
+
+    output
+}
+
+pub(crate) fn parse_csv_records(text: &str) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if in_quotes {
+            match ch {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(ch),
+            }
+            continue;
+        }
+
+        match ch {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(ch),
+        }
+    }
+
+    if in_quotes {
+        return Err("csv parse error: unterminated quoted field".into());
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+fn parse_csv(text: &str) -> Result<Vec<HandoffUnit>, Box<dyn Error>> {
+    let mut records = parse_csv_records(text)?.into_iter();
+    let header = records.next().ok_or("csv parse error: missing header row")?;
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|column| names.contains(&column.trim().to_ascii_lowercase().as_str()))
+    };
+
+    let key_column = column(&["key", "name"]).ok_or("csv parse error: missing 'key' column")?;
+    let target_column = column(&["target", "translation"]).ok_or("csv parse error: missing 'target' column")?;
+    let source_column = column(&["source"]);
+    let state_column = column(&["state"]);
+
+    let mut units = Vec::new();
+    for record in records {
+        let field = |index: Option<usize>| index.and_then(|index| record.get(index)).map(String::as_str);
+        let Some(name) = field(Some(key_column)).filter(|name| !name.is_empty()) else {
+            continue;
+        };
+        let target = field(Some(target_column)).unwrap_or_default();
+        let state = field(state_column)
+            .map(HandoffState::parse)
+            .unwrap_or(if target.is_empty() {
+                HandoffState::New
+            } else {
+                HandoffState::Translated
+            });
+
+        units.push(HandoffUnit {
+            name: name.to_owned(),
+            state,
+            source: field(source_column).unwrap_or_default().to_owned(),
+            target: target.to_owned(),
+        });
+    }
+
+    Ok(units)
+}
+
+fn load_handoff_file(filename: &str) -> Result<Vec<HandoffUnit>, io::Error> {
+    let format = handoff_format_from_filename(filename)?;
+    let text = load_string_from_file(filename).map_err(|e| io::Error::other(format!("{}: {}", filename, e)))?;
+
+    let units = match format {
+        HandoffFormat::Xliff => parse_xliff(&text),
+        HandoffFormat::Csv => parse_csv(&text),
+    };
+
+    units.map_err(|e| io::Error::other(format!("{}: {}", filename, e)))
+}
+
+/// Registers the resources of `filename`, or no resources when it does not exist yet.
+fn register_optional_table(engine: &mut CirupEngine, table: &str, filename: &str) -> Result<(), io::Error> {
+    let resources = if Path::new(filename).exists() {
+        load_resources(filename)?
+    } else {
+        Vec::new()
+    };
+
+    engine.register_table_from_resources(table, resources);
+    Ok(())
+}
+
+pub struct HandoffExport {
+    base: String,
+    target: String,
+    previous_base: Option<String>,
+    source_language: String,
+    target_language: Option<String>,
+}
+
+/// Collects the keys of `target` that need translation: keys missing from `target`, and when
+/// `previous_base` is given, keys whose value in `base` changed since that version.
+pub fn handoff_export(base: &str, target: &str, previous_base: Option<&str>) -> HandoffExport {
+    HandoffExport {
+        base: base.to_owned(),
+        target: target.to_owned(),
+        previous_base: previous_base.map(str::to_owned),
+        source_language: String::from("en"),
+        target_language: culture_from_filename(target),
+    }
+}
+
+impl HandoffExport {
+    #[must_use]
+    pub fn with_languages(mut self, source_language: &str, target_language: Option<&str>) -> Self {
+        self.source_language = source_language.to_owned();
+        if let Some(target_language) = target_language {
+            self.target_language = Some(target_language.to_owned());
+        }
+        self
+    }
+
+    /// Returns the units to translate, failing when an input file cannot be loaded. A missing target has no keys.
+    pub fn run(&self) -> Result<Vec<HandoffUnit>, io::Error> {
+        let mut engine = CirupEngine::new();
+        engine.register_table_from_resources("A", load_resources(&self.base)?);
+        register_optional_table(&mut engine, "B", &self.target)?;
+        if let Some(previous_base) = &self.previous_base {
+            engine.register_table_from_resources("C", load_resources(previous_base)?);
+        }
+
+        let mut pending: HashMap<String, HandoffUnit> = HashMap::new();
+
+        for resource in engine.query_resource(DIFF_QUERY) {
+            pending.insert(
+                resource.name.clone(),
+                HandoffUnit {
+                    name: resource.name,
+                    state: HandoffState::New,
+                    source: resource.value,
+                    target: String::new(),
+                },
+            );
+        }
+
+        if self.previous_base.is_some() {
+            for triple in engine.query_triple(EXPORT_CHANGED_QUERY) {
+                pending.insert(
+                    triple.name.clone(),
+                    HandoffUnit {
+                        name: triple.name,
+                        state: HandoffState::Changed,
+                        source: triple.value,
+                        target: triple.base,
+                    },
+                );
+            }
+        }
+
+        Ok(engine
+            .query_resource(PRINT_QUERY)
+            .iter()
+            .filter_map(|resource| pending.remove(&resource.name))
+            .collect())
+    }
+
+    pub fn evaluate_with_options(
+        &self,
+        out_file: &str,
+        touch: bool,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<HandoffUnit>, io::Error> {
+        let format = handoff_format_from_filename(out_file)?;
+        let original = Path::new(&self.base)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(self.base.as_str());
+        let header = XliffHeader {
+            original,
+            source_language: &self.source_language,
+            target_language: self.target_language.as_deref(),
+        };
+
+        let output = RecordOutput {
+            path: out_file,
+            render: Box::new(move |units: &[HandoffUnit]| match format {
+                HandoffFormat::Xliff => Ok(write_xliff(units, &header).into_bytes()),
+                HandoffFormat::Csv => Ok(write_csv(units).into_bytes()),
+            }),
+            print_records: false,
+        };
+
+        evaluate_records(self.run()?, "handoff-unit", Some(output), touch, options)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportFindingKind {
+    /// The key does not exist in the base file.
+    UnknownKey,
+    /// The unit was translated from a source value that no longer matches the base file.
+    StaleSource,
+    /// The translation does not use the same placeholders as the base value.
+    PlaceholderMismatch,
+    /// The unit has no translation.
+    Untranslated,
+}
+
+impl ImportFindingKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ImportFindingKind::UnknownKey => "unknown-key",
+            ImportFindingKind::StaleSource => "stale-source",
+            ImportFindingKind::PlaceholderMismatch => "placeholder-mismatch",
+            ImportFindingKind::Untranslated => "untranslated",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportFinding {
+    pub name: String,
+    pub kind: ImportFindingKind,
+    pub accepted: bool,
+    pub message: String,
+}
+
+impl QueryRecord for ImportFinding {
+    const COLUMNS: &'static [&'static str] = &["name", "kind", "accepted", "message"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.kind.as_str().to_owned(),
+            self.accepted.to_string(),
+            self.message.clone(),
+        ]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.name
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.message
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandoffImportResult {
+    pub findings: Vec<ImportFinding>,
+    pub imported_count: usize,
+    pub resources: Vec<Resource>,
+}
+
+pub struct HandoffImport {
+    base: String,
+    target: String,
+    translations: String,
+    allow_stale: bool,
+}
+
+/// Validates a returned hand-off file against `base` and merges its translations into `target`.
+pub fn handoff_import(base: &str, target: &str, translations: &str) -> HandoffImport {
+    HandoffImport {
+        base: base.to_owned(),
+        target: target.to_owned(),
+        translations: translations.to_owned(),
+        allow_stale: false,
+    }
+}
+
+impl HandoffImport {
+    #[must_use]
+    pub fn allow_stale(mut self, allow_stale: bool) -> Self {
+        self.allow_stale = allow_stale;
+        self
+    }
+
+    fn validate(&self, unit: &HandoffUnit, base_values: &HashMap<String, String>) -> Option<ImportFinding> {
+        let finding = |kind: ImportFindingKind, accepted: bool, message: String| {
+            Some(ImportFinding {
+                name: unit.name.clone(),
+                kind,
+                accepted,
+                message,
+            })
+        };
+
+        let Some(base_value) = base_values.get(&unit.name) else {
+            return finding(
+                ImportFindingKind::UnknownKey,
+                false,
+                format!("key does not exist in {}", self.base),
+            );
+        };
+
+        if unit.target.is_empty() {
+            return finding(ImportFindingKind::Untranslated, false, String::from("no translation"));
+        }
+
+        if !placeholders_match(base_value, &unit.target) {
+            return finding(
+                ImportFindingKind::PlaceholderMismatch,
+                false,
+                format!("placeholders differ from source value '{}'", base_value),
+            );
+        }
+
+        if !unit.source.is_empty() && unit.source != *base_value {
+            return finding(
+                ImportFindingKind::StaleSource,
+                self.allow_stale,
+                format!("source changed from '{}' to '{}'", unit.source, base_value),
+            );
+        }
+
+        None
+    }
+
+    pub fn run(&self) -> Result<HandoffImportResult, io::Error> {
+        let units = load_handoff_file(&self.translations)?;
+        let base_values = load_resource_file(&self.base)
+            .map_err(|e| load_error(&self.base, e))?
+            .into_iter()
+            .map(|resource| (resource.name, resource.value))
+            .collect::<HashMap<_, _>>();
+
+        let mut findings = Vec::new();
+        let mut accepted = Vec::with_capacity(units.len());
+
+        for unit in &units {
+            match self.validate(unit, &base_values) {
+                Some(finding) => {
+                    if finding.accepted {
+                        accepted.push(Resource::new(&unit.name, &unit.target));
+                    }
+                    findings.push(finding);
+                }
+                None => accepted.push(Resource::new(&unit.name, &unit.target)),
+            }
+        }
+
+        let imported_count = accepted.len();
+        let mut engine = CirupEngine::new();
+        register_optional_table(&mut engine, "A", &self.target)?;
+        engine.register_table_from_resources("B", accepted);
+
+        Ok(HandoffImportResult {
+            findings,
+            imported_count,
+            resources: engine.query_resource(MERGE_QUERY),
+        })
+    }
+
+    /// Returns the validation findings and the merged translations to write to `out_file`, or to the target file.
+    pub fn evaluate_with_options(
+        &self,
+        out_file: Option<&str>,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<ImportFinding>, io::Error> {
+        let out_file = out_file.unwrap_or(self.target.as_str());
+        let result = self.run()?;
+        let bytes = output_bytes_for_file(out_file, &result.resources, output_encoding)?;
+        info!(
+            "imported {} translation(s) from {} with {} finding(s)",
+            result.imported_count,
+            self.translations,
+            result.findings.len()
+        );
+
+        let output = RecordOutput {
+            path: out_file,
+            render: Box::new(move |_: &[ImportFinding]| Ok(bytes)),
+            print_records: true,
+        };
+
+        evaluate_records(result.findings, "import-finding", Some(output), touch, options)
+    }
+}
+
+#[test]
+fn test_xliff_round_trip() {
+    let units = vec![
+        HandoffUnit {
+            name: String::from("lblBoat"),
+            state: HandoffState::New,
+            source: String::from("I'm on a <boat> & {0}"),
+            target: String::new(),
+        },
+        HandoffUnit {
+            name: String::from("lblYolo"),
+            state: HandoffState::Changed,
+            source: String::from("You only live once"),
+            target: String::from("Juste une vie a vivre"),
+        },
+    ];
+    let header = XliffHeader {
+        original: "test.resx",
+        source_language: "en",
+        target_language: Some("fr"),
+    };
+
+    let text = write_xliff(&units, &header);
+    assert!(text.contains("<trans-unit id=\"lblBoat\" resname=\"lblBoat\" xml:space=\"preserve\">"));
+    assert!(text.contains("<source>I'm on a &lt;boat&gt; &amp; {0}</source>"));
+    assert!(text.contains("target-language=\"fr\""));
+
+    let parsed = parse_xliff(&text).expect("xliff parse failed");
+    assert_eq!(parsed, units);
+}
+
+#[test]
+fn test_csv_round_trip_with_quotes_and_newlines() {
+    let units = vec![HandoffUnit {
+        name: String::from("lblQuote"),
+        state: HandoffState::Translated,
+        source: String::from("Say \"hi\",\nthen leave"),
+        target: String::from("Dites \"salut\""),
+    }];
+
+    let text = write_csv(&units);
+    assert!(text.starts_with("key,state,source,target\r\n"));
+
+    let parsed = parse_csv(&text).expect("csv parse failed");
+    assert_eq!(parsed, units);
+}
+
+#[test]
+fn test_handoff_export_and_import() {
+    let files = TempFiles::new();
+
+    std::fs::write(
+        files.path("Strings.restext"),
+        "lblBoat=I'm on a boat.\r\nlblYolo=You only live once!\r\nlblCount={0} files\r\n",
+    )
+    .expect("failed to write base");
+    std::fs::write(files.path("Strings.old.restext"), "lblYolo=You only live once\r\n")
+        .expect("failed to write old base");
+    std::fs::write(files.path("Strings.fr.restext"), "lblYolo=Juste une vie a vivre\r\n")
+        .expect("failed to write target");
+
+    let export = handoff_export(
+        &files.path("Strings.restext"),
+        &files.path("Strings.fr.restext"),
+        Some(&files.path("Strings.old.restext")),
+    );
+    let units = export.run().expect("export failed");
+    let names = units.iter().map(|unit| unit.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["lblBoat", "lblYolo", "lblCount"]);
+    assert_eq!(units[1].state, HandoffState::Changed);
+    assert_eq!(units[1].target, "Juste une vie a vivre");
+
+    let returned = "key,source,target\r\n\
+                    lblBoat,I'm on a boat.,Je suis sur un bateau\r\n\
+                    lblYolo,You only live once,Juste une vie\r\n\
+                    lblCount,{0} files,fichiers\r\n\
+                    lblGhost,Boo,Bouh\r\n";
+    std::fs::write(files.path("returned.csv"), returned).expect("failed to write returned csv");
+
+    let result = handoff_import(
+        &files.path("Strings.restext"),
+        &files.path("Strings.fr.restext"),
+        &files.path("returned.csv"),
+    )
+    .run()
+    .expect("import failed");
+
+    let kinds = result
+        .findings
+        .iter()
+        .map(|finding| (finding.name.as_str(), finding.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            ("lblYolo", ImportFindingKind::StaleSource),
+            ("lblCount", ImportFindingKind::PlaceholderMismatch),
+            ("lblGhost", ImportFindingKind::UnknownKey),
+        ]
+    );
+    assert_eq!(result.imported_count, 1);
+    assert_eq!(
+        result.resources,
+        vec![
+            Resource::new("lblYolo", "Juste une vie a vivre"),
+            Resource::new("lblBoat", "Je suis sur un bateau"),
+        ]
+    );
+}
+
+#[test]
+fn test_handoff_export_fails_on_load_errors() {
+    let files = TempFiles::new();
+    std::fs::write(files.path("Strings.restext"), "lblBoat=I'm on a boat.\r\n").expect("failed to write base");
+    std::fs::write(
+        files.path("Strings.de.json"),
+        "{\n  \"lblBoat\": \"Boot\"\n  \"lblYolo\": \"\"\n}\n",
+    )
+    .expect("failed to write target");
+
+    // a target that does not exist yet has no keys
+    let units = handoff_export(&files.path("Strings.restext"), &files.path("Strings.fr.restext"), None)
+        .run()
+        .expect("export failed");
+    assert_eq!(units.len(), 1);
+
+    let error = handoff_export(&files.path("Nope.restext"), &files.path("Strings.fr.restext"), None)
+        .run()
+        .expect_err("missing base");
+    assert!(error.to_string().contains("Nope.restext"), "{}", error);
+
+    assert!(
+        handoff_export(&files.path("Strings.restext"), &files.path("Strings.de.json"), None)
+            .run()
+            .is_err()
+    );
+}
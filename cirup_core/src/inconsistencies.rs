@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::file::{culture_from_filename, load_resources};
+use crate::query::{CirupEngine, QueryRecord, QueryResult, QueryRunOptions, evaluate_records};
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * Translation consistency across files: the base/translation pairs of many resource files are loaded
+ * into one engine, and for each language, a source string translated differently,
+ * or different source strings translated the same, is reported with the keys involved.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InconsistencyKind {
+    /// The same source string has different translations.
+    DivergentTranslations,
+    /// Different source strings have the same translation.
+    SharedTranslation,
+}
+
+impl InconsistencyKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            InconsistencyKind::DivergentTranslations => "divergent-translations",
+            InconsistencyKind::SharedTranslation => "shared-translation",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyLocation {
+    /// The translation file of the key.
+    pub file: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InconsistencyVariant {
+    /// A translation of the source string, or a source string of the translation.
+    pub value: String,
+    pub keys: Vec<KeyLocation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Inconsistency {
+    pub kind: InconsistencyKind,
+    /// The culture of the translation files, when their names have one.
+    pub language: Option<String>,
+    /// The source string of divergent translations, or the shared translation.
+    pub value: String,
+    pub variants: Vec<InconsistencyVariant>,
+}
+
+impl QueryRecord for Inconsistency {
+    const COLUMNS: &'static [&'static str] = &["kind", "language", "value", "variants"];
+
+    fn cells(&self) -> Vec<String> {
+        let variants = self
+            .variants
+            .iter()
+            .map(|variant| {
+                let keys = variant
+                    .keys
+                    .iter()
+                    .map(|key| format!("{}:{}", key.file, key.name))
+                    .collect::<Vec<_>>();
+                format!("{} ({})", variant.value, keys.join(", "))
+            })
+            .collect::<Vec<_>>();
+
+        vec![
+            self.kind.as_str().to_owned(),
+            self.language.clone().unwrap_or_default(),
+            self.value.clone(),
+            variants.join("; "),
+        ]
+    }
+
+    fn filter_key(&self) -> &str {
+        self.variants
+            .first()
+            .and_then(|variant| variant.keys.first())
+            .map(|key| key.name.as_str())
+            .unwrap_or_default()
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.value
+    }
+}
+
+/// A translated key of one of the pairs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranslationPair {
+    pub language: Option<String>,
+    pub file: String,
+    pub name: String,
+    pub source: String,
+    pub translation: String,
+}
+
+/// Groups `pairs` by language and by `group`, and reports the groups with more than one `variant`.
+fn find_inconsistencies(
+    pairs: &[TranslationPair],
+    kind: InconsistencyKind,
+    group: fn(&TranslationPair) -> &str,
+    variant: fn(&TranslationPair) -> &str,
+) -> Vec<Inconsistency> {
+    // groups and variants are listed in the order they first appear
+    let mut groups = Vec::<(Option<&str>, &str, Vec<(&str, Vec<&TranslationPair>)>)>::new();
+    let mut positions = HashMap::new();
+
+    for pair in pairs {
+        let language = pair.language.as_deref();
+        let position = *positions.entry((language, group(pair))).or_insert_with(|| {
+            groups.push((language, group(pair), Vec::new()));
+            groups.len() - 1
+        });
+
+        let variants = &mut groups[position].2;
+        match variants.iter_mut().find(|(value, _)| *value == variant(pair)) {
+            Some((_, keys)) => keys.push(pair),
+            None => variants.push((variant(pair), vec![pair])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, _, variants)| variants.len() > 1)
+        .map(|(language, value, variants)| Inconsistency {
+            kind,
+            language: language.map(str::to_owned),
+            value: value.to_owned(),
+            variants: variants
+                .into_iter()
+                .map(|(value, pairs)| InconsistencyVariant {
+                    value: value.to_owned(),
+                    keys: pairs
+                        .into_iter()
+                        .map(|pair| KeyLocation {
+                            file: pair.file.clone(),
+                            name: pair.name.clone(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Reports, for each language, the source strings with divergent translations,
+/// then the translations shared by different source strings.
+pub fn inconsistencies_in_pairs(pairs: &[TranslationPair]) -> Vec<Inconsistency> {
+    let mut findings = find_inconsistencies(
+        pairs,
+        InconsistencyKind::DivergentTranslations,
+        |pair| &pair.source,
+        |pair| &pair.translation,
+    );
+    findings.extend(find_inconsistencies(
+        pairs,
+        InconsistencyKind::SharedTranslation,
+        |pair| &pair.translation,
+        |pair| &pair.source,
+    ));
+    findings
+}
+
+/// Returns the neutral file of a culture file, e.g. `Strings.resx` for `Strings.fr.resx`.
+pub fn neutral_filename(filename: &str) -> Option<String> {
+    let culture = culture_from_filename(filename)?;
+    let path = Path::new(filename);
+    let stem = path.file_stem()?.to_str()?;
+    let neutral_stem = &stem[..stem.len() - culture.len() - 1];
+    let name = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => format!("{}.{}", neutral_stem, extension),
+        None => neutral_stem.to_owned(),
+    };
+
+    Some(path.with_file_name(name).to_string_lossy().into_owned())
+}
+
+pub struct InconsistencyCheck {
+    pairs: Vec<(String, String)>,
+}
+
+/// Checks the consistency of the translations of the `(base, target)` pairs.
+pub fn check_inconsistencies(pairs: &[(&str, &str)]) -> InconsistencyCheck {
+    InconsistencyCheck {
+        pairs: pairs
+            .iter()
+            .map(|(base, target)| ((*base).to_owned(), (*target).to_owned()))
+            .collect(),
+    }
+}
+
+impl InconsistencyCheck {
+    /// Returns the translated keys of every pair, with a non-empty source and translation,
+    /// failing when a file cannot be loaded.
+    pub fn pairs(&self) -> Result<Vec<TranslationPair>, io::Error> {
+        let mut engine = CirupEngine::new();
+        for (index, (base, target)) in self.pairs.iter().enumerate() {
+            engine.register_table_from_resources(&format!("A{}", index), load_resources(base)?);
+            engine.register_table_from_resources(&format!("B{}", index), load_resources(target)?);
+        }
+
+        let mut pairs = Vec::new();
+        for (index, (_, target)) in self.pairs.iter().enumerate() {
+            let language = culture_from_filename(target);
+            let query = format!(
+                "select a{0}.key, b{0}.val, a{0}.val from a{0} inner join b{0} on a{0}.key = b{0}.key",
+                index
+            );
+
+            pairs.extend(
+                engine
+                    .query_triple(&query)
+                    .into_iter()
+                    .filter(|triple| !triple.base.is_empty() && !triple.value.is_empty())
+                    .map(|triple| TranslationPair {
+                        language: language.clone(),
+                        file: target.clone(),
+                        name: triple.name,
+                        source: triple.base,
+                        translation: triple.value,
+                    }),
+            );
+        }
+
+        Ok(pairs)
+    }
+
+    pub fn run(&self) -> Result<Vec<Inconsistency>, io::Error> {
+        Ok(inconsistencies_in_pairs(&self.pairs()?))
+    }
+
+    pub fn evaluate_with_options(&self, options: &QueryRunOptions) -> Result<QueryResult<Inconsistency>, io::Error> {
+        let findings = self.run()?;
+        info!(
+            "inconsistencies: {} finding(s) in {} pair(s)",
+            findings.len(),
+            self.pairs.len()
+        );
+        evaluate_records(findings, "inconsistency", None, false, options)
+    }
+}
+
+#[test]
+fn test_inconsistencies_in_pairs() {
+    let pair = |language: &str, file: &str, name: &str, source: &str, translation: &str| TranslationPair {
+        language: Some(language.to_owned()),
+        file: file.to_owned(),
+        name: name.to_owned(),
+        source: source.to_owned(),
+        translation: translation.to_owned(),
+    };
+    let pairs = vec![
+        pair("fr", "UI.fr.resx", "btnCancel", "Cancel", "Annuler"),
+        pair("fr", "Msg.fr.resx", "lblCancel", "Cancel", "Abandonner"),
+        pair("fr", "Msg.fr.resx", "lblAbort", "Abort", "Abandonner"),
+        pair("fr", "UI.fr.resx", "btnSave", "Save", "Enregistrer"),
+        pair("fr", "Msg.fr.resx", "lblSave", "Save", "Enregistrer"),
+        pair("de", "UI.de.resx", "btnCancel", "Cancel", "Abbrechen"),
+    ];
+
+    let findings = inconsistencies_in_pairs(&pairs);
+    let summary = findings
+        .iter()
+        .map(|finding| {
+            let variants = finding
+                .variants
+                .iter()
+                .map(|variant| (variant.value.as_str(), variant.keys.len()))
+                .collect::<Vec<_>>();
+            (finding.kind, finding.value.as_str(), variants)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                InconsistencyKind::DivergentTranslations,
+                "Cancel",
+                vec![("Annuler", 1), ("Abandonner", 1)]
+            ),
+            (
+                InconsistencyKind::SharedTranslation,
+                "Abandonner",
+                vec![("Cancel", 1), ("Abort", 1)]
+            ),
+        ]
+    );
+    assert_eq!(findings[0].variants[1].keys[0].file, "Msg.fr.resx");
+    assert_eq!(findings[0].variants[1].keys[0].name, "lblCancel");
+}
+
+#[test]
+fn test_neutral_filename() {
+    assert_eq!(neutral_filename("res/UI.fr.resx").as_deref(), Some("res/UI.resx"));
+    assert_eq!(
+        neutral_filename("Strings.zh-Hans.json").as_deref(),
+        Some("Strings.json")
+    );
+    assert_eq!(neutral_filename("Strings.resx"), None);
+}
+
+#[test]
+fn test_check_inconsistencies_loads_pairs_into_one_engine() {
+    let files = TempFiles::new();
+    for (name, data) in [
+        ("UI.restext", "btnCancel=Cancel\r\nbtnOk=OK\r\n"),
+        ("UI.fr.restext", "btnCancel=Annuler\r\nbtnOk=OK\r\n"),
+        ("Msg.restext", "lblCancel=Cancel\r\nlblEmpty=Empty\r\n"),
+        ("Msg.fr.restext", "lblCancel=Abandonner\r\nlblEmpty=\r\n"),
+    ] {
+        std::fs::write(files.path(name), data).expect("failed to write resource file");
+    }
+
+    let (ui, ui_fr, msg, msg_fr) = (
+        files.path("UI.restext"),
+        files.path("UI.fr.restext"),
+        files.path("Msg.restext"),
+        files.path("Msg.fr.restext"),
+    );
+    let check = check_inconsistencies(&[(&ui, &ui_fr), (&msg, &msg_fr)]);
+    assert_eq!(
+        check.pairs().expect("failed to load pairs").len(),
+        3,
+        "empty translations are skipped"
+    );
+
+    let findings = check.run().expect("failed to check pairs");
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].language.as_deref(), Some("fr"));
+    assert_eq!(findings[0].value, "Cancel");
+    assert_eq!(findings[0].variants[0].keys[0].file, ui_fr);
+    assert_eq!(findings[0].variants[1].keys[0].file, msg_fr);
+
+    let missing = files.path("Nope.fr.restext");
+    let error = check_inconsistencies(&[(&ui, &ui_fr), (&msg, &missing)])
+        .run()
+        .expect_err("missing translation file");
+    assert!(error.to_string().contains("Nope.fr.restext"), "{}", error);
+}
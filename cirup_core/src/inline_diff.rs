@@ -0,0 +1,195 @@
+use serde::Serialize;
+
+/*
+ * Inline diff of two values, by word or by character, to review changed values at a glance.
+ */
+
+/// Above this number of token pairs, the changed middle of two values is reported as replaced
+/// instead of being diffed.
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+const ANSI_RED: &str = "\x1b[31m";
+const ANSI_GREEN: &str = "\x1b[32m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffGranularity {
+    /// Words, runs of whitespace and single punctuation characters.
+    #[default]
+    Word,
+    Char,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiffSpanKind {
+    Equal,
+    Removed,
+    Added,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffSpan {
+    pub kind: DiffSpanKind,
+    pub text: String,
+}
+
+fn tokenize(text: &str, granularity: DiffGranularity) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+
+    while let Some(first) = rest.chars().next() {
+        let end = match granularity {
+            DiffGranularity::Char => first.len_utf8(),
+            DiffGranularity::Word if first.is_alphanumeric() => {
+                rest.find(|c: char| !c.is_alphanumeric()).unwrap_or(rest.len())
+            }
+            DiffGranularity::Word if first.is_whitespace() => {
+                rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len())
+            }
+            DiffGranularity::Word => first.len_utf8(),
+        };
+
+        let (token, tail) = rest.split_at(end);
+        tokens.push(token);
+        rest = tail;
+    }
+
+    tokens
+}
+
+fn push_span(spans: &mut Vec<DiffSpan>, kind: DiffSpanKind, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+
+    match spans.last_mut() {
+        Some(last) if last.kind == kind => last.text.push_str(text),
+        _ => spans.push(DiffSpan {
+            kind,
+            text: text.to_owned(),
+        }),
+    }
+}
+
+/// Diffs the middle of two values that have no common first or last token.
+fn diff_tokens(spans: &mut Vec<DiffSpan>, old: &[&str], new: &[&str]) {
+    if old.len().saturating_mul(new.len()) > MAX_DIFF_CELLS {
+        push_span(spans, DiffSpanKind::Removed, &old.concat());
+        push_span(spans, DiffSpanKind::Added, &new.concat());
+        return;
+    }
+
+    // length of the longest common subsequence of old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut lcs = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i * width + j] = if old[i] == new[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            push_span(spans, DiffSpanKind::Equal, old[i]);
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1]) {
+            push_span(spans, DiffSpanKind::Removed, old[i]);
+            i += 1;
+        } else {
+            push_span(spans, DiffSpanKind::Added, new[j]);
+            j += 1;
+        }
+    }
+}
+
+/// Returns the spans that turn `old` into `new`: equal text, and removed text before the added text that replaces it.
+pub fn diff_values(old: &str, new: &str, granularity: DiffGranularity) -> Vec<DiffSpan> {
+    let old_tokens = tokenize(old, granularity);
+    let new_tokens = tokenize(new, granularity);
+
+    let prefix = old_tokens
+        .iter()
+        .zip(&new_tokens)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old_tokens[prefix..]
+        .iter()
+        .rev()
+        .zip(new_tokens[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let mut spans = Vec::new();
+    push_span(&mut spans, DiffSpanKind::Equal, &old_tokens[..prefix].concat());
+    diff_tokens(
+        &mut spans,
+        &old_tokens[prefix..old_tokens.len() - suffix],
+        &new_tokens[prefix..new_tokens.len() - suffix],
+    );
+    push_span(
+        &mut spans,
+        DiffSpanKind::Equal,
+        &old_tokens[old_tokens.len() - suffix..].concat(),
+    );
+    spans
+}
+
+/// Renders spans on one line: removed text in red and added text in green with `color`,
+/// or as `[-removed-]` and `{+added+}` without.
+pub fn render_inline(spans: &[DiffSpan], color: bool) -> String {
+    let mut output = String::new();
+
+    for span in spans {
+        let (open, close) = match (span.kind, color) {
+            (DiffSpanKind::Equal, _) => ("", ""),
+            (DiffSpanKind::Removed, true) => (ANSI_RED, ANSI_RESET),
+            (DiffSpanKind::Added, true) => (ANSI_GREEN, ANSI_RESET),
+            (DiffSpanKind::Removed, false) => ("[-", "-]"),
+            (DiffSpanKind::Added, false) => ("{+", "+}"),
+        };
+        output.push_str(open);
+        output.push_str(&span.text);
+        output.push_str(close);
+    }
+
+    output
+}
+
+#[test]
+fn test_diff_values_by_word() {
+    let spans = diff_values(
+        "The quick brown fox jumps.",
+        "The quick red fox leaps!",
+        DiffGranularity::Word,
+    );
+
+    assert_eq!(
+        render_inline(&spans, false),
+        "The quick [-brown-]{+red+} fox [-jumps.-]{+leaps!+}"
+    );
+    assert_eq!(spans[0].kind, DiffSpanKind::Equal);
+    assert_eq!(spans[0].text, "The quick ");
+    assert_eq!(
+        render_inline(&spans[..3], true),
+        "The quick \x1b[31mbrown\x1b[0m\x1b[32mred\x1b[0m"
+    );
+}
+
+#[test]
+fn test_diff_values_by_char() {
+    let spans = diff_values("colour", "color", DiffGranularity::Char);
+    assert_eq!(render_inline(&spans, false), "colo[-u-]r");
+
+    assert_eq!(diff_values("same", "same", DiffGranularity::Word).len(), 1);
+    assert_eq!(
+        render_inline(&diff_values("", "new", DiffGranularity::Word), false),
+        "{+new+}"
+    );
+}
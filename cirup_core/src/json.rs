@@ -6,9 +6,11 @@ use serde_json::{Map, Value};
 #[cfg(test)]
 use std::time::Instant;
 
-use crate::Resource;
-use crate::file::FileFormat;
-use crate::file::load_string_from_file;
+use std::collections::HashMap;
+use std::io;
+
+use crate::file::{FileFormat, OutputEncoding, ParseError, decode_utf8_text, encode_utf8_owned};
+use crate::{Resource, SourceSpan};
 use std::error::Error;
 
 pub(crate) struct JsonFileFormat {}
@@ -45,6 +47,155 @@ fn flatten_json_value(value: &Value, path: &mut String, resources: &mut Vec<Reso
     }
 }
 
+/// Finds the spans of the string values of a valid JSON document, from their key to the end of the value,
+/// by flattened key. serde_json does not keep positions, so the text is scanned again.
+struct JsonSpanScanner<'a> {
+    text: &'a str,
+    index: usize,
+    line: usize,
+    column: usize,
+    spans: HashMap<String, SourceSpan>,
+}
+
+impl<'a> JsonSpanScanner<'a> {
+    fn scan(text: &'a str) -> HashMap<String, SourceSpan> {
+        let mut scanner = Self {
+            text,
+            index: 0,
+            line: 1,
+            column: 1,
+            spans: HashMap::new(),
+        };
+        let mut path = String::new();
+        scanner.scan_value(&mut path, None);
+        scanner.spans
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.index).copied()
+    }
+
+    fn bump(&mut self) {
+        let Some(byte) = self.peek() else {
+            return;
+        };
+        self.index += 1;
+
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if byte & 0xC0 != 0x80 {
+            // columns count characters, not UTF-8 continuation bytes
+            self.column += 1;
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.bump();
+        }
+    }
+
+    /// Skips a string literal, returning it with its quotes.
+    fn skip_string(&mut self) -> &'a str {
+        let start = self.index;
+        self.bump();
+
+        while let Some(byte) = self.peek() {
+            self.bump();
+            match byte {
+                b'\\' => self.bump(),
+                b'"' => break,
+                _ => {}
+            }
+        }
+
+        &self.text[start..self.index]
+    }
+
+    /// Scans a value; `key_start` is the position of its key when it is an object member to record.
+    fn scan_value(&mut self, path: &mut String, key_start: Option<(usize, usize)>) -> Option<()> {
+        self.skip_whitespace();
+
+        match self.peek()? {
+            b'{' => {
+                self.bump();
+                loop {
+                    self.skip_whitespace();
+                    match self.peek()? {
+                        b'}' => {
+                            self.bump();
+                            break;
+                        }
+                        b',' => self.bump(),
+                        b'"' => {
+                            let start = (self.line, self.column);
+                            let key = serde_json::from_str::<String>(self.skip_string()).ok()?;
+                            self.skip_whitespace();
+                            self.bump();
+
+                            let prefix_len = path.len();
+                            if prefix_len > 0 {
+                                path.push('.');
+                            }
+                            path.push_str(&key);
+                            self.scan_value(path, Some(start))?;
+                            path.truncate(prefix_len);
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            b'[' => {
+                // array items are not resources
+                self.bump();
+                loop {
+                    self.skip_whitespace();
+                    match self.peek()? {
+                        b']' => {
+                            self.bump();
+                            break;
+                        }
+                        b',' => self.bump(),
+                        _ => self.scan_value(path, None)?,
+                    }
+                }
+            }
+            b'"' => {
+                self.skip_string();
+                if let Some((line, column)) = key_start {
+                    let span = SourceSpan {
+                        file: String::new(),
+                        line,
+                        column,
+                        end_line: self.line,
+                        end_column: self.column,
+                    };
+                    self.spans.insert(path.clone(), span);
+                }
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|byte| !matches!(byte, b',' | b'}' | b']') && !byte.is_ascii_whitespace())
+                {
+                    self.bump();
+                }
+            }
+        }
+
+        Some(())
+    }
+}
+
+fn json_parse_error(error: serde_json::Error) -> ParseError {
+    // serde_json appends the position to its messages, it is part of the parse error instead
+    let message = error.to_string();
+    let position = format!(" at line {} column {}", error.line(), error.column());
+    let message = message.strip_suffix(&position).unwrap_or(&message);
+    ParseError::new(error.line(), error.column(), message)
+}
+
 fn json_to_string_pretty(value: &Map<String, Value>) -> String {
     let writer = Vec::new();
     let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
@@ -55,15 +206,15 @@ fn json_to_string_pretty(value: &Map<String, Value>) -> String {
     String::from_utf8(ser.into_inner()).unwrap_or_default()
 }
 
-impl FileFormat for JsonFileFormat {
-    const EXTENSION: &'static str = "json";
+impl JsonFileFormat {
+    pub(crate) const EXTENSION: &'static str = "json";
 
-    fn parse_from_str(&self, text: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
+    pub(crate) fn parse_from_str(text: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
         let mut resources: Vec<Resource> = Vec::new();
-        let root_value: Value = serde_json::from_str(text)?;
+        let root_value: Value = serde_json::from_str(text).map_err(json_parse_error)?;
         let root_object = match root_value.as_object() {
             Some(object) => object,
-            None => Err("json value is not an object")?,
+            None => Err(ParseError::new(1, 1, "json value is not an object"))?,
         };
 
         let mut path = String::new();
@@ -73,15 +224,15 @@ impl FileFormat for JsonFileFormat {
             flatten_json_value(value, &mut path, &mut resources);
         }
 
-        Ok(resources)
-    }
+        let mut spans = JsonSpanScanner::scan(text);
+        for resource in &mut resources {
+            resource.span = spans.remove(&resource.name);
+        }
 
-    fn parse_from_file(&self, filename: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
-        let text = load_string_from_file(filename)?;
-        self.parse_from_str(text.as_ref())
+        Ok(resources)
     }
 
-    fn write_to_str(&self, resources: &[Resource]) -> String {
+    pub(crate) fn write_to_str(resources: &[Resource]) -> String {
         let mut root_map: Map<String, Value> = Map::new();
 
         for resource in resources {
@@ -92,6 +243,24 @@ impl FileFormat for JsonFileFormat {
     }
 }
 
+impl FileFormat for JsonFileFormat {
+    fn name(&self) -> &str {
+        Self::EXTENSION
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[Self::EXTENSION]
+    }
+
+    fn parse_from_bytes(&self, bytes: &[u8]) -> Result<Vec<Resource>, Box<dyn Error>> {
+        Self::parse_from_str(decode_utf8_text(bytes)?)
+    }
+
+    fn write_to_bytes(&self, resources: &[Resource], output_encoding: OutputEncoding) -> Result<Vec<u8>, io::Error> {
+        Ok(encode_utf8_owned(Self::write_to_str(resources), output_encoding))
+    }
+}
+
 #[test]
 fn test_json_parse() {
     let text = r#"
@@ -111,9 +280,7 @@ fn test_json_parse() {
 }
     "#;
 
-    let file_format = JsonFileFormat {};
-
-    let resources = match file_format.parse_from_str(text) {
+    let resources = match JsonFileFormat::parse_from_str(text) {
         Ok(resources) => resources,
         Err(e) => panic!("json parse failed: {}", e),
     };
@@ -144,9 +311,21 @@ fn test_json_parse() {
 }
 
 #[test]
-fn test_json_write() {
-    let file_format = JsonFileFormat {};
+fn test_json_parse_spans() {
+    let text = "{\n    \"lblBoat\": \"I'm on a boat.\",\n    \"language\": {\n        \"en\": \"English\"\n    },\n    \"count\": 2\n}";
+
+    let resources = JsonFileFormat::parse_from_str(text).expect("json parse");
 
+    let span = resources[0].span.as_ref().expect("lblBoat span");
+    assert_eq!((span.line, span.column, span.end_line, span.end_column), (2, 5, 2, 32));
+
+    assert_eq!(resources[1].name, "language.en");
+    let span = resources[1].span.as_ref().expect("language.en span");
+    assert_eq!((span.line, span.column, span.end_line, span.end_column), (4, 9, 4, 24));
+}
+
+#[test]
+fn test_json_write() {
     let resources = vec![
         Resource::new("lblBoat", "I'm on a boat."),
         Resource::new("lblYolo", "You only live once"),
@@ -171,7 +350,7 @@ fn test_json_write() {
     }
 }"#;
 
-    let actual_text = file_format.write_to_str(&resources);
+    let actual_text = JsonFileFormat::write_to_str(&resources);
     //println!("{}", actual_text);
     //println!("{}", expected_text);
     assert_eq!(actual_text, expected_text);
@@ -181,7 +360,6 @@ fn test_json_write() {
 #[ignore = "benchmark: run manually with --ignored --nocapture"]
 #[allow(clippy::print_stdout)]
 fn benchmark_json_parse_and_write_large_input() {
-    let file_format = JsonFileFormat {};
     let repetitions = 5_000usize;
     let mut resources = Vec::with_capacity(repetitions * 6);
 
@@ -196,13 +374,12 @@ fn benchmark_json_parse_and_write_large_input() {
     }
 
     let started = Instant::now();
-    let written = file_format.write_to_str(&resources);
+    let written = JsonFileFormat::write_to_str(&resources);
     let write_elapsed = started.elapsed();
 
     let started = Instant::now();
-    let reparsed = file_format
-        .parse_from_str(&written)
-        .unwrap_or_else(|e| panic!("json benchmark parse failed: {}", e));
+    let reparsed =
+        JsonFileFormat::parse_from_str(&written).unwrap_or_else(|e| panic!("json benchmark parse failed: {}", e));
     let parse_elapsed = started.elapsed();
 
     assert_eq!(reparsed.len(), resources.len());
@@ -13,7 +13,7 @@ extern crate log;
 extern crate lazy_static;
 
 mod resource;
-pub use crate::resource::Resource;
+pub use crate::resource::{Resource, SourceSpan};
 
 mod triple;
 pub use crate::triple::Triple;
@@ -21,11 +21,65 @@ pub use crate::triple::Triple;
 pub mod config;
 
 mod json;
+mod resources;
 mod restext;
 mod resx;
 
+mod error;
+pub use crate::error::CirupError;
+
 mod file;
-pub use crate::file::OutputEncoding;
+pub use crate::file::{
+    FileFormat, OutputEncoding, PARSE_ERROR_CODE, ParseError, decode_utf8_text, encode_utf8_owned, format_by_name,
+    format_for_extension, formats, load_resources, parse_resources, register_format, resources_to_bytes,
+};
 mod query_backend;
 
+#[cfg(test)]
+mod test_util;
+
 pub mod query;
+
+pub mod placeholder;
+
+pub mod handoff;
+
+pub mod stale;
+
+pub mod tm;
+
+pub mod pseudo;
+
+pub mod usage;
+
+pub mod codegen;
+
+pub mod watch;
+
+pub mod batch;
+
+pub mod diagnostic;
+
+pub mod lint;
+
+pub mod coverage;
+
+pub mod sync;
+
+pub mod rename;
+
+pub mod edit;
+
+pub mod sort;
+
+pub mod merge;
+
+pub mod changelog;
+
+pub mod inline_diff;
+
+pub mod stats;
+
+pub mod inconsistencies;
+
+pub mod glossary;
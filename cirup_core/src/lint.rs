@@ -0,0 +1,398 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use serde::Serialize;
+
+use crate::Resource;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::file::{culture_from_filename, load_error, load_resource_file};
+use crate::glossary::{GlossaryTerm, contains_term, load_glossary};
+use crate::placeholder::{extract_placeholders, placeholders_match};
+use crate::query::{QueryRecord, QueryResult, QueryRunOptions, evaluate_records};
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * Lint rules, checked on every file, and between the base file and each translation:
+ * CIRUP001 placeholder mismatch, CIRUP002 duplicate key, CIRUP003 key not in the base file,
+ * CIRUP004 empty translation, CIRUP005 leading or trailing whitespace mismatch.
+ * With a glossary: CIRUP006 glossary term not translated as mandated, CIRUP007 do-not-translate term changed.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintRule {
+    PlaceholderMismatch,
+    DuplicateKey,
+    UnknownKey,
+    EmptyValue,
+    WhitespaceMismatch,
+    GlossaryTerm,
+    DoNotTranslate,
+}
+
+impl LintRule {
+    pub fn code(self) -> &'static str {
+        match self {
+            LintRule::PlaceholderMismatch => "CIRUP001",
+            LintRule::DuplicateKey => "CIRUP002",
+            LintRule::UnknownKey => "CIRUP003",
+            LintRule::EmptyValue => "CIRUP004",
+            LintRule::WhitespaceMismatch => "CIRUP005",
+            LintRule::GlossaryTerm => "CIRUP006",
+            LintRule::DoNotTranslate => "CIRUP007",
+        }
+    }
+
+    pub fn severity(self) -> Severity {
+        match self {
+            LintRule::PlaceholderMismatch
+            | LintRule::DuplicateKey
+            | LintRule::GlossaryTerm
+            | LintRule::DoNotTranslate => Severity::Error,
+            LintRule::UnknownKey | LintRule::EmptyValue | LintRule::WhitespaceMismatch => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintFinding {
+    pub file: String,
+    /// 1-based line of the key, or 0 when it could not be located.
+    pub line: usize,
+    pub column: usize,
+    pub name: String,
+    pub rule: LintRule,
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl QueryRecord for LintFinding {
+    const COLUMNS: &'static [&'static str] = &["file", "line", "name", "code", "severity", "message"];
+    const HAS_DIAGNOSTICS: bool = true;
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.file.clone(),
+            self.line.to_string(),
+            self.name.clone(),
+            self.code.to_owned(),
+            self.severity.as_str().to_owned(),
+            self.message.clone(),
+        ]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.name
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.message
+    }
+
+    fn diagnostic(&self) -> Option<Diagnostic> {
+        Some(Diagnostic {
+            file: self.file.clone(),
+            line: self.line,
+            column: self.column,
+            severity: self.severity,
+            code: self.code.to_owned(),
+            message: format!("{}: {}", self.name, self.message),
+        })
+    }
+}
+
+fn edge_whitespace(value: &str) -> (bool, bool) {
+    (
+        value.starts_with(char::is_whitespace),
+        value.ends_with(char::is_whitespace),
+    )
+}
+
+/// Collects the findings of one file, at the position of their resource.
+struct FileLint<'a> {
+    file: &'a str,
+    findings: Vec<LintFinding>,
+}
+
+impl<'a> FileLint<'a> {
+    fn new(file: &'a str) -> Self {
+        Self {
+            file,
+            findings: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, resource: &Resource, rule: LintRule, message: String) {
+        // resources of binary formats have no span, their findings apply to the whole file
+        let (line, column) = resource.span.as_ref().map_or((0, 0), |span| (span.line, span.column));
+        self.findings.push(LintFinding {
+            file: self.file.to_owned(),
+            line,
+            column,
+            name: resource.name.clone(),
+            rule,
+            code: rule.code(),
+            severity: rule.severity(),
+            message,
+        });
+    }
+
+    fn check_duplicates(&mut self, resources: &[Resource]) {
+        let mut seen = HashSet::new();
+        for resource in resources {
+            if !seen.insert(resource.name.as_str()) {
+                self.push(
+                    resource,
+                    LintRule::DuplicateKey,
+                    String::from("key is defined more than once"),
+                );
+            }
+        }
+    }
+
+    fn check_translation(&mut self, base: &HashMap<&str, &str>, base_file: &str, resources: &[Resource]) {
+        for resource in resources {
+            let name = resource.name.as_str();
+            let value = resource.value.as_str();
+
+            let Some(base_value) = base.get(name) else {
+                self.push(
+                    resource,
+                    LintRule::UnknownKey,
+                    format!("key is not defined in {}", base_file),
+                );
+                continue;
+            };
+
+            if value.is_empty() {
+                if !base_value.is_empty() {
+                    self.push(resource, LintRule::EmptyValue, String::from("translation is empty"));
+                }
+                continue;
+            }
+
+            if !placeholders_match(base_value, value) {
+                self.push(
+                    resource,
+                    LintRule::PlaceholderMismatch,
+                    format!(
+                        "placeholders [{}] do not match [{}] in {}",
+                        extract_placeholders(value).join(", "),
+                        extract_placeholders(base_value).join(", "),
+                        base_file
+                    ),
+                );
+            }
+
+            if edge_whitespace(base_value) != edge_whitespace(value) {
+                self.push(
+                    resource,
+                    LintRule::WhitespaceMismatch,
+                    format!("leading or trailing whitespace differs from {}", base_file),
+                );
+            }
+        }
+    }
+
+    /// Checks that the translations of source values containing a glossary term contain its mandated translation
+    /// for `language`, and do-not-translate terms verbatim.
+    fn check_glossary(
+        &mut self,
+        base: &HashMap<&str, &str>,
+        glossary: &[GlossaryTerm],
+        language: Option<&str>,
+        resources: &[Resource],
+    ) {
+        for resource in resources {
+            let value = resource.value.as_str();
+            let Some(base_value) = base.get(resource.name.as_str()).filter(|_| !value.is_empty()) else {
+                continue;
+            };
+
+            for term in glossary {
+                if term.do_not_translate {
+                    if contains_term(base_value, &term.source, true) && !contains_term(value, &term.source, true) {
+                        self.push(
+                            resource,
+                            LintRule::DoNotTranslate,
+                            format!("'{}' must not be translated", term.source),
+                        );
+                    }
+                    continue;
+                }
+
+                let Some(target) = language.and_then(|language| term.target(language)) else {
+                    continue;
+                };
+                if contains_term(base_value, &term.source, false) && !contains_term(value, target, false) {
+                    self.push(
+                        resource,
+                        LintRule::GlossaryTerm,
+                        format!("'{}' must be translated as '{}'", term.source, target),
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub struct LintQuery {
+    base: String,
+    targets: Vec<String>,
+    glossary: Option<String>,
+}
+
+/// Lints `base` and each of `targets` against it.
+pub fn query_lint(base: &str, targets: &[&str]) -> LintQuery {
+    LintQuery {
+        base: base.to_owned(),
+        targets: targets.iter().map(|target| (*target).to_owned()).collect(),
+        glossary: None,
+    }
+}
+
+fn load(file: &str) -> Result<Vec<Resource>, io::Error> {
+    load_resource_file(file).map_err(|e| load_error(file, e))
+}
+
+impl LintQuery {
+    /// Checks the translations against the terms of a `.csv` or `.tbx` glossary, in the language
+    /// of the culture suffix of each target file.
+    #[must_use]
+    pub fn with_glossary(mut self, glossary: Option<&str>) -> Self {
+        self.glossary = glossary.map(str::to_owned);
+        self
+    }
+
+    pub fn run(&self) -> Result<Vec<LintFinding>, io::Error> {
+        let glossary = self.glossary.as_deref().map(load_glossary).transpose()?;
+        let base_resources = load(&self.base)?;
+        let mut base_lint = FileLint::new(&self.base);
+        base_lint.check_duplicates(&base_resources);
+        let mut findings = base_lint.findings;
+
+        let base = base_resources
+            .iter()
+            .map(|resource| (resource.name.as_str(), resource.value.as_str()))
+            .collect::<HashMap<_, _>>();
+
+        for target in &self.targets {
+            let resources = load(target)?;
+            let mut lint = FileLint::new(target);
+            lint.check_duplicates(&resources);
+            lint.check_translation(&base, &self.base, &resources);
+            if let Some(glossary) = &glossary {
+                let language = culture_from_filename(target);
+                lint.check_glossary(&base, glossary, language.as_deref(), &resources);
+            }
+            findings.extend(lint.findings);
+        }
+
+        Ok(findings)
+    }
+
+    pub fn evaluate_with_options(&self, options: &QueryRunOptions) -> Result<QueryResult<LintFinding>, io::Error> {
+        evaluate_records(self.run()?, "lint-finding", None, false, options)
+    }
+}
+
+#[test]
+fn test_lint_reports_findings_with_positions() {
+    use std::fs;
+
+    let files = TempFiles::new();
+
+    fs::write(
+        files.path("Strings.restext"),
+        "lblHello=Hello {0}\r\nlblEmpty=Empty\r\nlblSpace=Space \r\nlblHello=Hello again {0}\r\n",
+    )
+    .expect("failed to write base");
+    fs::write(
+        files.path("Strings.fr.restext"),
+        "lblHello=Bonjour\r\nlblEmpty=\r\nlblSpace=Espace\r\nlblExtra=Extra\r\n",
+    )
+    .expect("failed to write target");
+
+    let findings = query_lint(
+        &files.path("Strings.restext"),
+        &[files.path("Strings.fr.restext").as_str()],
+    )
+    .run()
+    .expect("lint failed");
+    let summary = findings
+        .iter()
+        .map(|finding| {
+            (
+                finding.file.rsplit(['/', '\\']).next().unwrap_or_default(),
+                finding.line,
+                finding.code,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        summary,
+        vec![
+            ("Strings.restext", 4, "CIRUP002"),
+            ("Strings.fr.restext", 1, "CIRUP001"),
+            ("Strings.fr.restext", 2, "CIRUP004"),
+            ("Strings.fr.restext", 3, "CIRUP005"),
+            ("Strings.fr.restext", 4, "CIRUP003"),
+        ]
+    );
+
+    let diagnostic = findings[1].diagnostic().expect("lint findings have diagnostics");
+    assert_eq!(
+        diagnostic.render(crate::diagnostic::DiagnosticFormat::Msbuild),
+        format!(
+            "{}(1,1): error CIRUP001: lblHello: placeholders [] do not match [{{0}}] in {}",
+            files.path("Strings.fr.restext"),
+            files.path("Strings.restext")
+        )
+    );
+}
+
+#[test]
+fn test_lint_checks_glossary_terms() {
+    use std::fs;
+
+    let files = TempFiles::new();
+
+    fs::write(
+        files.path("glossary.csv"),
+        "en,fr,do-not-translate\r\nData Source,Source de données,\r\nDevolutions,,yes\r\n",
+    )
+    .expect("failed to write glossary");
+    fs::write(
+        files.path("Strings.restext"),
+        "lblSource=Data source\r\nlblOther=Other data source\r\nlblBrand=Devolutions Server\r\nlblDatabase=Database\r\n",
+    )
+    .expect("failed to write base");
+    fs::write(
+        files.path("Strings.fr-CA.restext"),
+        "lblSource=Source de données\r\nlblOther=Autre origine\r\nlblBrand=Serveur Dévolutions\r\nlblDatabase=Base de données\r\n",
+    )
+    .expect("failed to write target");
+
+    let findings = query_lint(
+        &files.path("Strings.restext"),
+        &[files.path("Strings.fr-CA.restext").as_str()],
+    )
+    .with_glossary(Some(&files.path("glossary.csv")))
+    .run()
+    .expect("lint failed");
+    let summary = findings
+        .iter()
+        .map(|finding| (finding.line, finding.code, finding.message.as_str()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        summary,
+        vec![
+            (2, "CIRUP006", "'Data Source' must be translated as 'Source de données'"),
+            (3, "CIRUP007", "'Devolutions' must not be translated"),
+        ]
+    );
+}
@@ -0,0 +1,285 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use serde::Serialize;
+
+use crate::Resource;
+use crate::file::{OutputEncoding, load_resources, output_bytes_for_file};
+use crate::query::{QueryResult, QueryRunOptions, RecordOutput, evaluate_records_with_merge};
+
+/*
+ * Merge strategies of file-merge, the default one adding the new keys of the second file
+ * and taking its value for the others. The merged file keeps the key order of the first file,
+ * followed by the added keys in the order of the second.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeStrategy {
+    /// Adds the new keys and takes the value of the second file, as the merge query.
+    #[default]
+    PreferRight,
+    /// Adds the new keys and keeps the value of the first file, unless it is empty.
+    PreferLeft,
+    /// Takes the value of the second file for existing keys and never adds keys.
+    UpdateOnly,
+    /// Adds the new keys and never changes existing values.
+    AddOnly,
+}
+
+impl MergeStrategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MergeStrategy::PreferRight => "prefer-right",
+            MergeStrategy::PreferLeft => "prefer-left",
+            MergeStrategy::UpdateOnly => "update-only",
+            MergeStrategy::AddOnly => "add-only",
+        }
+    }
+}
+
+/// What a merge did, reported in the summary.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MergeSummary {
+    pub strategy: MergeStrategy,
+    pub skip_empty: bool,
+    /// The file whose keys are kept.
+    pub base: Option<String>,
+    /// Keys of the second file added to the first.
+    pub added: usize,
+    /// Values of the first file replaced by the value of the second.
+    pub updated: usize,
+    /// Keys and values of the second file that the strategy did not apply.
+    pub skipped: usize,
+    /// Keys of the first file dropped because they are not in the base file.
+    pub removed: usize,
+}
+
+pub struct FileMerge {
+    file_one: String,
+    file_two: String,
+    strategy: MergeStrategy,
+    skip_empty: bool,
+    base: Option<String>,
+}
+
+/// Merges the keys and values of `file_two` into `file_one`.
+pub fn merge_files(file_one: &str, file_two: &str) -> FileMerge {
+    FileMerge {
+        file_one: file_one.to_owned(),
+        file_two: file_two.to_owned(),
+        strategy: MergeStrategy::default(),
+        skip_empty: false,
+        base: None,
+    }
+}
+
+/// Merges `b` into `a`, returning the merged resources and what the merge did. `base` restricts the keys
+/// of the result to its keys.
+pub fn merge_resources(
+    a: &[Resource],
+    b: &[Resource],
+    strategy: MergeStrategy,
+    skip_empty: bool,
+    base: Option<&[Resource]>,
+) -> (Vec<Resource>, MergeSummary) {
+    let mut summary = MergeSummary {
+        strategy,
+        skip_empty,
+        ..MergeSummary::default()
+    };
+
+    let base_keys = base.map(|base| {
+        base.iter()
+            .map(|resource| resource.name.as_str())
+            .collect::<HashSet<_>>()
+    });
+    let in_base = |name: &str| base_keys.as_ref().is_none_or(|keys| keys.contains(name));
+
+    let mut b_values = HashMap::with_capacity(b.len());
+    for resource in b {
+        b_values
+            .entry(resource.name.as_str())
+            .or_insert(resource.value.as_str());
+    }
+
+    let mut merged = Vec::with_capacity(a.len());
+    for resource in a {
+        if !in_base(&resource.name) {
+            summary.removed += 1;
+            continue;
+        }
+
+        let mut resource = resource.clone();
+        match b_values.get(resource.name.as_str()) {
+            Some(value) if *value == resource.value => {}
+            Some(value) if skip_empty && value.is_empty() => summary.skipped += 1,
+            Some(value) => {
+                let update = match strategy {
+                    MergeStrategy::PreferRight | MergeStrategy::UpdateOnly => true,
+                    MergeStrategy::PreferLeft => resource.value.is_empty(),
+                    MergeStrategy::AddOnly => false,
+                };
+
+                if update {
+                    resource.value = (*value).to_owned();
+                    summary.updated += 1;
+                } else {
+                    summary.skipped += 1;
+                }
+            }
+            None => {}
+        }
+        merged.push(resource);
+    }
+
+    let a_keys = a.iter().map(|resource| resource.name.as_str()).collect::<HashSet<_>>();
+    let mut added_keys = HashSet::new();
+    for resource in b {
+        if a_keys.contains(resource.name.as_str()) || !added_keys.insert(resource.name.as_str()) {
+            continue;
+        }
+
+        if strategy == MergeStrategy::UpdateOnly
+            || (skip_empty && resource.value.is_empty())
+            || !in_base(&resource.name)
+        {
+            summary.skipped += 1;
+        } else {
+            merged.push(resource.clone());
+            summary.added += 1;
+        }
+    }
+
+    (merged, summary)
+}
+
+impl FileMerge {
+    #[must_use]
+    pub fn with_strategy(mut self, strategy: MergeStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Ignores the empty values of the second file, so that they neither add keys nor clear values.
+    #[must_use]
+    pub fn with_skip_empty(mut self, skip_empty: bool) -> Self {
+        self.skip_empty = skip_empty;
+        self
+    }
+
+    /// Drops the keys that are not in `base` from the merged file.
+    #[must_use]
+    pub fn with_base(mut self, base: Option<&str>) -> Self {
+        self.base = base.map(str::to_owned);
+        self
+    }
+
+    pub fn run(&self) -> Result<(Vec<Resource>, MergeSummary), io::Error> {
+        let a = load_resources(&self.file_one)?;
+        let b = load_resources(&self.file_two)?;
+        let base = self.base.as_deref().map(load_resources).transpose()?;
+
+        let (merged, mut summary) = merge_resources(&a, &b, self.strategy, self.skip_empty, base.as_deref());
+        summary.base.clone_from(&self.base);
+        Ok((merged, summary))
+    }
+
+    /// Returns the merged resources and the file to write to `out_file`, with the merge counts in the report.
+    pub fn evaluate_with_options(
+        &self,
+        out_file: Option<&str>,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<Resource>, io::Error> {
+        if let Some(out_file) = out_file {
+            output_bytes_for_file(out_file, &[], output_encoding)?;
+        }
+
+        let (merged, summary) = self.run()?;
+        info!(
+            "file-merge ({}): {} added, {} updated, {} skipped, {} removed",
+            summary.strategy.as_str(),
+            summary.added,
+            summary.updated,
+            summary.skipped,
+            summary.removed
+        );
+
+        let output = out_file.map(|path| RecordOutput {
+            path,
+            render: Box::new(move |resources: &[Resource]| output_bytes_for_file(path, resources, output_encoding)),
+            print_records: false,
+        });
+
+        evaluate_records_with_merge(merged, "resource", output, touch, options, Some(summary))
+    }
+}
+
+#[test]
+fn test_merge_strategies() {
+    let a = vec![
+        Resource::new("lblOne", "One"),
+        Resource::new("lblTwo", ""),
+        Resource::new("lblThree", "Three"),
+    ];
+    let b = vec![
+        Resource::new("lblThree", "Trois"),
+        Resource::new("lblTwo", "Deux"),
+        Resource::new("lblOne", ""),
+        Resource::new("lblFour", "Quatre"),
+        Resource::new("lblFive", ""),
+    ];
+    let merge = |strategy: MergeStrategy, skip_empty: bool| {
+        let (merged, summary) = merge_resources(&a, &b, strategy, skip_empty, None);
+        let values = merged
+            .into_iter()
+            .map(|resource| format!("{}={}", resource.name, resource.value))
+            .collect::<Vec<_>>();
+        (values, (summary.added, summary.updated, summary.skipped))
+    };
+
+    assert_eq!(
+        merge(MergeStrategy::PreferRight, false),
+        (
+            vec!["lblOne=", "lblTwo=Deux", "lblThree=Trois", "lblFour=Quatre", "lblFive="]
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+            (2, 3, 0)
+        )
+    );
+    assert_eq!(
+        merge(MergeStrategy::PreferRight, true).1,
+        (1, 2, 2),
+        "empty values of b are skipped"
+    );
+    assert_eq!(
+        merge(MergeStrategy::PreferLeft, false).0[..3],
+        ["lblOne=One", "lblTwo=Deux", "lblThree=Three"]
+    );
+    assert_eq!(merge(MergeStrategy::PreferLeft, false).1, (2, 1, 2));
+    assert_eq!(merge(MergeStrategy::UpdateOnly, true).0.len(), 3);
+    assert_eq!(merge(MergeStrategy::UpdateOnly, true).1, (0, 2, 3));
+    assert_eq!(
+        merge(MergeStrategy::AddOnly, false).0[..3],
+        ["lblOne=One", "lblTwo=", "lblThree=Three"]
+    );
+    assert_eq!(merge(MergeStrategy::AddOnly, false).1, (2, 0, 3));
+}
+
+#[test]
+fn test_merge_drops_keys_not_in_base() {
+    let a = vec![Resource::new("lblOne", "One"), Resource::new("lblOld", "Old")];
+    let b = vec![Resource::new("lblTwo", "Two"), Resource::new("lblExtra", "Extra")];
+    let base = vec![Resource::new("lblOne", "Un"), Resource::new("lblTwo", "Deux")];
+
+    let (merged, summary) = merge_resources(&a, &b, MergeStrategy::PreferRight, false, Some(&base));
+
+    assert_eq!(
+        merged,
+        vec![Resource::new("lblOne", "One"), Resource::new("lblTwo", "Two")]
+    );
+    assert_eq!((summary.added, summary.skipped, summary.removed), (1, 1, 1));
+}
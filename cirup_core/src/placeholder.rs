@@ -0,0 +1,154 @@
+/*
+ * Placeholder detection for translated values:
+ * .NET composite format items: {0}, {0:N2}, {1,-10}
+ * named and double-brace placeholders: {name}, {{name}}
+ * printf-style conversions: %s, %d, %1$s, %.2f, %@
+ */
+
+const PRINTF_FLAGS: &[char] = &['-', '+', '#', '0'];
+const PRINTF_LENGTH_MODIFIERS: &[char] = &['h', 'l', 'z', 'j', 't', 'L', 'q'];
+const PRINTF_CONVERSIONS: &[char] = &[
+    's', 'd', 'i', 'u', 'f', 'F', 'e', 'E', 'g', 'G', 'x', 'X', 'o', 'c', 'p', 'n', 'a', 'A', '@',
+];
+
+fn brace_placeholder_len(text: &str) -> Option<usize> {
+    if let Some(rest) = text.strip_prefix("{{") {
+        let end = rest.find("}}")?;
+        let inner = &rest[..end];
+        if inner.is_empty() || inner.contains('{') || inner.contains('}') {
+            return None;
+        }
+        return Some(end + 4);
+    }
+
+    let end = text[1..].find('}')?;
+    let inner = &text[1..=end];
+    if inner.is_empty() || inner.contains('{') || inner.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    Some(end + 2)
+}
+
+fn printf_placeholder_len(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().skip(1).peekable();
+
+    let mut saw_digits = false;
+    while let Some((_, ch)) = chars.peek() {
+        if ch.is_ascii_digit() {
+            saw_digits = true;
+            chars.next();
+        } else {
+            break;
+        }
+    }
+
+    if saw_digits && matches!(chars.peek(), Some((_, '$'))) {
+        chars.next();
+    }
+
+    while matches!(chars.peek(), Some((_, ch)) if PRINTF_FLAGS.contains(ch)) {
+        chars.next();
+    }
+
+    while matches!(chars.peek(), Some((_, ch)) if ch.is_ascii_digit()) {
+        chars.next();
+    }
+
+    if matches!(chars.peek(), Some((_, '.'))) {
+        chars.next();
+        while matches!(chars.peek(), Some((_, ch)) if ch.is_ascii_digit()) {
+            chars.next();
+        }
+    }
+
+    while matches!(chars.peek(), Some((_, ch)) if PRINTF_LENGTH_MODIFIERS.contains(ch)) {
+        chars.next();
+    }
+
+    let (index, conversion) = chars.next()?;
+    if !PRINTF_CONVERSIONS.contains(&conversion) {
+        return None;
+    }
+
+    Some(index + conversion.len_utf8())
+}
+
+/// Returns the byte length of the placeholder starting at the beginning of `text`, if any.
+pub(crate) fn placeholder_len_at(text: &str) -> Option<usize> {
+    match text.chars().next()? {
+        '{' => brace_placeholder_len(text),
+        '%' if text[1..].starts_with('%') => None,
+        '%' => printf_placeholder_len(text),
+        _ => None,
+    }
+}
+
+/// Extracts placeholders from `text` in order of appearance.
+pub fn extract_placeholders(text: &str) -> Vec<&str> {
+    let mut placeholders = Vec::new();
+    let mut index = 0usize;
+
+    while index < text.len() {
+        let rest = &text[index..];
+        let Some(ch) = rest.chars().next() else {
+            break;
+        };
+
+        if ch == '%' && rest[1..].starts_with('%') {
+            index += 2;
+            continue;
+        }
+
+        if let Some(len) = placeholder_len_at(rest) {
+            placeholders.push(&rest[..len]);
+            index += len;
+        } else {
+            index += ch.len_utf8();
+        }
+    }
+
+    placeholders
+}
+
+/// Returns true when both values use the same placeholders, regardless of order.
+pub fn placeholders_match(source: &str, translation: &str) -> bool {
+    let mut expected = extract_placeholders(source);
+    let mut actual = extract_placeholders(translation);
+    expected.sort_unstable();
+    actual.sort_unstable();
+    expected == actual
+}
+
+#[test]
+fn test_extract_dotnet_placeholders() {
+    assert_eq!(
+        extract_placeholders("Hello {0}, you have {1:N0} new {2,-5} items"),
+        vec!["{0}", "{1:N0}", "{2,-5}"]
+    );
+}
+
+#[test]
+fn test_extract_named_and_double_brace_placeholders() {
+    assert_eq!(
+        extract_placeholders("Welcome {userName} to {{product}}"),
+        vec!["{userName}", "{{product}}"]
+    );
+    assert!(extract_placeholders("a { b } c").is_empty());
+}
+
+#[test]
+fn test_extract_printf_placeholders() {
+    assert_eq!(
+        extract_placeholders("%s has %d files (%.2f%%) %1$s %@"),
+        vec!["%s", "%d", "%.2f", "%1$s", "%@"]
+    );
+    assert!(extract_placeholders("100% sure").is_empty());
+}
+
+#[test]
+fn test_placeholders_match_ignores_order() {
+    assert!(placeholders_match("{0} of {1}", "{1} de {0}"));
+    assert!(!placeholders_match("{0} of {1}", "{0} de"));
+    assert!(!placeholders_match("%s", "%d"));
+}
@@ -0,0 +1,287 @@
+use std::io;
+
+use crate::Resource;
+use crate::file::{OutputEncoding, load_error, load_resource_file, output_bytes_for_file};
+use crate::placeholder::placeholder_len_at;
+use crate::query::{QueryResult, QueryRunOptions, RecordOutput, evaluate_records};
+
+/*
+ * Pseudo-localization: letters are replaced with accented look-alikes, values are
+ * padded to simulate longer translations and wrapped in brackets to reveal truncation.
+ * Placeholders, HTML tags, character entities and backslash escapes are kept verbatim.
+ */
+
+pub const DEFAULT_EXPANSION: u16 = 30;
+
+const PADDING_CHAR: char = '~';
+
+fn accented(ch: char) -> char {
+    match ch {
+        'a' => 'å',
+        'b' => 'ƀ',
+        'c' => 'ç',
+        'd' => 'ð',
+        'e' => 'é',
+        'f' => 'ƒ',
+        'g' => 'ĝ',
+        'h' => 'ĥ',
+        'i' => 'î',
+        'j' => 'ĵ',
+        'k' => 'ķ',
+        'l' => 'ļ',
+        'm' => 'ɱ',
+        'n' => 'ñ',
+        'o' => 'ö',
+        'p' => 'þ',
+        'q' => 'ǫ',
+        'r' => 'ŕ',
+        's' => 'š',
+        't' => 'ţ',
+        'u' => 'û',
+        'v' => 'ṽ',
+        'w' => 'ŵ',
+        'x' => 'ẋ',
+        'y' => 'ý',
+        'z' => 'ž',
+        'A' => 'Å',
+        'B' => 'Ɓ',
+        'C' => 'Ç',
+        'D' => 'Ð',
+        'E' => 'É',
+        'F' => 'Ƒ',
+        'G' => 'Ĝ',
+        'H' => 'Ĥ',
+        'I' => 'Î',
+        'J' => 'Ĵ',
+        'K' => 'Ķ',
+        'L' => 'Ļ',
+        'M' => 'Ṁ',
+        'N' => 'Ñ',
+        'O' => 'Ö',
+        'P' => 'Þ',
+        'Q' => 'Ǫ',
+        'R' => 'Ŕ',
+        'S' => 'Š',
+        'T' => 'Ţ',
+        'U' => 'Û',
+        'V' => 'Ṽ',
+        'W' => 'Ŵ',
+        'X' => 'Ẋ',
+        'Y' => 'Ý',
+        'Z' => 'Ž',
+        _ => ch,
+    }
+}
+
+fn html_tag_len(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix('<')?;
+    let first = rest.chars().next()?;
+    if !(first.is_ascii_alphabetic() || first == '/' || first == '!') {
+        return None;
+    }
+
+    let end = rest.find('>')?;
+    if rest[..end].contains('<') {
+        return None;
+    }
+
+    Some(end + 2)
+}
+
+fn entity_len(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix('&')?;
+    let end = rest.find(';')?;
+    let name = &rest[..end];
+    let valid = match name.strip_prefix('#') {
+        Some(number) => match number.strip_prefix(['x', 'X']) {
+            Some(hex) => !hex.is_empty() && hex.chars().all(|ch| ch.is_ascii_hexdigit()),
+            None => !number.is_empty() && number.chars().all(|ch| ch.is_ascii_digit()),
+        },
+        None => !name.is_empty() && name.chars().all(|ch| ch.is_ascii_alphanumeric()),
+    };
+
+    valid.then_some(end + 2)
+}
+
+fn escape_len(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix('\\')?;
+    let next = rest.chars().next()?;
+
+    if next == 'u'
+        && rest
+            .get(1..5)
+            .is_some_and(|hex| hex.chars().all(|ch| ch.is_ascii_hexdigit()))
+    {
+        return Some(6);
+    }
+
+    Some(1 + next.len_utf8())
+}
+
+/// Returns the byte length of the text at the start of `text` that must not be pseudo-localized.
+fn protected_len(text: &str) -> Option<usize> {
+    match text.chars().next()? {
+        '{' | '%' => placeholder_len_at(text),
+        '<' => html_tag_len(text),
+        '&' => entity_len(text),
+        '\\' => escape_len(text),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PseudoOptions {
+    /// Length expansion of the translatable text, in percent.
+    pub expansion: u16,
+    pub brackets: bool,
+}
+
+impl Default for PseudoOptions {
+    fn default() -> Self {
+        Self {
+            expansion: DEFAULT_EXPANSION,
+            brackets: true,
+        }
+    }
+}
+
+/// Pseudo-localizes `text`. Empty values are left empty.
+pub fn pseudo_localize(text: &str, options: &PseudoOptions) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+
+    let mut output = String::with_capacity(text.len() * 2);
+    let mut translatable = 0usize;
+    let mut index = 0usize;
+
+    if options.brackets {
+        output.push('[');
+    }
+
+    while index < text.len() {
+        let rest = &text[index..];
+
+        if rest.starts_with("%%") {
+            output.push_str("%%");
+            index += 2;
+            continue;
+        }
+
+        if let Some(len) = protected_len(rest) {
+            output.push_str(&rest[..len]);
+            index += len;
+            continue;
+        }
+
+        let Some(ch) = rest.chars().next() else {
+            break;
+        };
+
+        if ch.is_alphanumeric() {
+            translatable += 1;
+        }
+
+        output.push(accented(ch));
+        index += ch.len_utf8();
+    }
+
+    let padding = (translatable * usize::from(options.expansion)).div_ceil(100);
+    output.extend(std::iter::repeat_n(PADDING_CHAR, padding));
+
+    if options.brackets {
+        output.push(']');
+    }
+
+    output
+}
+
+pub struct PseudoQuery {
+    file: String,
+    options: PseudoOptions,
+}
+
+/// Pseudo-localizes the values of `file`.
+pub fn query_pseudo(file: &str, options: PseudoOptions) -> PseudoQuery {
+    PseudoQuery {
+        file: file.to_owned(),
+        options,
+    }
+}
+
+impl PseudoQuery {
+    pub fn run(&self) -> Result<Vec<Resource>, io::Error> {
+        let resources = load_resource_file(&self.file).map_err(|e| load_error(&self.file, e))?;
+
+        Ok(resources
+            .into_iter()
+            .map(|resource| Resource {
+                value: pseudo_localize(&resource.value, &self.options),
+                ..resource
+            })
+            .collect())
+    }
+
+    /// Renders the pseudo-localized values to write to `out_file`, in the format given by its extension.
+    pub fn evaluate_with_options(
+        &self,
+        out_file: &str,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<Resource>, io::Error> {
+        let resources = self.run()?;
+        output_bytes_for_file(out_file, &[], output_encoding)?;
+
+        let output = RecordOutput {
+            path: out_file,
+            render: Box::new(move |resources: &[Resource]| output_bytes_for_file(out_file, resources, output_encoding)),
+            print_records: false,
+        };
+
+        evaluate_records(resources, "resource", Some(output), touch, options)
+    }
+}
+
+#[test]
+fn test_pseudo_localize_accents_and_brackets() {
+    let options = PseudoOptions {
+        expansion: 0,
+        brackets: true,
+    };
+    assert_eq!(pseudo_localize("Save file", &options), "[Šåṽé ƒîļé]");
+    assert_eq!(pseudo_localize("", &options), "");
+}
+
+#[test]
+fn test_pseudo_localize_expansion() {
+    let options = PseudoOptions {
+        expansion: 50,
+        brackets: false,
+    };
+    assert_eq!(pseudo_localize("Open", &options), "Öþéñ~~");
+
+    let options = PseudoOptions {
+        expansion: 30,
+        brackets: false,
+    };
+    assert_eq!(pseudo_localize("Open", &options), "Öþéñ~~");
+}
+
+#[test]
+fn test_pseudo_localize_keeps_placeholders_markup_and_escapes() {
+    let options = PseudoOptions {
+        expansion: 0,
+        brackets: false,
+    };
+    assert_eq!(
+        pseudo_localize("Hi {0}, <b>%s</b> &amp; 100%% done\\n", &options),
+        "Ĥî {0}, <b>%s</b> &amp; 100%% ðöñé\\n"
+    );
+    assert_eq!(
+        pseudo_localize("<a href=\"x\">{name}</a> \\u00e9", &options),
+        "<a href=\"x\">{name}</a> \\u00e9"
+    );
+    assert_eq!(pseudo_localize("a < b", &options), "å < ƀ");
+    assert_eq!(pseudo_localize("x \\uab€", &options), "ẋ \\uåƀ€");
+}
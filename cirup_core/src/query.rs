@@ -1,18 +1,29 @@
 #![allow(clippy::self_named_module_files)]
 
+use std::collections::HashMap;
+use std::fs;
 use std::io;
 #[cfg(test)]
 use std::time::Instant;
 
 use unicode_width::UnicodeWidthStr;
 
+use crate::CirupError;
 use crate::config::{QueryBackendKind, QueryConfig};
+use crate::diagnostic::{Diagnostic, DiagnosticFormat, Severity, render_diagnostics};
+#[cfg(test)]
+use crate::file::vfile_set;
 use crate::file::{
-    OutputEncoding, save_resource_file, save_resource_file_with_encoding, would_save_resource_file_with_encoding,
+    OutputEncoding, load_error, load_resource_file, load_resources, output_bytes_for_file, read_resources,
+    save_resource_file, save_resource_file_with_encoding, would_write_output_bytes,
 };
+use crate::inline_diff::{DiffGranularity, DiffSpan, diff_values, render_inline};
+use crate::merge::MergeSummary;
 use crate::query_backend::{QueryBackend, build_backend};
+#[cfg(test)]
+use crate::test_util::TempFiles;
 
-use crate::{Resource, Triple};
+use crate::{Resource, SourceSpan, Triple};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum QueryOutputFormat {
@@ -35,6 +46,12 @@ pub struct QueryRunOptions {
     pub operation_name: Option<String>,
     pub input_files: Vec<String>,
     pub output_file: Option<String>,
+    /// Prints the records as diagnostics instead of rows, for records that have one.
+    pub diagnostic_format: Option<DiagnosticFormat>,
+    /// Colors the table output with ANSI escapes, e.g. the inline diff of changed values.
+    pub color: bool,
+    /// Granularity of the inline diff of changed values.
+    pub diff_granularity: DiffGranularity,
 }
 
 impl QueryRunOptions {
@@ -483,12 +500,24 @@ fn wrap_resource_query_with_filters(query: &str, filters: &CompiledQueryFilters)
         return query.to_owned();
     }
 
-    let mut wrapped = format!(
-        "WITH filtered(key, val) AS ({query}) SELECT key, val FROM filtered WHERE {}",
-        conditions.join(" AND ")
-    );
+    let canonical = canonical_sql(query);
+    // the diff and change queries also select the value in `b`, after the key and value
+    let mut wrapped = if [DIFF_QUERY, CHANGE_QUERY]
+        .iter()
+        .any(|three_columns| canonical == canonical_sql(three_columns))
+    {
+        format!(
+            "WITH filtered(key, val, other_val) AS ({query}) SELECT key, val FROM filtered WHERE {}",
+            conditions.join(" AND ")
+        )
+    } else {
+        format!(
+            "WITH filtered(key, val) AS ({query}) SELECT key, val FROM filtered WHERE {}",
+            conditions.join(" AND ")
+        )
+    };
 
-    if canonical_sql(query) == canonical_sql(SORT_QUERY) {
+    if canonical == canonical_sql(SORT_QUERY) {
         wrapped.push_str(" ORDER BY key");
     }
 
@@ -547,6 +576,9 @@ pub struct QueryExecutionReport {
     pub would_write: bool,
     pub wrote_output: bool,
     pub change_detected: bool,
+    /// The strategy and counts of a merge with strategy options.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge: Option<MergeSummary>,
 }
 
 impl QueryExecutionReport {
@@ -577,6 +609,7 @@ impl QueryExecutionReport {
             would_write,
             wrote_output,
             change_detected,
+            merge: None,
         }
     }
 
@@ -593,7 +626,7 @@ fn ensure_trailing_newline(mut text: String) -> String {
     text
 }
 
-fn ascii_table_border<const N: usize>(output: &mut String, widths: &[usize; N]) {
+fn ascii_table_border(output: &mut String, widths: &[usize]) {
     output.push('+');
     for width in widths {
         for _ in 0..(*width + 2) {
@@ -604,12 +637,12 @@ fn ascii_table_border<const N: usize>(output: &mut String, widths: &[usize; N])
     output.push('\n');
 }
 
-fn ascii_table_row<const N: usize>(output: &mut String, widths: &[usize; N], cells: [&str; N]) {
+fn ascii_table_row(output: &mut String, widths: &[usize], cells: &[&str]) {
     output.push('|');
-    for (width, cell) in widths.iter().zip(cells) {
+    for (width, cell) in widths.iter().zip(cells.iter().copied()) {
         output.push(' ');
         output.push_str(cell);
-        let padding = width.saturating_sub(UnicodeWidthStr::width(cell)) + 1;
+        let padding = width.saturating_sub(display_width(cell)) + 1;
         for _ in 0..padding {
             output.push(' ');
         }
@@ -634,11 +667,11 @@ fn resources_to_table(resources: &[Resource]) -> String {
     let mut output = String::with_capacity(estimate_ascii_table_capacity(&widths, resources.len() + 1));
 
     ascii_table_border(&mut output, &widths);
-    ascii_table_row(&mut output, &widths, ["name", "value"]);
+    ascii_table_row(&mut output, &widths, &["name", "value"]);
     ascii_table_border(&mut output, &widths);
 
     for resource in resources {
-        ascii_table_row(&mut output, &widths, [resource.name.as_str(), resource.value.as_str()]);
+        ascii_table_row(&mut output, &widths, &[resource.name.as_str(), resource.value.as_str()]);
         ascii_table_border(&mut output, &widths);
     }
 
@@ -661,14 +694,14 @@ fn triples_to_table(triples: &[Triple]) -> String {
     let mut output = String::with_capacity(estimate_ascii_table_capacity(&widths, triples.len() + 1));
 
     ascii_table_border(&mut output, &widths);
-    ascii_table_row(&mut output, &widths, ["name", "value", "base"]);
+    ascii_table_row(&mut output, &widths, &["name", "value", "base"]);
     ascii_table_border(&mut output, &widths);
 
     for triple in triples {
         ascii_table_row(
             &mut output,
             &widths,
-            [triple.name.as_str(), triple.value.as_str(), triple.base.as_str()],
+            &[triple.name.as_str(), triple.value.as_str(), triple.base.as_str()],
         );
         ascii_table_border(&mut output, &widths);
     }
@@ -688,26 +721,6 @@ fn render_jsonl<T: serde::Serialize>(values: &[T]) -> String {
     unsafe { String::from_utf8_unchecked(output) }
 }
 
-fn render_resources(resources: &[Resource], output_format: QueryOutputFormat) -> String {
-    match output_format {
-        QueryOutputFormat::Table => resources_to_table(resources),
-        QueryOutputFormat::Json => ensure_trailing_newline(
-            serde_json::to_string(resources).expect("failed to serialize resource list to JSON"),
-        ),
-        QueryOutputFormat::Jsonl => render_jsonl(resources),
-    }
-}
-
-fn render_triples(triples: &[Triple], output_format: QueryOutputFormat) -> String {
-    match output_format {
-        QueryOutputFormat::Table => triples_to_table(triples),
-        QueryOutputFormat::Json => {
-            ensure_trailing_newline(serde_json::to_string(triples).expect("failed to serialize triple list to JSON"))
-        }
-        QueryOutputFormat::Jsonl => render_jsonl(triples),
-    }
-}
-
 fn render_count(count: usize) -> String {
     format!("{count}\n")
 }
@@ -721,7 +734,7 @@ fn report_to_table(report: &QueryExecutionReport) -> String {
     let matched_count = report.matched_count.to_string();
     let filtered_count = report.filtered_count.to_string();
     let output_count = report.output_count.to_string();
-    let rows = [
+    let mut rows = vec![
         ("operation", report.operation.as_deref().unwrap_or_default()),
         ("result_kind", report.result_kind.as_str()),
         ("input_files", input_files.as_str()),
@@ -737,19 +750,35 @@ fn report_to_table(report: &QueryExecutionReport) -> String {
         ("change_detected", bool_str(report.change_detected)),
     ];
 
+    let merge_counts = report
+        .merge
+        .as_ref()
+        .map(|merge| [merge.added, merge.updated, merge.skipped, merge.removed].map(|count| count.to_string()));
+    if let (Some(merge), Some([added, updated, skipped, removed])) = (report.merge.as_ref(), merge_counts.as_ref()) {
+        rows.extend([
+            ("merge_strategy", merge.strategy.as_str()),
+            ("skip_empty", bool_str(merge.skip_empty)),
+            ("base", merge.base.as_deref().unwrap_or_default()),
+            ("added_count", added.as_str()),
+            ("updated_count", updated.as_str()),
+            ("skipped_count", skipped.as_str()),
+            ("removed_count", removed.as_str()),
+        ]);
+    }
+
     let mut widths = [UnicodeWidthStr::width("field"), UnicodeWidthStr::width("value")];
-    for (field, value) in rows {
+    for &(field, value) in &rows {
         widths[0] = widths[0].max(UnicodeWidthStr::width(field));
         widths[1] = widths[1].max(UnicodeWidthStr::width(value));
     }
 
     let mut output = String::with_capacity(estimate_ascii_table_capacity(&widths, rows.len() + 1));
     ascii_table_border(&mut output, &widths);
-    ascii_table_row(&mut output, &widths, ["field", "value"]);
+    ascii_table_row(&mut output, &widths, &["field", "value"]);
     ascii_table_border(&mut output, &widths);
 
     for (field, value) in rows {
-        ascii_table_row(&mut output, &widths, [field, value]);
+        ascii_table_row(&mut output, &widths, &[field, value]);
         ascii_table_border(&mut output, &widths);
     }
 
@@ -842,6 +871,488 @@ fn filter_triples(
     )
 }
 
+/// A structured result row, such as a finding or a hand-off unit.
+pub(crate) trait QueryRecord: serde::Serialize {
+    const COLUMNS: &'static [&'static str];
+
+    fn cells(&self) -> Vec<String>;
+
+    /// The text matched by `--key-filter`.
+    fn filter_key(&self) -> &str;
+
+    /// The text matched by `--value-filter`.
+    fn filter_value(&self) -> &str;
+
+    /// Whether the records have diagnostics, which replace the rows with `--diagnostic-format`.
+    const HAS_DIAGNOSTICS: bool = false;
+
+    /// The diagnostic printed for this record with `--diagnostic-format`.
+    fn diagnostic(&self) -> Option<Diagnostic> {
+        None
+    }
+
+    /// The cells of the table output, which may contain ANSI escapes with `color`.
+    fn table_cells(&self, _color: bool) -> Vec<String> {
+        self.cells()
+    }
+}
+
+impl QueryRecord for Resource {
+    const COLUMNS: &'static [&'static str] = &["name", "value"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.name.clone(), self.value.clone()]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.name
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl QueryRecord for Triple {
+    const COLUMNS: &'static [&'static str] = &["name", "value", "base"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.name.clone(), self.value.clone(), self.base.clone()]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.name
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.value
+    }
+}
+
+/// A row of `query_change`: a resource of the first file, with its value in the second file.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ValueChange {
+    #[serde(flatten)]
+    pub resource: Resource,
+    /// The value in the second file, unless the key is missing from it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub other_value: Option<String>,
+    /// The inline diff from the value in the second file to the value in the first one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spans: Option<Vec<DiffSpan>>,
+}
+
+impl QueryRecord for ValueChange {
+    const COLUMNS: &'static [&'static str] = Resource::COLUMNS;
+
+    fn cells(&self) -> Vec<String> {
+        self.resource.cells()
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.resource.name
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.resource.value
+    }
+
+    fn table_cells(&self, color: bool) -> Vec<String> {
+        match &self.spans {
+            Some(spans) => vec![self.resource.name.clone(), render_inline(spans, color)],
+            None => self.cells(),
+        }
+    }
+}
+
+impl QueryRecord for QueryExecutionReport {
+    const COLUMNS: &'static [&'static str] = &[
+        "operation",
+        "input_files",
+        "output_file",
+        "output_count",
+        "would_write",
+        "wrote_output",
+        "change_detected",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.operation.clone().unwrap_or_default(),
+            self.input_files.join(","),
+            self.output_file.clone().unwrap_or_default(),
+            self.output_count.to_string(),
+            bool_str(self.would_write).to_owned(),
+            bool_str(self.wrote_output).to_owned(),
+            bool_str(self.change_detected).to_owned(),
+        ]
+    }
+
+    fn filter_key(&self) -> &str {
+        self.operation.as_deref().unwrap_or_default()
+    }
+
+    fn filter_value(&self) -> &str {
+        self.output_file.as_deref().unwrap_or_default()
+    }
+}
+
+/// The display width of a table cell, without its ANSI escapes.
+fn display_width(cell: &str) -> usize {
+    if !cell.contains('\x1b') {
+        return UnicodeWidthStr::width(cell);
+    }
+
+    let mut width = 0;
+    let mut rest = cell;
+    while let Some(start) = rest.find('\x1b') {
+        width += UnicodeWidthStr::width(&rest[..start]);
+        rest = &rest[start..];
+        // escapes end with their first letter, e.g. `\x1b[31m`
+        let end = rest
+            .find(|c: char| c.is_ascii_alphabetic())
+            .map_or(rest.len(), |end| end + 1);
+        rest = &rest[end..];
+    }
+
+    width + UnicodeWidthStr::width(rest)
+}
+
+fn records_to_table<T: QueryRecord>(records: &[T], color: bool) -> String {
+    let rows = records
+        .iter()
+        .map(|record| record.table_cells(color))
+        .collect::<Vec<_>>();
+    let mut widths = T::COLUMNS
+        .iter()
+        .map(|column| UnicodeWidthStr::width(*column))
+        .collect::<Vec<_>>();
+
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(display_width(cell));
+        }
+    }
+
+    let mut output = String::with_capacity(estimate_ascii_table_capacity(&widths, rows.len() + 1));
+
+    ascii_table_border(&mut output, &widths);
+    ascii_table_row(&mut output, &widths, T::COLUMNS);
+    ascii_table_border(&mut output, &widths);
+
+    for row in &rows {
+        let cells = row.iter().map(String::as_str).collect::<Vec<_>>();
+        ascii_table_row(&mut output, &widths, &cells);
+        ascii_table_border(&mut output, &widths);
+    }
+
+    output
+}
+
+pub(crate) fn render_records<T: QueryRecord>(records: &[T], output_format: QueryOutputFormat) -> String {
+    render_records_with_color(records, output_format, false)
+}
+
+fn render_records_with_color<T: QueryRecord>(records: &[T], output_format: QueryOutputFormat, color: bool) -> String {
+    match output_format {
+        QueryOutputFormat::Table => records_to_table(records, color),
+        QueryOutputFormat::Json => {
+            ensure_trailing_newline(serde_json::to_string(records).expect("failed to serialize record list to JSON"))
+        }
+        QueryOutputFormat::Jsonl => render_jsonl(records),
+    }
+}
+
+fn filter_records<T: QueryRecord>(
+    mut records: Vec<T>,
+    filters: &CompiledQueryFilters,
+    limit: Option<usize>,
+) -> (QueryExecutionCounts, Vec<T>) {
+    let matched_count = records.len();
+
+    if let Some(key_filter) = filters.key_filter.as_ref() {
+        records.retain(|record| key_filter.matches(record.filter_key()));
+    }
+
+    if let Some(value_filter) = filters.value_filter.as_ref() {
+        records.retain(|record| value_filter.matches(record.filter_value()));
+    }
+
+    let filtered_count = records.len();
+    let mut truncated = false;
+
+    if let Some(limit) = limit {
+        truncated = filtered_count > limit;
+        records.truncate(limit);
+    }
+
+    (
+        QueryExecutionCounts {
+            matched_count,
+            filtered_count,
+            output_count: records.len(),
+            truncated,
+        },
+        records,
+    )
+}
+
+/// Applies the filters and limit of `options` up front, for commands that act on the selected records.
+pub(crate) fn select_records<T: QueryRecord>(records: Vec<T>, options: &QueryRunOptions) -> Result<Vec<T>, io::Error> {
+    let filters = compile_query_filters(options)?;
+    Ok(filter_records(records, &filters, options.limit).1)
+}
+
+pub(crate) type RenderRecordOutput<'a, T> = Box<dyn FnOnce(&[T]) -> Result<Vec<u8>, io::Error> + 'a>;
+
+/// Output file produced alongside a list of records, rendered from the records left after filtering.
+pub(crate) struct RecordOutput<'a, T> {
+    pub(crate) path: &'a str,
+    pub(crate) render: RenderRecordOutput<'a, T>,
+    /// Print the records even when the output file is written, e.g. findings next to a merged file.
+    pub(crate) print_records: bool,
+}
+
+/// An output file that a query would write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingWrite {
+    pub path: String,
+    pub bytes: Vec<u8>,
+}
+
+impl PendingWrite {
+    pub fn write(&self) -> Result<(), io::Error> {
+        fs::write(&self.path, &self.bytes).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", self.path, e)))
+    }
+}
+
+/// The result of a query, computed without printing or writing anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryResult<T> {
+    /// The rows left after filtering.
+    pub rows: Vec<T>,
+    /// The text to print for this run in the requested output format: rows, count or summary.
+    pub rendered: String,
+    /// The execution report, assuming that the pending writes are written.
+    pub report: QueryExecutionReport,
+    /// The output files to write; empty for dry runs and files that are already up to date.
+    pub pending_writes: Vec<PendingWrite>,
+}
+
+impl<T> QueryResult<T> {
+    /// Writes the pending output files. Several files are all staged next to their destination first, so that
+    /// a failure to write one of them leaves every file unchanged, then moved into place. Moving is not atomic
+    /// across files: if a move fails, the remaining staged files are removed and the error names the files
+    /// that were already replaced.
+    pub fn write_pending(&self) -> Result<(), io::Error> {
+        if let [pending_write] = self.pending_writes.as_slice() {
+            return pending_write.write();
+        }
+
+        let mut staged = Vec::with_capacity(self.pending_writes.len());
+        for pending_write in &self.pending_writes {
+            let staging_path = format!("{}.cirup-tmp", pending_write.path);
+            if let Err(e) = fs::write(&staging_path, &pending_write.bytes) {
+                for staging_path in &staged {
+                    let _ = fs::remove_file(staging_path);
+                }
+                return Err(io::Error::new(e.kind(), format!("{}: {}", pending_write.path, e)));
+            }
+            staged.push(staging_path);
+        }
+
+        for (index, (staging_path, pending_write)) in staged.iter().zip(&self.pending_writes).enumerate() {
+            if let Err(e) = fs::rename(staging_path, &pending_write.path) {
+                for staging_path in &staged[index..] {
+                    let _ = fs::remove_file(staging_path);
+                }
+
+                let replaced = self.pending_writes[..index]
+                    .iter()
+                    .map(|pending_write| pending_write.path.as_str())
+                    .collect::<Vec<_>>();
+                let message = if replaced.is_empty() {
+                    format!("{}: {}; no file was replaced", pending_write.path, e)
+                } else {
+                    format!(
+                        "{}: {}; already replaced: {}",
+                        pending_write.path,
+                        e,
+                        replaced.join(", ")
+                    )
+                };
+                return Err(io::Error::new(e.kind(), message));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the diagnostic of a record, for the records that have diagnostics.
+type Diagnose<'a, T> = &'a dyn Fn(&T) -> Option<Diagnostic>;
+
+#[allow(clippy::too_many_arguments)]
+fn evaluate_counted<T: QueryRecord>(
+    counts: QueryExecutionCounts,
+    merge: Option<MergeSummary>,
+    records: Vec<T>,
+    result_kind: &str,
+    outputs: Vec<(&str, Vec<u8>, bool)>,
+    touch: bool,
+    options: &QueryRunOptions,
+    diagnose: Option<Diagnose<'_, T>>,
+) -> QueryResult<T> {
+    let outputs = outputs
+        .into_iter()
+        .map(|(path, bytes, print_records)| {
+            let would_write = would_write_output_bytes(path, &bytes, touch);
+            (path, bytes, print_records, would_write)
+        })
+        .collect::<Vec<_>>();
+    let would_write = outputs.iter().any(|(_, _, _, would_write)| *would_write);
+    let mut rendered = String::new();
+    let mut pending_writes = Vec::new();
+
+    if options.count_only {
+        rendered = render_count(counts.output_count);
+    } else if !options.check {
+        let print_records =
+            outputs.is_empty() || options.dry_run || outputs.iter().any(|(_, _, print_records, _)| *print_records);
+        if !options.dry_run {
+            pending_writes.extend(outputs.into_iter().filter(|output| output.3).map(|(path, bytes, ..)| {
+                PendingWrite {
+                    path: path.to_owned(),
+                    bytes,
+                }
+            }));
+        }
+
+        if print_records && !options.summary {
+            rendered = render_records_with_color(&records, options.output_format, options.color);
+        }
+    }
+
+    // diagnostics describe the findings rather than the output, they are printed even with --check;
+    // records without diagnostics are rendered as usual
+    if let Some(format) = options.diagnostic_format
+        && let Some(diagnose) = diagnose
+        && !options.count_only
+        && !options.summary
+    {
+        let diagnostics = records.iter().filter_map(diagnose).collect::<Vec<_>>();
+        rendered = render_diagnostics(&diagnostics, format);
+    }
+
+    let wrote_output = !pending_writes.is_empty();
+    let mut report = QueryExecutionReport::from_options(options, result_kind, counts, would_write, wrote_output);
+    report.merge = merge;
+
+    if options.summary && !options.count_only {
+        rendered.push_str(&render_report(&report, options.output_format));
+    }
+
+    QueryResult {
+        rows: records,
+        rendered,
+        report,
+        pending_writes,
+    }
+}
+
+/// Filters `records` and renders them and their output file, without side effects.
+pub(crate) fn evaluate_records<T: QueryRecord>(
+    records: Vec<T>,
+    result_kind: &str,
+    output: Option<RecordOutput<'_, T>>,
+    touch: bool,
+    options: &QueryRunOptions,
+) -> Result<QueryResult<T>, io::Error> {
+    evaluate_records_with_merge(records, result_kind, output, touch, options, None)
+}
+
+/// Filters `records` and renders them as `evaluate_records`, with the counts of the merge they result from
+/// in the report and its summary.
+pub(crate) fn evaluate_records_with_merge<T: QueryRecord>(
+    records: Vec<T>,
+    result_kind: &str,
+    output: Option<RecordOutput<'_, T>>,
+    touch: bool,
+    options: &QueryRunOptions,
+    merge: Option<MergeSummary>,
+) -> Result<QueryResult<T>, io::Error> {
+    evaluate_records_to_outputs(
+        records,
+        result_kind,
+        output.into_iter().collect(),
+        touch,
+        options,
+        merge,
+    )
+}
+
+/// Filters `records` and renders them as `evaluate_records`, with several output files rendered from the
+/// records left after filtering, such as a file and its review list.
+pub(crate) fn evaluate_records_to_outputs<T: QueryRecord>(
+    records: Vec<T>,
+    result_kind: &str,
+    outputs: Vec<RecordOutput<'_, T>>,
+    touch: bool,
+    options: &QueryRunOptions,
+    merge: Option<MergeSummary>,
+) -> Result<QueryResult<T>, io::Error> {
+    options.validate_for_output(outputs.first().map(|output| output.path))?;
+    let filters = compile_query_filters(options)?;
+
+    let (counts, records) = filter_records(records, &filters, options.limit);
+    let outputs = outputs
+        .into_iter()
+        .map(|output| Ok((output.path, (output.render)(&records)?, output.print_records)))
+        .collect::<Result<_, io::Error>>()?;
+
+    Ok(evaluate_counted(
+        counts,
+        merge,
+        records,
+        result_kind,
+        outputs,
+        touch,
+        options,
+        T::HAS_DIAGNOSTICS.then_some(&T::diagnostic),
+    ))
+}
+
+/// Filters `records` and renders them with the output files they apply to, such as the files of a family.
+/// The records are printed even when the files are written.
+pub(crate) fn evaluate_records_with_outputs<T: QueryRecord>(
+    records: Vec<T>,
+    result_kind: &str,
+    outputs: &[PendingWrite],
+    touch: bool,
+    options: &QueryRunOptions,
+) -> Result<QueryResult<T>, io::Error> {
+    // the files are updated in place rather than requested, --count-only counts the records without writing them
+    options.validate_for_output(None)?;
+    let filters = compile_query_filters(options)?;
+
+    let (counts, records) = filter_records(records, &filters, options.limit);
+    let outputs = outputs
+        .iter()
+        .map(|output| (output.path.as_str(), output.bytes.clone(), true))
+        .collect();
+
+    Ok(evaluate_counted(
+        counts,
+        None,
+        records,
+        result_kind,
+        outputs,
+        touch,
+        options,
+        T::HAS_DIAGNOSTICS.then_some(&T::diagnostic),
+    ))
+}
+
 #[allow(clippy::print_stdout)]
 pub fn print_resources_pretty(resources: &[Resource]) {
     print!("{}", resources_to_table(resources));
@@ -884,8 +1395,15 @@ pub fn query_file(input: &str, table: &str, query: &str) {
     print_resources_pretty(&resources);
 }
 
+/// The values of the resources of a table by key, with their span in the file they were parsed from.
+type TableSpans = HashMap<String, Vec<(String, Option<SourceSpan>)>>;
+
 pub struct CirupEngine {
     backend: Box<dyn QueryBackend>,
+    /// The spans of the resources of each table, in the order the tables were registered. They are kept
+    /// out of the tables, so that queries only see the key and value columns, and attached to the rows
+    /// that only one table has.
+    spans: Vec<(String, TableSpans)>,
 }
 
 impl CirupEngine {
@@ -902,20 +1420,61 @@ impl CirupEngine {
     pub fn with_query_config(query_config: &QueryConfig) -> Self {
         Self {
             backend: build_backend(query_config),
+            spans: Vec::new(),
         }
     }
 
     #[cfg(test)]
     fn register_table_from_str(&mut self, table: &str, filename: &str, data: &str) {
-        self.backend.register_table_from_str(table, filename, data);
+        vfile_set(filename, data);
+        self.register_table_from_file(table, filename);
     }
 
+    /// Registers the resources of `filename`, or an empty table when it cannot be loaded.
     pub fn register_table_from_file(&mut self, table: &str, filename: &str) {
-        self.backend.register_table_from_file(table, filename);
+        let resources = load_resource_file(filename).unwrap_or_else(|e| {
+            error!("failed to load {}", load_error(filename, e));
+            Vec::new()
+        });
+        self.register_table_from_resources(table, resources);
+    }
+
+    pub fn register_table_from_resources(&mut self, table: &str, resources: Vec<Resource>) {
+        let mut spans = TableSpans::new();
+        for resource in &resources {
+            spans
+                .entry(resource.name.clone())
+                .or_default()
+                .push((resource.value.clone(), resource.span.clone()));
+        }
+
+        match self.spans.iter_mut().find(|(name, _)| name.eq_ignore_ascii_case(table)) {
+            Some((_, table_spans)) => *table_spans = spans,
+            None => self.spans.push((table.to_owned(), spans)),
+        }
+
+        self.backend.register_table_from_resources(table, resources);
     }
 
+    /// Runs `query`, attaching to each row the span of its key and value in the only table that has them.
+    /// Rows found in several tables, as in an intersection, or in none, as rewritten values, have no span.
     pub fn query_resource(&self, query: &str) -> Vec<Resource> {
-        self.backend.query_resource(query)
+        let mut resources = self.backend.query_resource(query);
+
+        for resource in &mut resources {
+            let mut matches = self.spans.iter().filter_map(|(_, spans)| {
+                spans
+                    .get(&resource.name)?
+                    .iter()
+                    .find(|(value, _)| *value == resource.value)
+            });
+            resource.span = match (matches.next(), matches.next()) {
+                (Some((_, span)), None) => span.clone(),
+                _ => None,
+            };
+        }
+
+        resources
     }
 
     pub fn query_triple(&self, query: &str) -> Vec<Triple> {
@@ -932,16 +1491,28 @@ impl Default for CirupEngine {
 pub struct CirupQuery {
     engine: CirupEngine,
     query: String,
+    tables: Vec<(&'static str, String)>,
 }
 
-const PRINT_QUERY: &str = "select * from a";
-const DIFF_QUERY: &str = "select a.key, a.val, b.val from a left outer join b on a.key = b.key where (b.val is null)";
+/// Code of the diagnostics of `file-diff`, for keys of the first file missing from the second.
+pub const MISSING_KEY_CODE: &str = "CIRUP010";
+/// Code of the diagnostics of `file-diff --show-changes`, for keys missing or with another value.
+pub const CHANGED_KEY_CODE: &str = "CIRUP011";
+
+pub(crate) const PRINT_QUERY: &str = "select * from a";
+pub(crate) const DIFF_QUERY: &str =
+    "select a.key, a.val, b.val from a left outer join b on a.key = b.key where (b.val is null)";
 const DIFF_WITH_BASE_QUERY: &str = "select b.key, b.val, c.val from b left outer join a on b.key = a.key inner join c on b.key = c.key where (a.val is null)";
 const CHANGE_QUERY: &str =
     "select a.key, a.val, b.val from a left outer join b on a.key = b.key where (b.val is null) or (a.val <> b.val)";
-const MERGE_QUERY: &str = "select a.key, case when b.val is not null then b.val else a.val end from a left outer join b on a.key = b.key union select b.key, b.val from b left outer join a on a.key = b.key where (a.key is null)";
+pub(crate) const MERGE_QUERY: &str = "select a.key, case when b.val is not null then b.val else a.val end from a left outer join b on a.key = b.key union select b.key, b.val from b left outer join a on a.key = b.key where (a.key is null)";
 const INTERSECT_QUERY: &str = "select * from a intersect select * from b";
 const SUBTRACT_QUERY: &str = "select * from a where a.key not in (select b.key from b)";
+/// The keys of `a` in its order, with their value in `b` when they have one.
+pub(crate) const PULL_QUERY: &str = "select a.key, case when b.val is not null then b.val else a.val end as val from a left outer join b on a.key = b.key";
+/// The values of `b` that differ from the value of the same key in `a`.
+pub(crate) const PUSH_QUERY: &str = "select b.key, b.val from b inner join a on (a.key = b.key) and (a.val <> b.val)";
+pub(crate) const TRANSLATED_QUERY: &str = "select a.key, b.val, a.val from a inner join b on a.key = b.key";
 const CONVERT_QUERY: &str = PRINT_QUERY;
 const SORT_QUERY: &str = "select * from a order by a.key";
 
@@ -1029,6 +1600,94 @@ pub fn query_subtract_with_backend(file_one: &str, file_two: &str, backend: Quer
     CirupQuery::new_with_backend(SUBTRACT_QUERY, file_one, Some(file_two), None, backend)
 }
 
+/// The operations of the `file-*` and `diff-with-base` commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryOperation {
+    Print,
+    Convert,
+    Sort,
+    Diff,
+    /// A diff that also reports keys with different values.
+    Change,
+    DiffWithBase,
+    Merge,
+    Intersect,
+    Subtract,
+}
+
+impl QueryOperation {
+    const ALL: [QueryOperation; 9] = [
+        QueryOperation::Print,
+        QueryOperation::Convert,
+        QueryOperation::Sort,
+        QueryOperation::Diff,
+        QueryOperation::Change,
+        QueryOperation::DiffWithBase,
+        QueryOperation::Merge,
+        QueryOperation::Intersect,
+        QueryOperation::Subtract,
+    ];
+
+    /// The operation name used in execution reports.
+    pub fn name(self) -> &'static str {
+        match self {
+            QueryOperation::Print => "file-print",
+            QueryOperation::Convert => "file-convert",
+            QueryOperation::Sort => "file-sort",
+            QueryOperation::Diff => "file-diff",
+            QueryOperation::Change => "file-diff-changes",
+            QueryOperation::DiffWithBase => "diff-with-base",
+            QueryOperation::Merge => "file-merge",
+            QueryOperation::Intersect => "file-intersect",
+            QueryOperation::Subtract => "file-subtract",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|operation| operation.name() == name)
+    }
+
+    /// The number of input files or resource lists of the operation.
+    pub fn input_count(self) -> usize {
+        match self {
+            QueryOperation::Print | QueryOperation::Convert | QueryOperation::Sort => 1,
+            QueryOperation::DiffWithBase => 3,
+            _ => 2,
+        }
+    }
+
+    /// Whether the operation returns triples rather than resources.
+    pub fn returns_triples(self) -> bool {
+        self == QueryOperation::DiffWithBase
+    }
+
+    pub(crate) fn query(self) -> &'static str {
+        match self {
+            QueryOperation::Print => PRINT_QUERY,
+            QueryOperation::Convert => CONVERT_QUERY,
+            QueryOperation::Sort => SORT_QUERY,
+            QueryOperation::Diff => DIFF_QUERY,
+            QueryOperation::Change => CHANGE_QUERY,
+            QueryOperation::DiffWithBase => DIFF_WITH_BASE_QUERY,
+            QueryOperation::Merge => MERGE_QUERY,
+            QueryOperation::Intersect => INTERSECT_QUERY,
+            QueryOperation::Subtract => SUBTRACT_QUERY,
+        }
+    }
+
+    fn check_input_count(self, count: usize) -> Result<(), CirupError> {
+        if count == self.input_count() {
+            return Ok(());
+        }
+
+        Err(CirupError::InputCount {
+            operation: self.name(),
+            expected: self.input_count(),
+            actual: count,
+        })
+    }
+}
+
 impl CirupQuery {
     pub fn new(query: &str, file_one: &str, file_two: Option<&str>, file_three: Option<&str>) -> Self {
         Self::new_with_query_config(query, file_one, file_two, file_three, &default_query_config())
@@ -1053,23 +1712,144 @@ impl CirupQuery {
         file_three: Option<&str>,
         query_config: &QueryConfig,
     ) -> Self {
-        let mut engine = CirupEngine::with_query_config(query_config);
-        engine.register_table_from_file("A", file_one);
+        Self::with_engine(
+            CirupEngine::with_query_config(query_config),
+            query,
+            file_one,
+            file_two,
+            file_three,
+        )
+    }
+
+    /// Builds a query on an existing engine, replacing its tables with the input files.
+    pub fn with_engine(
+        engine: CirupEngine,
+        query: &str,
+        file_one: &str,
+        file_two: Option<&str>,
+        file_three: Option<&str>,
+    ) -> Self {
+        let mut query = Self::unloaded(engine, query, file_one, file_two, file_three);
+        query.reload();
+        query
+    }
 
-        if let Some(file_two) = file_two {
-            engine.register_table_from_file("B", file_two);
+    /// Builds a query on an existing engine as `with_engine`, failing on missing files and parse errors.
+    /// The engine is returned with the error, to run other queries on it.
+    pub fn try_with_engine(
+        engine: CirupEngine,
+        query: &str,
+        file_one: &str,
+        file_two: Option<&str>,
+        file_three: Option<&str>,
+    ) -> Result<Self, (io::Error, CirupEngine)> {
+        let mut query = Self::unloaded(engine, query, file_one, file_two, file_three);
+        match query.try_reload() {
+            Ok(()) => Ok(query),
+            Err(e) => Err((e, query.engine)),
         }
+    }
 
-        if let Some(file_three) = file_three {
-            engine.register_table_from_file("C", file_three);
-        }
+    fn unloaded(
+        engine: CirupEngine,
+        query: &str,
+        file_one: &str,
+        file_two: Option<&str>,
+        file_three: Option<&str>,
+    ) -> Self {
+        let tables = [("A", Some(file_one)), ("B", file_two), ("C", file_three)]
+            .into_iter()
+            .filter_map(|(table, file)| file.map(|file| (table, file.to_owned())))
+            .collect();
 
         CirupQuery {
             engine,
             query: query.to_owned(),
+            tables,
+        }
+    }
+
+    /// Builds a query over in-memory resources, registered in order as the inputs of `operation`.
+    pub fn from_resources(operation: QueryOperation, inputs: &[&[Resource]]) -> Result<Self, CirupError> {
+        Self::from_resources_with_query_config(operation, inputs, &default_query_config())
+    }
+
+    /// Builds a query over in-memory resources as `from_resources`, on the backend of `query_config`.
+    pub fn from_resources_with_query_config(
+        operation: QueryOperation,
+        inputs: &[&[Resource]],
+        query_config: &QueryConfig,
+    ) -> Result<Self, CirupError> {
+        operation.check_input_count(inputs.len())?;
+
+        let mut engine = CirupEngine::with_query_config(query_config);
+        for (table, resources) in ["A", "B", "C"].into_iter().zip(inputs) {
+            engine.register_table_from_resources(table, resources.to_vec());
+        }
+
+        Ok(CirupQuery {
+            engine,
+            query: operation.query().to_owned(),
+            tables: Vec::new(),
+        })
+    }
+
+    /// Builds a query over resource files, failing on missing files, unsupported formats and parse errors
+    /// instead of logging them and running on empty tables.
+    pub fn from_files(operation: QueryOperation, files: &[&str]) -> Result<Self, CirupError> {
+        Self::from_files_with_query_config(operation, files, &default_query_config())
+    }
+
+    /// Builds a query over resource files as `from_files`, on the backend of `query_config`.
+    pub fn from_files_with_query_config(
+        operation: QueryOperation,
+        files: &[&str],
+        query_config: &QueryConfig,
+    ) -> Result<Self, CirupError> {
+        operation.check_input_count(files.len())?;
+        let inputs = files
+            .iter()
+            .map(|file| read_resources(file))
+            .collect::<Result<Vec<_>, _>>()?;
+        let inputs = inputs.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+        let mut query = Self::from_resources_with_query_config(operation, &inputs, query_config)?;
+        query.tables = ["A", "B", "C"]
+            .into_iter()
+            .zip(files)
+            .map(|(table, file)| (table, (*file).to_owned()))
+            .collect();
+        Ok(query)
+    }
+
+    /// Returns the engine, to run other queries without creating a new database.
+    pub fn into_engine(self) -> CirupEngine {
+        self.engine
+    }
+
+    /// Loads the input files again into the tables of the engine, to re-run the query after they changed.
+    pub fn reload(&mut self) {
+        for (table, file) in &self.tables {
+            self.engine.register_table_from_file(table, file);
         }
     }
 
+    /// Loads the input files again as `reload`, failing on missing files and parse errors.
+    /// The tables are left unchanged when an input file cannot be loaded.
+    pub fn try_reload(&mut self) -> Result<(), io::Error> {
+        let inputs = self
+            .tables
+            .iter()
+            .map(|(_, file)| load_resources(file))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for ((table, _), resources) in self.tables.iter().zip(inputs) {
+            self.engine.register_table_from_resources(table, resources);
+        }
+
+        Ok(())
+    }
+
     pub fn run(&self) -> Vec<Resource> {
         self.engine.query_resource(&self.query)
     }
@@ -1112,57 +1892,165 @@ impl CirupQuery {
         }
     }
 
-    #[allow(clippy::print_stdout)]
-    pub fn run_interactive_with_options(
+    /// Runs the query and renders its rows and output file without printing or writing anything.
+    pub fn evaluate_with_options(
         &self,
         out_file: Option<&str>,
         touch: bool,
         output_encoding: OutputEncoding,
         options: &QueryRunOptions,
-    ) -> Result<QueryExecutionReport, io::Error> {
+    ) -> Result<QueryResult<Resource>, io::Error> {
+        let diagnose = self.row_diagnostics(options);
+        self.evaluate_rows(
+            out_file,
+            touch,
+            output_encoding,
+            options,
+            "resource",
+            |resources| resources,
+            diagnose,
+        )
+    }
+
+    /// Runs a `query_change` query as `evaluate_with_options`, with the value of each key in the second file
+    /// and the inline diff from it to the value in the first file.
+    pub fn evaluate_changes_with_options(
+        &self,
+        out_file: Option<&str>,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<ValueChange>, io::Error> {
+        let other_values = self
+            .engine
+            .query_resource("select b.key, b.val from b")
+            .into_iter()
+            .map(|resource| (resource.name, resource.value))
+            .collect::<HashMap<_, _>>();
+        let changes = |resources: Vec<Resource>| {
+            resources
+                .into_iter()
+                .map(|resource| {
+                    let other_value = other_values.get(&resource.name).cloned();
+                    let spans = other_value
+                        .as_deref()
+                        .map(|other_value| diff_values(other_value, &resource.value, options.diff_granularity));
+                    ValueChange {
+                        resource,
+                        other_value,
+                        spans,
+                    }
+                })
+                .collect()
+        };
+
+        let diagnose = self.row_diagnostics(options);
+        self.evaluate_rows(
+            out_file,
+            touch,
+            output_encoding,
+            options,
+            "value-change",
+            changes,
+            diagnose.map(|diagnose| move |change: &ValueChange| diagnose(&change.resource)),
+        )
+    }
+
+    /// Runs the query and renders its rows, made from the resources it returns, and its output file.
+    #[allow(clippy::too_many_arguments)]
+    fn evaluate_rows<T: QueryRecord>(
+        &self,
+        out_file: Option<&str>,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+        result_kind: &str,
+        rows: impl FnOnce(Vec<Resource>) -> Vec<T>,
+        diagnose: Option<impl Fn(&T) -> Option<Diagnostic>>,
+    ) -> Result<QueryResult<T>, io::Error> {
         options.validate_for_output(out_file)?;
         let filters = compile_query_filters(options)?;
         let query = wrap_resource_query_with_filters(&self.query, &filters);
 
         let (counts, resources) = filter_resources(self.engine.query_resource(&query), &filters, options.limit);
-        let would_write = out_file
-            .map(|path| would_save_resource_file_with_encoding(path, &resources, touch, output_encoding))
-            .unwrap_or(false);
-        let mut wrote_output = false;
-        let report = QueryExecutionReport::from_options(options, "resource", counts, would_write, false);
-
-        if options.count_only {
-            print!("{}", render_count(counts.output_count));
-            return Ok(report);
-        }
-
-        if options.check {
-            if options.summary {
-                print!("{}", render_report(&report, options.output_format));
+        let output = match out_file {
+            Some(path) => {
+                let bytes = output_bytes_for_file(path, &resources, output_encoding)?;
+                Some((path, bytes, false))
             }
-            return Ok(report);
-        }
+            None => None,
+        };
 
-        if let Some(out_file) = out_file {
-            if options.dry_run {
-                if !options.summary {
-                    print!("{}", render_resources(&resources, options.output_format));
-                }
-            } else {
-                save_resource_file_with_encoding(out_file, &resources, touch, output_encoding);
-                wrote_output = would_write;
+        Ok(evaluate_counted(
+            counts,
+            None,
+            rows(resources),
+            result_kind,
+            output.into_iter().collect(),
+            touch,
+            options,
+            diagnose
+                .as_ref()
+                .map(|diagnose| diagnose as &dyn Fn(&T) -> Option<Diagnostic>),
+        ))
+    }
+
+    /// Returns the diagnostic of a row of a diff on files, at the span of its resource in the first file:
+    /// the key is missing from the second file, or with `query_change`, has another value in it.
+    /// Other queries have no diagnostics.
+    fn row_diagnostics(&self, options: &QueryRunOptions) -> Option<impl Fn(&Resource) -> Option<Diagnostic>> {
+        let (code, message) = match self.query.as_str() {
+            DIFF_QUERY => (MISSING_KEY_CODE, "key is missing from"),
+            CHANGE_QUERY => (CHANGED_KEY_CODE, "key is missing or has another value in"),
+            _ => ("", ""),
+        };
+        let (file_one, message) = match self.tables.as_slice() {
+            [(_, file_one), (_, file_two), ..] if !code.is_empty() && options.diagnostic_format.is_some() => {
+                Some((file_one.clone(), format!("{} {}", message, file_two)))
             }
-        } else if !options.summary {
-            print!("{}", render_resources(&resources, options.output_format));
-        }
-
-        let report = QueryExecutionReport::from_options(options, "resource", counts, would_write, wrote_output);
+            _ => None,
+        }?;
+
+        Some(move |resource: &Resource| {
+            let span = resource.span.as_ref();
+            Some(Diagnostic {
+                file: span.map_or_else(|| file_one.clone(), |span| span.file.clone()),
+                line: span.map_or(0, |span| span.line),
+                column: span.map_or(0, |span| span.column),
+                severity: Severity::Error,
+                code: code.to_owned(),
+                message: format!("{}: {}", resource.name, message),
+            })
+        })
+    }
 
-        if options.summary {
-            print!("{}", render_report(&report, options.output_format));
-        }
+    /// Runs the query and writes `out_file` unless `options.dry_run` is set, without printing anything.
+    #[deprecated(note = "use `evaluate_with_options` and `QueryResult::write_pending`")]
+    pub fn execute_with_options(
+        &self,
+        out_file: Option<&str>,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryExecutionReport, io::Error> {
+        let result = self.evaluate_with_options(out_file, touch, output_encoding, options)?;
+        result.write_pending()?;
+        Ok(result.report)
+    }
 
-        Ok(report)
+    /// Prints the rendered result and writes the output file.
+    #[allow(clippy::print_stdout)]
+    pub fn run_interactive_with_options(
+        &self,
+        out_file: Option<&str>,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryExecutionReport, io::Error> {
+        let result = self.evaluate_with_options(out_file, touch, output_encoding, options)?;
+        result.write_pending()?;
+        print!("{}", result.rendered);
+        Ok(result.report)
     }
 
     pub fn run_triple_interactive(&self) {
@@ -1170,39 +2058,33 @@ impl CirupQuery {
         print_triples_pretty(&triples);
     }
 
-    #[allow(clippy::print_stdout)]
-    pub fn run_triple_interactive_with_options(
-        &self,
-        options: &QueryRunOptions,
-    ) -> Result<QueryExecutionReport, io::Error> {
+    /// Runs the query and renders its rows without printing anything.
+    pub fn evaluate_triple_with_options(&self, options: &QueryRunOptions) -> Result<QueryResult<Triple>, io::Error> {
         options.validate_for_output(None)?;
         let filters = compile_query_filters(options)?;
         let query = wrap_triple_query_with_filters(&self.query, &filters);
 
         let (counts, triples) = filter_triples(self.engine.query_triple(&query), &filters, options.limit);
-        let report = QueryExecutionReport::from_options(options, "triple", counts, false, false);
-
-        if options.count_only {
-            print!("{}", render_count(counts.output_count));
-            return Ok(report);
-        }
-
-        if options.check {
-            if options.summary {
-                print!("{}", render_report(&report, options.output_format));
-            }
-            return Ok(report);
-        }
-
-        if !options.summary {
-            print!("{}", render_triples(&triples, options.output_format));
-        }
-
-        if options.summary {
-            print!("{}", render_report(&report, options.output_format));
-        }
+        Ok(evaluate_counted(
+            counts,
+            None,
+            triples,
+            "triple",
+            Vec::new(),
+            false,
+            options,
+            None,
+        ))
+    }
 
-        Ok(report)
+    #[allow(clippy::print_stdout)]
+    pub fn run_triple_interactive_with_options(
+        &self,
+        options: &QueryRunOptions,
+    ) -> Result<QueryExecutionReport, io::Error> {
+        let result = self.evaluate_triple_with_options(options)?;
+        print!("{}", result.rendered);
+        Ok(result.report)
     }
 }
 
@@ -1260,6 +2142,199 @@ fn test_query_diff_with_base() {
     assert_eq!(triples[0].value, String::from("Juste une vie a vivre"));
 }
 
+#[test]
+fn test_query_from_resources_has_no_side_effects() {
+    use crate::file::parse_resources;
+
+    let base = parse_resources("lblBoat=I'm on a boat\r\nlblYolo=You only live once\r\n", "restext")
+        .expect("failed to parse base");
+    let target = parse_resources("lblBoat=Je suis sur un bateau\r\n", "restext").expect("failed to parse target");
+
+    let query = CirupQuery::from_resources(QueryOperation::Diff, &[&base, &target]).expect("failed to build query");
+    let files = TempFiles::new();
+    let out_file = files.path("Strings.fr.restext");
+    let options = QueryRunOptions {
+        output_format: QueryOutputFormat::Jsonl,
+        ..QueryRunOptions::default()
+    };
+
+    let result = query
+        .evaluate_with_options(None, false, OutputEncoding::Utf8NoBom, &options)
+        .expect("evaluate failed");
+    assert_eq!(result.rows.len(), 1);
+    assert_eq!(result.rows[0].name, "lblYolo");
+    assert!(result.rendered.contains("\"lblYolo\""));
+    assert!(result.pending_writes.is_empty());
+
+    let result = query
+        .evaluate_with_options(Some(&out_file), false, OutputEncoding::Utf8NoBom, &options)
+        .expect("evaluate failed");
+    assert_eq!(result.pending_writes.len(), 1);
+    assert_eq!(result.pending_writes[0].path, out_file);
+    assert!(result.report.would_write);
+    assert!(!std::path::Path::new(&out_file).exists());
+
+    assert!(matches!(
+        CirupQuery::from_resources(QueryOperation::Diff, &[&base]),
+        Err(CirupError::InputCount {
+            operation: "file-diff",
+            expected: 2,
+            actual: 1
+        })
+    ));
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_execute_with_options_writes_output() {
+    let base = vec![
+        Resource::new("lblBoat", "I'm on a boat"),
+        Resource::new("lblYolo", "You only live once"),
+    ];
+    let target = vec![Resource::new("lblBoat", "Je suis sur un bateau")];
+    let query_config = QueryConfig {
+        backend: QueryBackendKind::Rusqlite,
+        ..default_query_config()
+    };
+    let query = CirupQuery::from_resources_with_query_config(QueryOperation::Diff, &[&base, &target], &query_config)
+        .expect("failed to build query");
+
+    let files = TempFiles::new();
+    let out_file = files.path("Strings.fr.restext");
+    let report = query
+        .execute_with_options(
+            Some(&out_file),
+            false,
+            OutputEncoding::Utf8NoBom,
+            &QueryRunOptions::default(),
+        )
+        .expect("execute failed");
+    assert!(report.wrote_output);
+    assert_eq!(
+        fs::read_to_string(&out_file).expect("output not written"),
+        "lblYolo=You only live once\r\n"
+    );
+}
+
+#[test]
+fn test_query_from_files_errors_by_kind() {
+    let files = TempFiles::new();
+    let base = files.path("Strings.restext");
+    fs::write(&base, "lblBoat=I'm on a boat\r\n").expect("failed to write base");
+    fs::write(files.path("Strings.fr.json"), "{ \"lblBoat\": ").expect("failed to write broken file");
+    fs::write(files.path("Strings.fr.txt"), "").expect("failed to write unsupported file");
+
+    let from_files = |target: &str| CirupQuery::from_files(QueryOperation::Diff, &[&base, &files.path(target)]);
+    assert!(matches!(from_files("Strings.de.restext"), Err(CirupError::Io(_))));
+    assert!(matches!(
+        from_files("Strings.fr.txt"),
+        Err(CirupError::UnsupportedFormat { extension, .. }) if extension == "txt"
+    ));
+    match from_files("Strings.fr.json") {
+        Err(CirupError::Parse(error)) => assert_eq!(error.file, files.path("Strings.fr.json")),
+        _ => panic!("expected a parse error"),
+    }
+    assert!(from_files("Strings.restext").is_ok());
+}
+
+#[test]
+fn test_query_keeps_source_spans_through_filters() {
+    use crate::file::parse_resources;
+
+    let base = parse_resources("lblBoat=I'm on a boat\r\nlblYolo=You only live once\r\n", "restext")
+        .expect("failed to parse base");
+    let target = parse_resources("lblBoat=Je suis sur un bateau\r\n", "restext").expect("failed to parse target");
+
+    let query = CirupQuery::from_resources(QueryOperation::Diff, &[&base, &target]).expect("failed to build query");
+    let options = QueryRunOptions {
+        key_filters: vec![String::from("^lbl")],
+        ..QueryRunOptions::default()
+    };
+
+    let result = query
+        .evaluate_with_options(None, false, OutputEncoding::Utf8NoBom, &options)
+        .expect("evaluate failed");
+    assert_eq!(result.rows.len(), 1);
+    let span = result.rows[0].span.as_ref().expect("lblYolo span");
+    assert_eq!((span.line, span.column, span.end_line, span.end_column), (2, 1, 2, 27));
+}
+
+#[test]
+fn test_query_attaches_spans_to_rows_of_one_table() {
+    use crate::file::parse_resources;
+
+    let mut engine = CirupEngine::new();
+    let base = parse_resources("lblBoat=Boat\r\nlblYolo=Yolo\r\n", "restext").expect("failed to parse base");
+    let target = parse_resources("lblBoat=Boat\r\n\r\nlblYolo=Vivre\r\n", "restext").expect("failed to parse target");
+    engine.register_table_from_resources("A", base);
+    engine.register_table_from_resources("B", target);
+
+    // the tables only have the key and value columns, so identical rows of both files are merged
+    assert_eq!(engine.query_resource("SELECT * FROM A UNION SELECT * FROM B").len(), 3);
+
+    // rows have the span of the only table with their key and value, and none when both tables have them
+    let lines = |query: &str| {
+        engine
+            .query_resource(query)
+            .iter()
+            .map(|resource| (resource.value.clone(), resource.span.as_ref().map(|span| span.line)))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        lines(MERGE_QUERY),
+        vec![(String::from("Boat"), None), (String::from("Vivre"), Some(3))]
+    );
+    assert_eq!(lines(CHANGE_QUERY), vec![(String::from("Yolo"), Some(2))]);
+    assert_eq!(lines(INTERSECT_QUERY), vec![(String::from("Boat"), None)]);
+
+    // rewritten values are in no table
+    assert_eq!(
+        lines("select a.key, upper(a.val) from a where a.key = 'lblYolo'"),
+        vec![(String::from("YOLO"), None)]
+    );
+}
+
+#[test]
+fn test_query_changes_render_inline_diff() {
+    let base = vec![
+        Resource::new("lblBoat", "I'm on a big boat"),
+        Resource::new("lblYolo", "You only live once"),
+    ];
+    let target = vec![Resource::new("lblBoat", "I'm on a boat")];
+
+    let query = CirupQuery::from_resources(QueryOperation::Change, &[&base, &target]).expect("failed to build query");
+    let options = QueryRunOptions {
+        output_format: QueryOutputFormat::Table,
+        color: true,
+        ..QueryRunOptions::default()
+    };
+
+    let result = query
+        .evaluate_changes_with_options(None, false, OutputEncoding::Utf8NoBom, &options)
+        .expect("evaluate failed");
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.rows[0].other_value.as_deref(), Some("I'm on a boat"));
+    assert_eq!(result.rows[1].spans, None);
+    assert_eq!(
+        result.rendered,
+        concat!(
+            "+---------+--------------------+\n",
+            "| name    | value              |\n",
+            "+---------+--------------------+\n",
+            "| lblBoat | I'm on a \x1b[32mbig \x1b[0mboat  |\n",
+            "+---------+--------------------+\n",
+            "| lblYolo | You only live once |\n",
+            "+---------+--------------------+\n",
+        )
+    );
+
+    let json = render_records(&result.rows[..1], QueryOutputFormat::Jsonl);
+    assert_eq!(
+        json,
+        "{\"name\":\"lblBoat\",\"value\":\"I'm on a big boat\",\"other_value\":\"I'm on a boat\",\"spans\":[{\"kind\":\"equal\",\"text\":\"I'm on a \"},{\"kind\":\"added\",\"text\":\"big \"},{\"kind\":\"equal\",\"text\":\"boat\"}]}\n"
+    );
+}
+
 #[test]
 #[cfg(feature = "turso-rust")]
 fn test_query_turso_remote_env_gated() {
@@ -1320,7 +2395,7 @@ fn test_query_run_options_filter_and_limit_resources() {
 #[test]
 fn test_render_resources_jsonl() {
     let resources = vec![Resource::new("hello", "world"), Resource::new("goodbye", "moon")];
-    let output = render_resources(&resources, QueryOutputFormat::Jsonl);
+    let output = render_records(&resources, QueryOutputFormat::Jsonl);
 
     assert_eq!(
         output,
@@ -1339,7 +2414,7 @@ fn test_render_resources_table_preserves_ascii_layout() {
         Resource::new("very.deep.object", "value"),
     ];
 
-    let output = render_resources(&resources, QueryOutputFormat::Table);
+    let output = render_records(&resources, QueryOutputFormat::Table);
     let expected = concat!(
         "+------------------+-----------------------+\n",
         "| name             | value                 |\n",
@@ -1364,7 +2439,7 @@ fn test_render_resources_table_preserves_ascii_layout() {
 #[test]
 fn test_render_triples_json() {
     let triples = vec![Triple::new("hello", "world", "base")];
-    let output = render_triples(&triples, QueryOutputFormat::Json);
+    let output = render_records(&triples, QueryOutputFormat::Json);
 
     assert_eq!(output, "[{\"name\":\"hello\",\"value\":\"world\",\"base\":\"base\"}]\n");
 }
@@ -1391,7 +2466,7 @@ fn benchmark_render_and_filter_resources_large_input() {
     let filters = compile_query_filters(&options).expect("failed to compile benchmark filters");
 
     let started = Instant::now();
-    let rendered = render_resources(&resources, QueryOutputFormat::Jsonl);
+    let rendered = render_records(&resources, QueryOutputFormat::Jsonl);
     let render_elapsed = started.elapsed();
 
     let started = Instant::now();
@@ -1575,7 +2650,7 @@ fn benchmark_render_resource_table_repeated() {
     let mut total_bytes = 0usize;
 
     for _ in 0..20_000 {
-        total_bytes += render_resources(&resources, QueryOutputFormat::Table).len();
+        total_bytes += render_records(&resources, QueryOutputFormat::Table).len();
     }
 
     let elapsed = started.elapsed();
@@ -1604,6 +2679,7 @@ fn benchmark_render_report_table_repeated() {
         would_write: true,
         wrote_output: false,
         change_detected: true,
+        merge: None,
     };
 
     let iterations = 20_000usize;
@@ -1706,6 +2782,7 @@ fn test_report_renders_as_table_summary() {
         would_write: true,
         wrote_output: false,
         change_detected: true,
+        merge: None,
     };
 
     let output = render_report(&report, QueryOutputFormat::Table);
@@ -1813,3 +2890,54 @@ fn test_value_filter_matches_resource_values() {
     assert!(!resources.is_empty());
     assert!(resources.iter().all(|resource| resource.value == "English"));
 }
+
+#[test]
+fn test_write_pending_reports_replaced_files_and_removes_staged_files() {
+    let files = TempFiles::new();
+    // a non-empty directory cannot be replaced by a file, so the second move fails
+    fs::create_dir_all(files.dir().join("b.restext").join("child")).expect("failed to create temp dir");
+
+    let outputs = [
+        PendingWrite {
+            path: files.path("a.restext"),
+            bytes: b"lblA=A\r\n".to_vec(),
+        },
+        PendingWrite {
+            path: files.path("b.restext"),
+            bytes: b"lblB=B\r\n".to_vec(),
+        },
+        PendingWrite {
+            path: files.path("c.restext"),
+            bytes: b"lblC=C\r\n".to_vec(),
+        },
+    ];
+    let result = evaluate_records_with_outputs(
+        Vec::<Resource>::new(),
+        "resource",
+        &outputs,
+        false,
+        &QueryRunOptions::default(),
+    )
+    .expect("evaluate failed");
+    assert_eq!(result.pending_writes.len(), 3);
+
+    let error = result.write_pending().expect_err("replacing a directory should fail");
+    assert!(error.to_string().starts_with(&files.path("b.restext")));
+    assert!(
+        error
+            .to_string()
+            .ends_with(&format!("already replaced: {}", files.path("a.restext")))
+    );
+    assert_eq!(
+        fs::read(files.path("a.restext")).expect("a was replaced"),
+        b"lblA=A\r\n"
+    );
+    assert!(!files.dir().join("c.restext").exists());
+
+    let mut names = fs::read_dir(files.dir())
+        .expect("failed to list temp dir")
+        .map(|entry| entry.expect("dir entry").file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["a.restext", "b.restext"]);
+}
@@ -1,9 +1,6 @@
 #[cfg(feature = "turso-rust")]
 use crate::config::TursoConfig;
 use crate::config::{QueryBackendKind, QueryConfig};
-use crate::file::load_resource_file;
-#[cfg(test)]
-use crate::file::vfile_set;
 use crate::{Resource, Triple};
 
 #[cfg(feature = "rusqlite-c")]
@@ -23,9 +20,7 @@ use tokio::runtime::{Builder as RuntimeBuilder, Runtime};
 use turso::{Builder as TursoBuilder, Connection as TursoConnection, Database as TursoDatabase, Error as TursoError};
 
 pub(crate) trait QueryBackend {
-    #[cfg(test)]
-    fn register_table_from_str(&mut self, table: &str, filename: &str, data: &str);
-    fn register_table_from_file(&mut self, table: &str, filename: &str);
+    fn register_table_from_resources(&mut self, table: &str, resources: Vec<Resource>);
     fn query_resource(&self, query: &str) -> Vec<Resource>;
     fn query_triple(&self, query: &str) -> Vec<Triple>;
 }
@@ -43,16 +38,6 @@ fn valid_table_name(table: &str) -> bool {
     chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
 }
 
-fn load_resources(filename: &str) -> Vec<Resource> {
-    match load_resource_file(filename) {
-        Ok(resources) => resources,
-        Err(e) => {
-            error!("failed to load {}: {}", filename, e);
-            Vec::new()
-        }
-    }
-}
-
 const TURSO_INSERT_CHUNK_SIZE: usize = 2000;
 
 const QUERY_SELECT_A: &str = "select * from a";
@@ -64,7 +49,7 @@ const QUERY_CHANGE: &str =
 const QUERY_MERGE: &str = "select a.key, case when b.val is not null then b.val else a.val end from a left outer join b on a.key = b.key union select b.key, b.val from b left outer join a on a.key = b.key where (a.key is null)";
 const QUERY_INTERSECT: &str = "select * from a intersect select * from b";
 const QUERY_SUBTRACT: &str = "select * from a where a.key not in (select b.key from b)";
-const QUERY_PULL_LEFT_JOIN: &str = "select a.key, a.val from a left outer join b on a.key = b.key";
+const QUERY_PULL_LEFT_JOIN: &str = "select a.key, case when b.val is not null then b.val else a.val end as val from a left outer join b on a.key = b.key";
 const QUERY_PUSH_CHANGED_VALUES: &str =
     "select b.key, b.val from b inner join a on (a.key = b.key) and (a.val <> b.val)";
 
@@ -303,16 +288,20 @@ fn query_resource_fast_from_tables(tables: &HashMap<String, Vec<Resource>>, quer
         let a = table_a?;
         let b = table_b?;
 
-        let mut b_match_count: HashMap<&str, usize> = HashMap::new();
+        let mut b_matches: HashMap<&str, Vec<&Resource>> = HashMap::new();
         for resource in b {
-            *b_match_count.entry(resource.name.as_str()).or_insert(0) += 1;
+            b_matches.entry(resource.name.as_str()).or_default().push(resource);
         }
 
-        let mut resources = Vec::new();
+        let mut resources = Vec::with_capacity(a.len());
         for resource in a {
-            let repeat = b_match_count.get(resource.name.as_str()).copied().unwrap_or(1);
-            for _ in 0..repeat {
-                resources.push(resource.clone());
+            match b_matches.get(resource.name.as_str()) {
+                Some(matches) => resources.extend(
+                    matches
+                        .iter()
+                        .map(|matched| Resource::new(&resource.name, &matched.value)),
+                ),
+                None => resources.push(resource.clone()),
             }
         }
 
@@ -517,15 +506,7 @@ impl RusqliteBackend {
 
 #[cfg(feature = "rusqlite-c")]
 impl QueryBackend for RusqliteBackend {
-    #[cfg(test)]
-    fn register_table_from_str(&mut self, table: &str, filename: &str, data: &str) {
-        vfile_set(filename, data);
-        let resources = load_resources(filename);
-        self.register_table_with_resources(table, resources);
-    }
-
-    fn register_table_from_file(&mut self, table: &str, filename: &str) {
-        let resources = load_resources(filename);
+    fn register_table_from_resources(&mut self, table: &str, resources: Vec<Resource>) {
         self.register_table_with_resources(table, resources);
     }
 
@@ -674,17 +655,7 @@ impl TursoLocalBackend {
 
 #[cfg(feature = "turso-rust")]
 impl QueryBackend for TursoLocalBackend {
-    #[cfg(test)]
-    fn register_table_from_str(&mut self, table: &str, filename: &str, data: &str) {
-        vfile_set(filename, data);
-        let resources = load_resources(filename);
-        if let Err(e) = self.register_table_with_resources(table, &resources) {
-            error!("failed to register table {} in turso local backend: {}", table, e);
-        }
-    }
-
-    fn register_table_from_file(&mut self, table: &str, filename: &str) {
-        let resources = load_resources(filename);
+    fn register_table_from_resources(&mut self, table: &str, resources: Vec<Resource>) {
         if let Err(e) = self.register_table_with_resources(table, &resources) {
             error!("failed to register table {} in turso local backend: {}", table, e);
         }
@@ -825,17 +796,7 @@ impl TursoRemoteBackend {
 
 #[cfg(feature = "turso-rust")]
 impl QueryBackend for TursoRemoteBackend {
-    #[cfg(test)]
-    fn register_table_from_str(&mut self, table: &str, filename: &str, data: &str) {
-        vfile_set(filename, data);
-        let resources = load_resources(filename);
-        if let Err(e) = self.register_table_with_resources(table, &resources) {
-            error!("failed to register table {} in turso remote backend: {}", table, e);
-        }
-    }
-
-    fn register_table_from_file(&mut self, table: &str, filename: &str) {
-        let resources = load_resources(filename);
+    fn register_table_from_resources(&mut self, table: &str, resources: Vec<Resource>) {
         if let Err(e) = self.register_table_with_resources(table, &resources) {
             error!("failed to register table {} in turso remote backend: {}", table, e);
         }
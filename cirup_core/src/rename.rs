@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::Resource;
+use crate::file::{
+    OutputEncoding, UTF8_BOM, culture_family, decode_utf8_text, encode_utf8_owned, load_resources,
+    output_bytes_for_file,
+};
+use crate::query::{PendingWrite, QueryRecord, QueryResult, QueryRunOptions, evaluate_records_with_outputs};
+use crate::resx::push_escaped_xml_attr;
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * Key renaming across the files of a family, e.g. Strings.resx, Strings.fr.resx and Strings.de.resx.
+ * The keys of .resx and .restext files are renamed in place, keeping comments and formatting;
+ * other formats are written again, so renamed JSON keys move to their new nested path.
+ * No file is written unless every file can be renamed without collisions.
+ */
+
+/// A key to rename. A trailing `*` renames every key with the prefix, e.g. `lbl*` to `label.*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRename {
+    from: String,
+    to: String,
+}
+
+impl KeyRename {
+    pub fn new(from: &str, to: &str) -> Result<Self, io::Error> {
+        let invalid = |message: String| Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+
+        if from.is_empty() || to.is_empty() {
+            return invalid(String::from("key names cannot be empty"));
+        }
+
+        for name in [from, to] {
+            if name.trim_end_matches('*').contains('*') || name.ends_with("**") {
+                return invalid(format!("'{}' can only have a single trailing *", name));
+            }
+        }
+
+        if from.ends_with('*') != to.ends_with('*') {
+            return invalid(format!(
+                "'{}' and '{}' must both end with * to rename a prefix",
+                from, to
+            ));
+        }
+
+        Ok(Self {
+            from: from.to_owned(),
+            to: to.to_owned(),
+        })
+    }
+
+    fn apply(&self, name: &str) -> Option<String> {
+        match (self.from.strip_suffix('*'), self.to.strip_suffix('*')) {
+            (Some(from), Some(to)) => name.strip_prefix(from).map(|rest| format!("{}{}", to, rest)),
+            _ => (name == self.from).then(|| self.to.clone()),
+        }
+    }
+}
+
+/// Loads renames from a JSON object whose keys are the old names and values the new names.
+pub fn load_key_renames(filename: &str) -> Result<Vec<KeyRename>, io::Error> {
+    let error = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", filename, message));
+    let text = fs::read_to_string(filename).map_err(|e| error(e.to_string()))?;
+    let mapping: Map<String, Value> = serde_json::from_str(&text).map_err(|e| error(e.to_string()))?;
+
+    mapping
+        .iter()
+        .map(|(from, to)| match to {
+            Value::String(to) => KeyRename::new(from, to).map_err(|e| error(e.to_string())),
+            _ => Err(error(format!("the new name of '{}' is not a string", from))),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RenamedKey {
+    pub file: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl QueryRecord for RenamedKey {
+    const COLUMNS: &'static [&'static str] = &["file", "from", "to"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.file.clone(), self.from.clone(), self.to.clone()]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.from
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.to
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRenameResult {
+    pub renamed: Vec<RenamedKey>,
+    /// The new content of the files with renamed keys.
+    pub outputs: Vec<PendingWrite>,
+}
+
+pub struct KeyRenaming {
+    file: String,
+    files: Vec<String>,
+    renames: Vec<KeyRename>,
+}
+
+/// Renames keys across `file` and the other files of its family.
+pub fn rename_keys(file: &str, renames: Vec<KeyRename>) -> KeyRenaming {
+    KeyRenaming {
+        file: file.to_owned(),
+        files: culture_family(file),
+        renames,
+    }
+}
+
+fn file_extension(file: &str) -> String {
+    Path::new(file)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Returns the byte offset of a 1-based line and column in `text`, where columns count characters.
+fn text_offset(text: &str, line: usize, column: usize) -> Option<usize> {
+    let line_start = match line {
+        0 => return None,
+        1 => 0,
+        _ => text.match_indices('\n').nth(line - 2)?.0 + 1,
+    };
+
+    text[line_start..]
+        .char_indices()
+        .nth(column.checked_sub(1)?)
+        .map(|(index, _)| line_start + index)
+}
+
+/// Returns the byte range of the value of the `name` attribute of the start tag at the start of `text`.
+fn name_attribute_range(text: &str) -> Option<(usize, usize)> {
+    let tag = &text[..text.find('>')?];
+    let mut search = 0;
+
+    while let Some(found) = tag[search..].find("name") {
+        let start = search + found;
+        search = start + "name".len();
+        if !tag[..start].ends_with(char::is_whitespace) {
+            continue;
+        }
+
+        let Some(rest) = tag[search..].trim_start().strip_prefix('=') else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        let quote = rest.chars().next().filter(|ch| *ch == '"' || *ch == '\'')?;
+        let value_start = tag.len() - rest.len() + 1;
+        let value_len = tag[value_start..].find(quote)?;
+        return Some((value_start, value_start + value_len));
+    }
+
+    None
+}
+
+/// Renames keys in the text of a .resx or .restext file, at the span of their resource.
+fn rename_in_text(
+    text: &str,
+    extension: &str,
+    resources: &[Resource],
+    new_names: &[Option<String>],
+) -> Result<String, String> {
+    let mut edits = Vec::new();
+
+    for (resource, new_name) in resources.iter().zip(new_names) {
+        let Some(new_name) = new_name else {
+            continue;
+        };
+        let start = resource
+            .span
+            .as_ref()
+            .and_then(|span| text_offset(text, span.line, span.column))
+            .ok_or_else(|| format!("'{}' could not be located", resource.name))?;
+
+        if extension == "resx" {
+            let (value_start, value_end) = name_attribute_range(&text[start..])
+                .ok_or_else(|| format!("'{}' has no name attribute", resource.name))?;
+            let mut escaped = String::with_capacity(new_name.len());
+            push_escaped_xml_attr(&mut escaped, new_name);
+            edits.push((start + value_start, start + value_end, escaped));
+        } else if text[start..].starts_with(resource.name.as_str()) {
+            edits.push((start, start + resource.name.len(), new_name.clone()));
+        } else {
+            return Err(format!("'{}' could not be located", resource.name));
+        }
+    }
+
+    let mut text = text.to_owned();
+    edits.sort_by_key(|(start, ..)| *start);
+    for (start, end, replacement) in edits.into_iter().rev() {
+        text.replace_range(start..end, &replacement);
+    }
+
+    Ok(text)
+}
+
+fn valid_restext_name(name: &str) -> bool {
+    name.chars().all(|ch| ch == '_' || ch.is_alphanumeric())
+}
+
+impl KeyRenaming {
+    /// The files of the family, neutral file first.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    fn new_name(&self, name: &str) -> Option<String> {
+        self.renames
+            .iter()
+            .find_map(|rename| rename.apply(name))
+            .filter(|new_name| new_name != name)
+    }
+
+    /// Returns the collisions of renaming the keys of `file`: keys renamed to a key that still exists,
+    /// and in JSON files, keys that would have to be both a value and an object.
+    fn collisions(file: &str, resources: &[Resource], new_names: &[Option<String>]) -> Vec<String> {
+        let extension = file_extension(file);
+        let final_names = resources
+            .iter()
+            .zip(new_names)
+            .map(|(resource, new_name)| new_name.as_deref().unwrap_or(&resource.name))
+            .collect::<Vec<_>>();
+        let mut name_counts: HashMap<&str, usize> = HashMap::new();
+        for name in &final_names {
+            *name_counts.entry(name).or_default() += 1;
+        }
+
+        let mut collisions = Vec::new();
+        for (resource, new_name) in resources.iter().zip(new_names) {
+            let Some(new_name) = new_name.as_deref() else {
+                continue;
+            };
+            let collision =
+                |message: &str| format!("{}: renaming '{}' to '{}' {}", file, resource.name, new_name, message);
+
+            if new_name.is_empty() {
+                collisions.push(collision("leaves it without a name"));
+            } else if name_counts.get(new_name).copied().unwrap_or_default() > 1 {
+                collisions.push(collision("collides with another key"));
+            } else if extension == "restext" && !valid_restext_name(new_name) {
+                collisions.push(collision("is not a valid .restext key"));
+            } else if extension == "json"
+                && final_names.iter().any(|name| {
+                    name.strip_prefix(new_name).is_some_and(|rest| rest.starts_with('.'))
+                        || new_name.strip_prefix(name).is_some_and(|rest| rest.starts_with('.'))
+                })
+            {
+                collisions.push(collision("collides with a nested key"));
+            }
+        }
+
+        collisions
+    }
+
+    /// Renames the keys of every file of the family, without writing anything. Fails without renaming
+    /// any key when a rename collides with another key in one of the files.
+    pub fn run(&self, output_encoding: OutputEncoding) -> Result<KeyRenameResult, io::Error> {
+        if self.files.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{}: no resource files found", self.file),
+            ));
+        }
+
+        let mut renamed = Vec::new();
+        let mut outputs = Vec::new();
+        let mut collisions = Vec::new();
+        let mut matched = vec![false; self.renames.len()];
+
+        for file in &self.files {
+            let resources = load_resources(file)?;
+            let new_names = resources
+                .iter()
+                .map(|resource| self.new_name(&resource.name))
+                .collect::<Vec<_>>();
+            for resource in &resources {
+                for (matched, rename) in matched.iter_mut().zip(&self.renames) {
+                    *matched |= rename.apply(&resource.name).is_some();
+                }
+            }
+
+            if new_names.iter().all(Option::is_none) {
+                continue;
+            }
+
+            let file_collisions = Self::collisions(file, &resources, &new_names);
+            if !file_collisions.is_empty() {
+                collisions.extend(file_collisions);
+                continue;
+            }
+
+            let extension = file_extension(file);
+            let bytes = if extension == "resx" || extension == "restext" {
+                let bytes = fs::read(file).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", file, e)))?;
+                let text = rename_in_text(decode_utf8_text(&bytes)?, &extension, &resources, &new_names)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", file, e)))?;
+                let encoding = if bytes.starts_with(&UTF8_BOM) {
+                    OutputEncoding::Utf8Bom
+                } else {
+                    OutputEncoding::Utf8NoBom
+                };
+                encode_utf8_owned(text, encoding)
+            } else {
+                let resources = resources
+                    .iter()
+                    .zip(&new_names)
+                    .map(|(resource, new_name)| match new_name {
+                        Some(new_name) => Resource::new(new_name, &resource.value),
+                        None => resource.clone(),
+                    })
+                    .collect::<Vec<_>>();
+                output_bytes_for_file(file, &resources, output_encoding)?
+            };
+
+            renamed.extend(resources.iter().zip(new_names).filter_map(|(resource, new_name)| {
+                Some(RenamedKey {
+                    file: file.clone(),
+                    from: resource.name.clone(),
+                    to: new_name?,
+                })
+            }));
+            outputs.push(PendingWrite {
+                path: file.clone(),
+                bytes,
+            });
+        }
+
+        if !collisions.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, collisions.join("\n")));
+        }
+
+        for (rename, _) in self.renames.iter().zip(matched).filter(|(_, matched)| !matched) {
+            warn!("no key matches '{}' in {}", rename.from, self.files.join(", "));
+        }
+
+        Ok(KeyRenameResult { renamed, outputs })
+    }
+
+    /// Returns the renamed keys and the files to write, which are written together.
+    pub fn evaluate_with_options(
+        &self,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<RenamedKey>, io::Error> {
+        let result = self.run(output_encoding)?;
+        info!(
+            "renamed {} key(s) in {} file(s)",
+            result.renamed.len(),
+            result.outputs.len()
+        );
+
+        evaluate_records_with_outputs(result.renamed, "renamed-key", &result.outputs, touch, options)
+    }
+}
+
+#[test]
+fn test_key_rename_patterns() {
+    let rename = KeyRename::new("lblTitle", "title").expect("exact rename");
+    assert_eq!(rename.apply("lblTitle").as_deref(), Some("title"));
+    assert_eq!(rename.apply("lblTitle2"), None);
+
+    let rename = KeyRename::new("lbl*", "label.*").expect("prefix rename");
+    assert_eq!(rename.apply("lblTitle").as_deref(), Some("label.Title"));
+    assert_eq!(rename.apply("btnOk"), None);
+
+    assert!(KeyRename::new("lbl*", "label").is_err());
+    assert!(KeyRename::new("l*bl", "label").is_err());
+    assert!(KeyRename::new("", "label").is_err());
+}
+
+#[test]
+fn test_rename_keys_across_family_keeps_comments() {
+    let files = TempFiles::new();
+    let neutral = files.path("Strings.resx");
+    let restext = files.path("Strings.fr.restext");
+    fs::write(
+        &neutral,
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<root>
+  <!-- greetings -->
+  <data name="lblHello" xml:space="preserve">
+    <value>Hello</value>
+    <comment>shown on the home page</comment>
+  </data>
+  <data name="lblBye" xml:space="preserve">
+    <value>Bye</value>
+  </data>
+</root>"#,
+    )
+    .expect("failed to write neutral file");
+    fs::write(
+        files.path("Strings.fr.resx"),
+        "<root>\n  <data name=\"lblHello\"><value>Bonjour</value></data>\n</root>",
+    )
+    .expect("failed to write french file");
+    fs::write(&restext, "; not in the resx family\r\nlblHello=Bonjour\r\n").expect("failed to write restext file");
+    for unrelated in ["Strings.Web.resx", "Strings.App.resx"] {
+        fs::write(
+            files.path(unrelated),
+            "<root>\n  <data name=\"lblHello\"><value>Hello</value></data>\n</root>",
+        )
+        .expect("failed to write unrelated file");
+    }
+
+    let renames = vec![KeyRename::new("lbl*", "label*").expect("prefix rename")];
+    let result = rename_keys(&neutral, renames.clone())
+        .run(OutputEncoding::Utf8NoBom)
+        .expect("rename failed");
+
+    let renamed_files = result
+        .outputs
+        .iter()
+        .map(|output| output.path.rsplit(['/', '\\']).next().unwrap_or_default())
+        .collect::<Vec<_>>();
+    assert_eq!(renamed_files, vec!["Strings.resx", "Strings.fr.resx"]);
+    let neutral_text = String::from_utf8(result.outputs[0].bytes.clone()).expect("utf-8 output");
+    assert!(neutral_text.contains("<!-- greetings -->\n  <data name=\"labelHello\" xml:space=\"preserve\">"));
+    assert!(neutral_text.contains("<comment>shown on the home page</comment>"));
+    assert!(neutral_text.contains("<data name=\"labelBye\""));
+    let french_text = String::from_utf8(result.outputs[1].bytes.clone()).expect("utf-8 output");
+    assert_eq!(
+        french_text,
+        "<root>\n  <data name=\"labelHello\"><value>Bonjour</value></data>\n</root>"
+    );
+    assert_eq!(result.renamed.len(), 3);
+
+    let result = rename_keys(&restext, renames)
+        .run(OutputEncoding::Utf8NoBom)
+        .expect("rename failed");
+    assert_eq!(
+        result.outputs[0].bytes,
+        b"; not in the resx family\r\nlabelHello=Bonjour\r\n"
+    );
+}
+
+#[test]
+fn test_rename_keys_rejects_collisions_without_renaming() {
+    let files = TempFiles::new();
+    let neutral = files.path("Strings.json");
+    fs::write(&neutral, r#"{"title": "Title", "menu": {"open": "Open"}}"#).expect("failed to write neutral file");
+    fs::write(
+        files.path("Strings.fr.json"),
+        r#"{"title": "Titre", "caption": "Légende"}"#,
+    )
+    .expect("failed to write french file");
+
+    let collision = rename_keys(&neutral, vec![KeyRename::new("title", "caption").expect("rename")])
+        .run(OutputEncoding::Utf8NoBom)
+        .expect_err("caption already exists in the french file");
+    assert!(
+        collision
+            .to_string()
+            .contains("Strings.fr.json: renaming 'title' to 'caption'")
+    );
+
+    let nested =
+        rename_keys(&neutral, vec![KeyRename::new("title", "menu").expect("rename")]).run(OutputEncoding::Utf8NoBom);
+    assert!(nested.is_err());
+
+    let result = rename_keys(&neutral, vec![KeyRename::new("title", "page.title").expect("rename")])
+        .run(OutputEncoding::Utf8NoBom)
+        .expect("rename failed");
+    let neutral_text = String::from_utf8(result.outputs[0].bytes.clone()).expect("utf-8 output");
+    assert!(neutral_text.contains("\"page\": {\n        \"title\": \"Title\"\n    }"));
+}
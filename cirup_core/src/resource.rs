@@ -2,10 +2,24 @@ use std::fmt;
 
 use serde::Serialize;
 
+/// Where a resource is defined in its file, from the 1-based line and column of its first character
+/// to the line and column just past its last one.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SourceSpan {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
 #[derive(Clone, Serialize)]
 pub struct Resource {
     pub name: String,
     pub value: String,
+    /// Set for resources parsed from text formats; not compared by `==`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>,
 }
 
 impl fmt::Debug for Resource {
@@ -31,11 +45,21 @@ impl Resource {
         Resource {
             name: name.to_owned(),
             value: value.to_owned(),
+            span: None,
         }
     }
 
-    #[cfg(feature = "rusqlite-c")]
     pub(crate) fn from_owned(name: String, value: String) -> Self {
-        Resource { name, value }
+        Resource {
+            name,
+            value,
+            span: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_span(mut self, span: Option<SourceSpan>) -> Self {
+        self.span = span;
+        self
     }
 }
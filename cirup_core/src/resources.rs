@@ -0,0 +1,371 @@
+use std::error::Error;
+use std::io;
+
+use crate::Resource;
+use crate::file::{FileFormat, OutputEncoding};
+
+/*
+ * .resources binary file format, as written by System.Resources.ResourceWriter:
+ * https://github.com/dotnet/runtime/blob/main/src/libraries/System.Private.CoreLib/src/System/Resources/ResourceReader.cs
+ * https://github.com/dotnet/runtime/blob/main/src/libraries/System.Private.CoreLib/src/System/Resources/ResourceWriter.cs
+ *
+ * Only string resources are supported; other resources are skipped when reading.
+ */
+
+pub(crate) struct ResourcesFileFormat;
+
+const MAGIC_NUMBER: u32 = 0xBEEF_CACE;
+const RESOURCE_MANAGER_HEADER_VERSION: i32 = 1;
+const RESOURCE_READER_VERSION: i32 = 2;
+const RESOURCE_READER_TYPE: &str =
+    "System.Resources.ResourceReader, mscorlib, Version=4.0.0.0, Culture=neutral, PublicKeyToken=b77a5c561934e089";
+const RESOURCE_SET_TYPE: &str = "System.Resources.RuntimeResourceSet";
+
+/// ResourceTypeCode.String in version 2 files.
+const TYPE_CODE_STRING: usize = 1;
+
+/// FastResourceComparer.HashFunction, computed over UTF-16 code units.
+fn resource_name_hash(name: &str) -> i32 {
+    let mut hash: u32 = 5381;
+    for unit in name.encode_utf16() {
+        hash = (hash << 5).wrapping_add(hash) ^ u32::from(unit);
+    }
+    i32::from_le_bytes(hash.to_le_bytes())
+}
+
+fn write_i32(output: &mut Vec<u8>, value: i32) {
+    output.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_7bit_encoded_int(output: &mut Vec<u8>, value: usize) {
+    let mut value = value;
+    while value >= 0x80 {
+        output.push(u8::try_from(value & 0x7F).unwrap_or_default() | 0x80);
+        value >>= 7;
+    }
+    output.push(u8::try_from(value).unwrap_or_default());
+}
+
+/// BinaryWriter.Write(string) with UTF-8 encoding.
+fn write_utf8_string(output: &mut Vec<u8>, value: &str) {
+    write_7bit_encoded_int(output, value.len());
+    output.extend_from_slice(value.as_bytes());
+}
+
+/// BinaryWriter.Write(string) with UTF-16LE encoding, used for resource names.
+fn write_utf16_string(output: &mut Vec<u8>, value: &str) {
+    let units = value.encode_utf16().collect::<Vec<_>>();
+    write_7bit_encoded_int(output, units.len() * 2);
+    for unit in units {
+        output.extend_from_slice(&unit.to_le_bytes());
+    }
+}
+
+fn to_i32(value: usize) -> Result<i32, io::Error> {
+    i32::try_from(value).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, ".resources file exceeds 2 GiB"))
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn seek(&mut self, position: usize) -> Result<(), Box<dyn Error>> {
+        if position > self.bytes.len() {
+            return Err(format!("offset {} is past the end of the file", position).into());
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or("unexpected end of file")?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn read_i32(&mut self) -> Result<i32, Box<dyn Error>> {
+        let bytes = self.read_bytes(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_offset(&mut self) -> Result<usize, Box<dyn Error>> {
+        let value = self.read_i32()?;
+        usize::try_from(value).map_err(|_| format!("invalid negative offset {}", value).into())
+    }
+
+    fn read_7bit_encoded_int(&mut self) -> Result<usize, Box<dyn Error>> {
+        let mut value = 0usize;
+        for shift in (0..35).step_by(7) {
+            let byte = self.read_bytes(1)?[0];
+            value |= usize::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("invalid 7-bit encoded integer".into())
+    }
+
+    fn read_utf8_string(&mut self) -> Result<String, Box<dyn Error>> {
+        let len = self.read_7bit_encoded_int()?;
+        Ok(String::from_utf8(self.read_bytes(len)?.to_vec())?)
+    }
+
+    fn read_utf16_string(&mut self) -> Result<String, Box<dyn Error>> {
+        let len = self.read_7bit_encoded_int()?;
+        let units = self
+            .read_bytes(len)?
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+        Ok(String::from_utf16(&units)?)
+    }
+}
+
+impl ResourcesFileFormat {
+    pub(crate) const EXTENSION: &'static str = "resources";
+}
+
+impl FileFormat for ResourcesFileFormat {
+    fn name(&self) -> &str {
+        Self::EXTENSION
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[Self::EXTENSION]
+    }
+
+    fn parse_from_bytes(&self, bytes: &[u8]) -> Result<Vec<Resource>, Box<dyn Error>> {
+        let mut reader = ByteReader::new(bytes);
+
+        if reader.read_i32()?.to_le_bytes() != MAGIC_NUMBER.to_le_bytes() {
+            return Err("not a .resources file: invalid magic number".into());
+        }
+
+        let header_version = reader.read_i32()?;
+        let header_size = reader.read_offset()?;
+        if header_version > RESOURCE_MANAGER_HEADER_VERSION {
+            reader.read_bytes(header_size)?;
+        } else {
+            let reader_type = reader.read_utf8_string()?;
+            if !reader_type.starts_with("System.Resources.ResourceReader") {
+                return Err(format!("unsupported resource reader '{}'", reader_type).into());
+            }
+            reader.read_utf8_string()?;
+        }
+
+        let version = reader.read_i32()?;
+        if version != 1 && version != RESOURCE_READER_VERSION {
+            return Err(format!("unsupported .resources version {}", version).into());
+        }
+
+        let count = reader.read_offset()?;
+        let type_count = reader.read_offset()?;
+        if count > bytes.len() || type_count > bytes.len() {
+            return Err("invalid resource or type count".into());
+        }
+
+        let mut type_names = Vec::with_capacity(type_count);
+        for _ in 0..type_count {
+            type_names.push(reader.read_utf8_string()?);
+        }
+
+        let misalignment = reader.position & 7;
+        if misalignment != 0 {
+            reader.read_bytes(8 - misalignment)?;
+        }
+
+        reader.read_bytes(count * 4)?;
+        let mut name_positions = Vec::with_capacity(count);
+        for _ in 0..count {
+            name_positions.push(reader.read_offset()?);
+        }
+        name_positions.sort_unstable();
+
+        let data_section = reader.read_offset()?;
+        let name_section = reader.position;
+        let mut resources = Vec::with_capacity(count);
+
+        for name_position in name_positions {
+            reader.seek(name_section + name_position)?;
+            let name = reader.read_utf16_string()?;
+            let data_position = reader.read_offset()?;
+
+            reader.seek(data_section + data_position)?;
+            let type_code = reader.read_7bit_encoded_int()?;
+            // version 1 files only have type table indexes, version 2 files have primitive type codes
+            let is_string = if version == 1 {
+                type_names
+                    .get(type_code)
+                    .is_some_and(|type_name| type_name.starts_with("System.String"))
+            } else {
+                type_code == TYPE_CODE_STRING
+            };
+
+            if !is_string {
+                debug!("skipping non-string resource '{}'", name);
+                continue;
+            }
+
+            let value = reader.read_utf8_string()?;
+            resources.push(Resource::from_owned(name, value));
+        }
+
+        Ok(resources)
+    }
+
+    fn write_to_bytes(&self, resources: &[Resource], _output_encoding: OutputEncoding) -> Result<Vec<u8>, io::Error> {
+        let entries = resources
+            .iter()
+            .map(|resource| {
+                let mut value = Vec::with_capacity(resource.value.len() + 6);
+                write_7bit_encoded_int(&mut value, TYPE_CODE_STRING);
+                write_utf8_string(&mut value, &resource.value);
+                (resource.name.as_str(), value)
+            })
+            .collect();
+
+        write_resources(entries)
+    }
+}
+
+/// Writes a version 2 file from (name, type code and serialized value) entries.
+fn write_resources(mut entries: Vec<(&str, Vec<u8>)>) -> Result<Vec<u8>, io::Error> {
+    entries.sort_by(|a, b| a.0.encode_utf16().cmp(b.0.encode_utf16()));
+    // ResourceReader looks names up by binary search, so a name can only be written once
+    if let Some(pair) = entries.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("duplicate resource name '{}'", pair[0].0),
+        ));
+    }
+
+    let mut names = Vec::new();
+    let mut data = Vec::new();
+    let mut hashes = Vec::with_capacity(entries.len());
+
+    for (name, value) in &entries {
+        hashes.push((resource_name_hash(name), to_i32(names.len())?));
+        write_utf16_string(&mut names, name);
+        write_i32(&mut names, to_i32(data.len())?);
+        data.extend_from_slice(value);
+    }
+    hashes.sort_unstable();
+
+    let mut header = Vec::new();
+    write_utf8_string(&mut header, RESOURCE_READER_TYPE);
+    write_utf8_string(&mut header, RESOURCE_SET_TYPE);
+
+    let mut output = Vec::with_capacity(64 + header.len() + hashes.len() * 8 + names.len() + data.len());
+    output.extend_from_slice(&MAGIC_NUMBER.to_le_bytes());
+    write_i32(&mut output, RESOURCE_MANAGER_HEADER_VERSION);
+    write_i32(&mut output, to_i32(header.len())?);
+    output.extend_from_slice(&header);
+
+    write_i32(&mut output, RESOURCE_READER_VERSION);
+    write_i32(&mut output, to_i32(entries.len())?);
+    write_i32(&mut output, 0);
+
+    let misalignment = output.len() & 7;
+    if misalignment != 0 {
+        output.extend(b"PAD".iter().cycle().take(8 - misalignment));
+    }
+
+    for (hash, _) in &hashes {
+        write_i32(&mut output, *hash);
+    }
+    for (_, position) in &hashes {
+        write_i32(&mut output, *position);
+    }
+
+    let data_section = output.len() + 4 + names.len();
+    write_i32(&mut output, to_i32(data_section)?);
+    output.extend_from_slice(&names);
+    output.extend_from_slice(&data);
+
+    Ok(output)
+}
+
+#[test]
+fn test_resources_name_hash() {
+    // hash = ((hash << 5) + hash) ^ ch, starting from 5381
+    assert_eq!(resource_name_hash(""), 5381);
+    assert_eq!(resource_name_hash("a"), 177_604);
+}
+
+#[test]
+fn test_resources_round_trip() {
+    let resources = vec![
+        Resource::new("lblYolo", "You only live once"),
+        Resource::new("lblBoat", "I'm on a boat"),
+        Resource::new("lblUnicode", "Été 日本語 \u{1F600}"),
+        Resource::new("lblEmpty", ""),
+    ];
+
+    let bytes = ResourcesFileFormat
+        .write_to_bytes(&resources, OutputEncoding::Utf8NoBom)
+        .expect("failed to write .resources");
+    assert_eq!(&bytes[0..4], &[0xCE, 0xCA, 0xEF, 0xBE]);
+
+    let parsed = ResourcesFileFormat
+        .parse_from_bytes(&bytes)
+        .expect("failed to parse .resources");
+    let names = parsed.iter().map(|resource| resource.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["lblBoat", "lblEmpty", "lblUnicode", "lblYolo"]);
+    assert_eq!(parsed[2].value, "Été 日本語 \u{1F600}");
+}
+
+#[test]
+fn test_resources_layout_and_non_string_values() {
+    // ResourceTypeCode.Int32 is 8
+    let mut count = vec![8u8];
+    count.extend_from_slice(&42i32.to_le_bytes());
+    let mut label = Vec::new();
+    write_7bit_encoded_int(&mut label, TYPE_CODE_STRING);
+    write_utf8_string(&mut label, "Files");
+
+    let bytes = write_resources(vec![("lblFiles", label), ("count", count)]).expect("failed to write .resources");
+
+    // resource manager header, reader version, 2 resources, no types, then "PAD" padding to 8 bytes
+    let header_size =
+        usize::try_from(i32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]])).expect("header size");
+    let counts = 12 + header_size;
+    assert_eq!(&bytes[counts..counts + 12], &[2, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0]);
+    let padding = (8 - (counts + 12) % 8) % 8;
+    let expected_padding = b"PADPADP".iter().take(padding).copied().collect::<Vec<_>>();
+    assert_eq!(&bytes[counts + 12..counts + 12 + padding], expected_padding.as_slice());
+
+    let resources = ResourcesFileFormat
+        .parse_from_bytes(&bytes)
+        .expect("failed to parse .resources");
+    assert_eq!(resources, vec![Resource::new("lblFiles", "Files")]);
+}
+
+#[test]
+fn test_resources_rejects_duplicate_names_and_oversized_files() {
+    let resources = vec![
+        Resource::new("lblBoat", "I'm on a boat"),
+        Resource::new("lblYolo", "You only live once"),
+        Resource::new("lblBoat", "On a boat"),
+    ];
+
+    let error = ResourcesFileFormat
+        .write_to_bytes(&resources, OutputEncoding::Utf8NoBom)
+        .expect_err("duplicate names should not be written");
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(error.to_string(), "duplicate resource name 'lblBoat'");
+
+    let error = to_i32(usize::MAX).expect_err("offsets past 2 GiB should not be written");
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(to_i32(64).ok(), Some(64));
+}
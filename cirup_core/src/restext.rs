@@ -1,10 +1,10 @@
 #[cfg(test)]
 use std::time::Instant;
 
-use crate::Resource;
-use crate::file::FileFormat;
-use crate::file::load_string_from_file;
+use crate::file::{FileFormat, OutputEncoding, decode_utf8_text, encode_utf8_owned};
+use crate::{Resource, SourceSpan};
 use std::error::Error;
+use std::io;
 
 /*
  * .restext file format:
@@ -45,29 +45,31 @@ pub(crate) fn escape_newlines(input: &str) -> String {
     output
 }
 
-impl FileFormat for RestextFileFormat {
-    const EXTENSION: &'static str = "restext";
+impl RestextFileFormat {
+    pub(crate) const EXTENSION: &'static str = "restext";
 
-    fn parse_from_str(&self, text: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
+    pub(crate) fn parse_from_str(text: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
         let mut resources: Vec<Resource> = Vec::new();
         let text = text.strip_prefix('\u{feff}').unwrap_or(text);
 
-        for line in text.lines() {
+        for (index, line) in text.lines().enumerate() {
             if let Some((name, value)) = parse_restext_line(line) {
-                let resource = Resource::new(name, value);
-                resources.push(resource);
+                let indent = line.len() - line.trim_start_matches(char::is_whitespace).len();
+                let span = SourceSpan {
+                    file: String::new(),
+                    line: index + 1,
+                    column: line[..indent].chars().count() + 1,
+                    end_line: index + 1,
+                    end_column: line.chars().count() + 1,
+                };
+                resources.push(Resource::new(name, value).with_span(Some(span)));
             }
         }
 
         Ok(resources)
     }
 
-    fn parse_from_file(&self, filename: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
-        let text = load_string_from_file(filename)?;
-        self.parse_from_str(text.as_ref())
-    }
-
-    fn write_to_str(&self, resources: &[Resource]) -> String {
+    pub(crate) fn write_to_str(resources: &[Resource]) -> String {
         let estimated_len = resources
             .iter()
             .map(|resource| resource.name.len() + resource.value.len() + 3)
@@ -85,15 +87,31 @@ impl FileFormat for RestextFileFormat {
     }
 }
 
+impl FileFormat for RestextFileFormat {
+    fn name(&self) -> &str {
+        Self::EXTENSION
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[Self::EXTENSION]
+    }
+
+    fn parse_from_bytes(&self, bytes: &[u8]) -> Result<Vec<Resource>, Box<dyn Error>> {
+        Self::parse_from_str(decode_utf8_text(bytes)?)
+    }
+
+    fn write_to_bytes(&self, resources: &[Resource], output_encoding: OutputEncoding) -> Result<Vec<u8>, io::Error> {
+        Ok(encode_utf8_owned(Self::write_to_str(resources), output_encoding))
+    }
+}
+
 #[test]
 fn test_restext_parse() {
     let text = "lblBoat=I'm on a boat.\r\n\
                 lblYolo=You only live once\r\n\
                 lblDogs=Who let the dogs out?\r\n";
 
-    let file_format = RestextFileFormat {};
-
-    let resources = match file_format.parse_from_str(text) {
+    let resources = match RestextFileFormat::parse_from_str(text) {
         Ok(resources) => resources,
         Err(e) => panic!("restext parse failed: {}", e),
     };
@@ -115,9 +133,7 @@ fn test_restext_parse() {
 fn test_restext_parse_with_utf8_bom() {
     let text = "\u{feff}lblBoat=I'm on a boat.\r\n";
 
-    let file_format = RestextFileFormat {};
-
-    let resources = match file_format.parse_from_str(text) {
+    let resources = match RestextFileFormat::parse_from_str(text) {
         Ok(resources) => resources,
         Err(e) => panic!("restext parse with bom failed: {}", e),
     };
@@ -128,9 +144,20 @@ fn test_restext_parse_with_utf8_bom() {
 }
 
 #[test]
-fn test_restext_write() {
-    let file_format = RestextFileFormat {};
+fn test_restext_parse_spans() {
+    let text = "lblBoat=I'm on a boat.\r\n\r\n  lblYolo=You only live once\r\n";
+
+    let resources = RestextFileFormat::parse_from_str(text).expect("restext parse");
 
+    let span = resources[0].span.as_ref().expect("lblBoat span");
+    assert_eq!((span.line, span.column, span.end_line, span.end_column), (1, 1, 1, 23));
+
+    let span = resources[1].span.as_ref().expect("lblYolo span");
+    assert_eq!((span.line, span.column, span.end_line, span.end_column), (3, 3, 3, 29));
+}
+
+#[test]
+fn test_restext_write() {
     let resources = vec![
         Resource::new("lblBoat", "I'm on a boat."),
         Resource::new("lblYolo", "You only live once"),
@@ -141,7 +168,7 @@ fn test_restext_write() {
                          lblYolo=You only live once\r\n\
                          lblDogs=Who let the dogs out?\r\n";
 
-    let actual_text = file_format.write_to_str(&resources);
+    let actual_text = RestextFileFormat::write_to_str(&resources);
     assert_eq!(actual_text, expected_text);
 }
 
@@ -156,7 +183,6 @@ fn test_escape_newlines() {
 #[ignore = "benchmark: run manually with --ignored --nocapture"]
 #[allow(clippy::print_stdout)]
 fn benchmark_restext_parse_and_write_large_input() {
-    let file_format = RestextFileFormat {};
     let base = include_str!("../test/test.restext");
     let repetitions = 20_000usize;
     let mut text = String::with_capacity(base.len() * repetitions);
@@ -166,13 +192,12 @@ fn benchmark_restext_parse_and_write_large_input() {
     }
 
     let started = Instant::now();
-    let resources = file_format
-        .parse_from_str(&text)
-        .unwrap_or_else(|e| panic!("restext parse benchmark failed: {}", e));
+    let resources =
+        RestextFileFormat::parse_from_str(&text).unwrap_or_else(|e| panic!("restext parse benchmark failed: {}", e));
     let parse_elapsed = started.elapsed();
 
     let started = Instant::now();
-    let written = file_format.write_to_str(&resources);
+    let written = RestextFileFormat::write_to_str(&resources);
     let write_elapsed = started.elapsed();
 
     assert_eq!(resources.len(), 3 * repetitions);
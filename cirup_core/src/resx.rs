@@ -1,14 +1,14 @@
-extern crate treexml;
-use treexml::Document;
+use xml::common::{Position, TextPosition};
+use xml::reader::{EventReader, XmlEvent};
 
-use crate::Resource;
-use crate::file::FileFormat;
-use crate::file::load_string_from_file;
+use crate::file::{FileFormat, OutputEncoding, ParseError, decode_utf8_text, encode_utf8_owned};
+use crate::{Resource, SourceSpan};
 use std::error::Error;
+use std::io;
 
 pub(crate) struct ResxFileFormat {}
 
-fn without_bom(text: &str) -> &[u8] {
+pub(crate) fn without_bom(text: &str) -> &[u8] {
     if text.starts_with("\u{feff}") {
         return &text.as_bytes()[3..];
     }
@@ -16,7 +16,7 @@ fn without_bom(text: &str) -> &[u8] {
     text.as_bytes()
 }
 
-fn push_escaped_xml_text(output: &mut String, value: &str) {
+pub(crate) fn push_escaped_xml_text(output: &mut String, value: &str) {
     for ch in value.chars() {
         match ch {
             '&' => output.push_str("&amp;"),
@@ -27,7 +27,7 @@ fn push_escaped_xml_text(output: &mut String, value: &str) {
     }
 }
 
-fn push_escaped_xml_attr(output: &mut String, value: &str) {
+pub(crate) fn push_escaped_xml_attr(output: &mut String, value: &str) {
     for ch in value.chars() {
         match ch {
             '&' => output.push_str("&amp;"),
@@ -40,37 +40,117 @@ fn push_escaped_xml_attr(output: &mut String, value: &str) {
     }
 }
 
-impl FileFormat for ResxFileFormat {
-    const EXTENSION: &'static str = "resx";
+fn xml_parse_error(error: &xml::reader::Error) -> ParseError {
+    let position = error.position();
+    ParseError::new(position_line(position), position_column(position), error.msg())
+}
+
+fn position_line(position: TextPosition) -> usize {
+    usize::try_from(position.row).unwrap_or(usize::MAX - 1) + 1
+}
+
+fn position_column(position: TextPosition) -> usize {
+    usize::try_from(position.column).unwrap_or(usize::MAX - 1) + 1
+}
 
-    fn parse_from_str(&self, text: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
+/// A `data` element of the root element, read up to its end tag.
+struct ResxEntry {
+    name: Option<String>,
+    value: Option<String>,
+    in_value: bool,
+    line: usize,
+    column: usize,
+}
+
+impl ResxFileFormat {
+    pub(crate) const EXTENSION: &'static str = "resx";
+
+    pub(crate) fn parse_from_str(text: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
         let mut resources: Vec<Resource> = Vec::new();
         let bytes = without_bom(text);
 
-        if !bytes.is_empty() {
-            let doc = Document::parse(bytes).map_err(|e| format!("resx parse error: {:?}", e))?;
-            let root = doc.root.ok_or("resx root not found")?;
-
-            for data in root.filter_children(|t| t.name == "data") {
-                if let Some(data_name) = data.attributes.get("name")
-                    && let Some(value) = data.find_child(|tag| tag.name == "value")
-                {
-                    let data_value = value.text.as_deref().unwrap_or_default();
-                    let resource = Resource::new(data_name, data_value);
-                    resources.push(resource);
+        if bytes.is_empty() {
+            return Ok(resources);
+        }
+
+        // a streaming reader, rather than a document, keeps the position of each entry
+        let mut reader = EventReader::new(bytes);
+        let mut depth = 0;
+        let mut entry: Option<ResxEntry> = None;
+
+        loop {
+            match reader.next().map_err(|e| xml_parse_error(&e))? {
+                XmlEvent::StartElement { name, attributes, .. } => {
+                    depth += 1;
+                    if depth == 2 && name.local_name == "data" {
+                        let position = reader.position();
+                        entry = Some(ResxEntry {
+                            name: attributes
+                                .into_iter()
+                                .find(|attribute| {
+                                    attribute.name.prefix.is_none() && attribute.name.local_name == "name"
+                                })
+                                .map(|attribute| attribute.value),
+                            value: None,
+                            in_value: false,
+                            line: position_line(position),
+                            column: position_column(position),
+                        });
+                    } else if depth == 3
+                        && name.local_name == "value"
+                        && let Some(entry) = entry.as_mut().filter(|entry| entry.value.is_none())
+                    {
+                        entry.value = Some(String::new());
+                        entry.in_value = true;
+                    }
+                }
+                XmlEvent::Characters(text) => {
+                    if depth == 3
+                        && let Some(value) = entry
+                            .as_mut()
+                            .filter(|entry| entry.in_value)
+                            .and_then(|entry| entry.value.as_mut())
+                    {
+                        value.push_str(&text);
+                    }
                 }
+                XmlEvent::EndElement { name } => {
+                    if depth == 3
+                        && let Some(entry) = entry.as_mut()
+                    {
+                        entry.in_value = false;
+                    }
+
+                    if depth == 2
+                        && let Some(ResxEntry {
+                            name: Some(data_name),
+                            value: Some(data_value),
+                            line,
+                            column,
+                            ..
+                        }) = entry.take()
+                    {
+                        let position = reader.position();
+                        let span = SourceSpan {
+                            file: String::new(),
+                            line,
+                            column,
+                            end_line: position_line(position),
+                            end_column: position_column(position) + name.to_string().chars().count() + 3,
+                        };
+                        resources.push(Resource::from_owned(data_name, data_value).with_span(Some(span)));
+                    }
+                    depth -= 1;
+                }
+                XmlEvent::EndDocument => break,
+                _ => {}
             }
         }
 
         Ok(resources)
     }
 
-    fn parse_from_file(&self, filename: &str) -> Result<Vec<Resource>, Box<dyn Error>> {
-        let text = load_string_from_file(filename)?;
-        self.parse_from_str(text.as_ref())
-    }
-
-    fn write_to_str(&self, resources: &[Resource]) -> String {
+    pub(crate) fn write_to_str(resources: &[Resource]) -> String {
         let estimated_body_len = resources
             .iter()
             .map(|resource| resource.name.len() + resource.value.len() + 64)
@@ -91,6 +171,24 @@ impl FileFormat for ResxFileFormat {
     }
 }
 
+impl FileFormat for ResxFileFormat {
+    fn name(&self) -> &str {
+        Self::EXTENSION
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[Self::EXTENSION]
+    }
+
+    fn parse_from_bytes(&self, bytes: &[u8]) -> Result<Vec<Resource>, Box<dyn Error>> {
+        Self::parse_from_str(decode_utf8_text(bytes)?)
+    }
+
+    fn write_to_bytes(&self, resources: &[Resource], output_encoding: OutputEncoding) -> Result<Vec<u8>, io::Error> {
+        Ok(encode_utf8_owned(Self::write_to_str(resources), output_encoding))
+    }
+}
+
 #[test]
 fn test_resx_parse() {
     let text = r#"
@@ -108,9 +206,7 @@ fn test_resx_parse() {
 </root>
 "#;
 
-    let file_format = ResxFileFormat {};
-
-    let resources = match file_format.parse_from_str(text) {
+    let resources = match ResxFileFormat::parse_from_str(text) {
         Ok(resources) => resources,
         Err(e) => panic!("resx parse failed: {}", e),
     };
@@ -129,9 +225,27 @@ fn test_resx_parse() {
 }
 
 #[test]
-fn test_resx_write() {
-    let file_format = ResxFileFormat {};
+fn test_resx_parse_spans() {
+    let text = r#"<?xml version="1.0" encoding="utf-8"?>
+<root>
+  <data name="lblBoat" xml:space="preserve">
+    <value>I'm on a boat.</value>
+  </data>
+  <data name="lblYolo"><value>You only live once</value></data>
+</root>
+"#;
+
+    let resources = ResxFileFormat::parse_from_str(text).expect("resx parse");
 
+    let span = resources[0].span.as_ref().expect("lblBoat span");
+    assert_eq!((span.line, span.column, span.end_line, span.end_column), (3, 3, 5, 10));
+
+    let span = resources[1].span.as_ref().expect("lblYolo span");
+    assert_eq!((span.line, span.column, span.end_line, span.end_column), (6, 3, 6, 64));
+}
+
+#[test]
+fn test_resx_write() {
     let resources = vec![
         Resource::new("lblBoat", "I'm on a boat."),
         Resource::new("lblYolo", "You only live once"),
@@ -151,7 +265,7 @@ fn test_resx_write() {
   </data>
 </root>"#;
 
-    let actual_text = file_format.write_to_str(&resources);
+    let actual_text = ResxFileFormat::write_to_str(&resources);
     //println!("{}", actual_text);
     //println!("{}", expected_text);
     assert_eq!(actual_text, expected_text);
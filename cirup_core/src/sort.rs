@@ -0,0 +1,248 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io;
+
+use crate::Resource;
+use crate::file::{OutputEncoding, load_resources, output_bytes_for_file};
+use crate::query::{QueryResult, QueryRunOptions, RecordOutput, evaluate_records};
+
+/*
+ * Key orders of file-sort beyond the binary order of the sort query:
+ * culture files aligned on the key order of their base file, and the orders of other tools.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /// Binary order of the key names, as the sort query.
+    #[default]
+    Key,
+    /// Order of the lowercase key names, so `_` sorts before letters.
+    CaseInsensitive,
+    /// Case-insensitive order where digit runs compare by number, so `lbl2` sorts before `lbl10`.
+    Natural,
+    /// Order of the uppercase UTF-16 key names, as .NET `StringComparer.OrdinalIgnoreCase`
+    /// and the Visual Studio resource editor, so `_` sorts after letters.
+    OrdinalIgnoreCase,
+}
+
+impl SortOrder {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortOrder::Key => "key",
+            SortOrder::CaseInsensitive => "case-insensitive",
+            SortOrder::Natural => "natural",
+            SortOrder::OrdinalIgnoreCase => "ordinal-ignore-case",
+        }
+    }
+
+    /// Compares key names, falling back to their binary order so that the order is total.
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        let ordering = match self {
+            SortOrder::Key => Ordering::Equal,
+            SortOrder::CaseInsensitive => compare_lowercase(a, b),
+            SortOrder::Natural => compare_natural(a, b),
+            SortOrder::OrdinalIgnoreCase => compare_ordinal_ignore_case(a, b),
+        };
+
+        ordering.then_with(|| a.cmp(b))
+    }
+}
+
+fn compare_lowercase(a: &str, b: &str) -> Ordering {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .cmp(b.chars().flat_map(char::to_lowercase))
+}
+
+/// Simple uppercase mapping: characters that uppercase to several characters are kept.
+fn to_upper_simple(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(upper), None) => upper,
+        _ => c,
+    }
+}
+
+fn compare_ordinal_ignore_case(a: &str, b: &str) -> Ordering {
+    let units = |text: &str| {
+        text.chars()
+            .map(to_upper_simple)
+            .collect::<String>()
+            .encode_utf16()
+            .collect::<Vec<_>>()
+    };
+
+    units(a).cmp(&units(b))
+}
+
+/// Splits a key name into runs of ASCII digits and runs of other characters.
+fn natural_chunks(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let digits = first.is_ascii_digit();
+        let end = rest.find(|c: char| c.is_ascii_digit() != digits).unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+fn compare_natural(a: &str, b: &str) -> Ordering {
+    let mut a_chunks = natural_chunks(a);
+    let mut b_chunks = natural_chunks(b);
+
+    loop {
+        let (a_chunk, b_chunk) = match (a_chunks.next(), b_chunks.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_chunk), Some(b_chunk)) => (a_chunk, b_chunk),
+        };
+
+        let is_number = |chunk: &str| chunk.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = if is_number(a_chunk) && is_number(b_chunk) {
+            // numbers of any length: fewer significant digits is smaller
+            let a_number = a_chunk.trim_start_matches('0');
+            let b_number = b_chunk.trim_start_matches('0');
+            a_number
+                .len()
+                .cmp(&b_number.len())
+                .then_with(|| a_number.cmp(b_number))
+                .then_with(|| a_chunk.len().cmp(&b_chunk.len()))
+        } else {
+            compare_lowercase(a_chunk, b_chunk)
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Sorts resources by key name in `order`.
+pub fn sort_resources(resources: &mut [Resource], order: SortOrder) {
+    resources.sort_by(|a, b| order.compare(&a.name, &b.name));
+}
+
+/// Orders resources by the key order of `base`, with the keys that are not in `base` at the end in their order.
+pub fn align_resources(resources: &mut [Resource], base: &[Resource]) {
+    let mut positions = HashMap::with_capacity(base.len());
+    for (position, resource) in base.iter().enumerate() {
+        positions.entry(resource.name.as_str()).or_insert(position);
+    }
+
+    resources.sort_by_key(|resource| positions.get(resource.name.as_str()).copied().unwrap_or(usize::MAX));
+}
+
+pub struct FileSort {
+    file: String,
+    order: SortOrder,
+    base: Option<String>,
+}
+
+/// Sorts `file` by key name in `order`.
+pub fn sort_file(file: &str, order: SortOrder) -> FileSort {
+    FileSort {
+        file: file.to_owned(),
+        order,
+        base: None,
+    }
+}
+
+/// Orders `file` by the key order of `base`, e.g. `Strings.fr.resx` by `Strings.resx`.
+pub fn align_file(file: &str, base: &str) -> FileSort {
+    FileSort {
+        file: file.to_owned(),
+        order: SortOrder::Key,
+        base: Some(base.to_owned()),
+    }
+}
+
+impl FileSort {
+    pub fn run(&self) -> Result<Vec<Resource>, io::Error> {
+        let mut resources = load_resources(&self.file)?;
+
+        match &self.base {
+            Some(base) => align_resources(&mut resources, &load_resources(base)?),
+            None => sort_resources(&mut resources, self.order),
+        }
+
+        Ok(resources)
+    }
+
+    /// Returns the sorted resources and the file to write to `out_file`, or over the sorted file.
+    pub fn evaluate_with_options(
+        &self,
+        out_file: Option<&str>,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<Resource>, io::Error> {
+        let out_file = out_file.unwrap_or(self.file.as_str());
+        output_bytes_for_file(out_file, &[], output_encoding)?;
+
+        let resources = self.run()?;
+        let output = RecordOutput {
+            path: out_file,
+            render: Box::new(move |resources: &[Resource]| output_bytes_for_file(out_file, resources, output_encoding)),
+            print_records: false,
+        };
+
+        evaluate_records(resources, "resource", Some(output), touch, options)
+    }
+}
+
+#[test]
+fn test_sort_orders() {
+    let keys = ["lbl10", "Lbl_Two", "lbl2", "lblOne", "lbl_one", "LBL1"];
+    let sorted = |order: SortOrder| {
+        let mut resources = keys.iter().map(|key| Resource::new(key, "")).collect::<Vec<_>>();
+        sort_resources(&mut resources, order);
+        resources.into_iter().map(|resource| resource.name).collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        sorted(SortOrder::Key),
+        vec!["LBL1", "Lbl_Two", "lbl10", "lbl2", "lblOne", "lbl_one"]
+    );
+    assert_eq!(
+        sorted(SortOrder::CaseInsensitive),
+        vec!["LBL1", "lbl10", "lbl2", "lbl_one", "Lbl_Two", "lblOne"]
+    );
+    assert_eq!(
+        sorted(SortOrder::Natural),
+        vec!["LBL1", "lbl2", "lbl10", "lbl_one", "Lbl_Two", "lblOne"]
+    );
+    assert_eq!(
+        sorted(SortOrder::OrdinalIgnoreCase),
+        vec!["LBL1", "lbl10", "lbl2", "lblOne", "lbl_one", "Lbl_Two"]
+    );
+}
+
+#[test]
+fn test_align_resources_follows_base_order() {
+    let base = vec![
+        Resource::new("lblOne", "One"),
+        Resource::new("lblTwo", "Two"),
+        Resource::new("lblThree", "Three"),
+    ];
+    let mut resources = vec![
+        Resource::new("lblExtra", "Extra"),
+        Resource::new("lblThree", "Trois"),
+        Resource::new("lblOther", "Autre"),
+        Resource::new("lblOne", "Un"),
+    ];
+
+    align_resources(&mut resources, &base);
+
+    assert_eq!(
+        resources,
+        vec![
+            Resource::new("lblOne", "Un"),
+            Resource::new("lblThree", "Trois"),
+            Resource::new("lblExtra", "Extra"),
+            Resource::new("lblOther", "Autre"),
+        ]
+    );
+}
@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::Triple;
+use crate::file::{load_error, load_resource_file};
+use crate::query::{
+    CirupEngine, QueryRecord, QueryResult, QueryRunOptions, RecordOutput, TRANSLATED_QUERY, evaluate_records,
+};
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * Source fingerprints are stored in a JSON lock file next to the translation file,
+ * mapping each translated key to the SHA-256 of the base value it was translated from.
+ */
+
+const LOCK_FILE_VERSION: u32 = 1;
+const LOCK_FILE_SUFFIX: &str = ".cirup.lock";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FingerprintLock {
+    pub version: u32,
+    pub algorithm: String,
+    pub entries: BTreeMap<String, String>,
+}
+
+impl Default for FingerprintLock {
+    fn default() -> Self {
+        Self {
+            version: LOCK_FILE_VERSION,
+            algorithm: String::from("sha256"),
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+impl FingerprintLock {
+    pub fn load(filename: &str) -> Result<Self, io::Error> {
+        let text = match fs::read_to_string(filename) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+
+        let lock: Self = serde_json::from_str(&text)
+            .map_err(|e| io::Error::other(format!("invalid lock file '{}': {}", filename, e)))?;
+
+        if lock.version != LOCK_FILE_VERSION || lock.algorithm != "sha256" {
+            return Err(io::Error::other(format!(
+                "unsupported lock file '{}': expected version {} with sha256 fingerprints",
+                filename, LOCK_FILE_VERSION
+            )));
+        }
+
+        Ok(lock)
+    }
+
+    fn to_json(&self) -> String {
+        let mut text = serde_json::to_string_pretty(self).expect("failed to serialize lock file");
+        text.push('\n');
+        text
+    }
+}
+
+/// Returns the default lock file path for a translation file, e.g. `Strings.fr.resx.cirup.lock`.
+pub fn default_lock_path(target: &str) -> String {
+    format!("{target}{LOCK_FILE_SUFFIX}")
+}
+
+/// Returns the hex-encoded SHA-256 of a source value.
+pub fn source_fingerprint(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    let mut output = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        let _ = write!(output, "{byte:02x}");
+    }
+    output
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceFingerprint {
+    pub name: String,
+    pub fingerprint: String,
+}
+
+impl QueryRecord for SourceFingerprint {
+    const COLUMNS: &'static [&'static str] = &["name", "fingerprint"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.name.clone(), self.fingerprint.clone()]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.name
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.fingerprint
+    }
+}
+
+pub struct StaleQuery {
+    base: String,
+    target: String,
+    lock_file: String,
+}
+
+/// Compares the values of `base` with the fingerprints recorded for the translations in `target`.
+pub fn query_stale(base: &str, target: &str, lock_file: Option<&str>) -> StaleQuery {
+    StaleQuery {
+        base: base.to_owned(),
+        target: target.to_owned(),
+        lock_file: lock_file.map_or_else(|| default_lock_path(target), str::to_owned),
+    }
+}
+
+impl StaleQuery {
+    /// Translated keys as (key, translation, current base value).
+    /// Fails if either file cannot be loaded, rather than comparing against an empty table.
+    fn translated(&self) -> Result<Vec<Triple>, io::Error> {
+        let mut engine = CirupEngine::new();
+        for (table, file) in [("A", &self.base), ("B", &self.target)] {
+            let resources = load_resource_file(file).map_err(|e| load_error(file, e))?;
+            engine.register_table_from_resources(table, resources);
+        }
+        Ok(engine.query_triple(TRANSLATED_QUERY))
+    }
+
+    /// Returns the translations whose base value changed since it was fingerprinted.
+    /// Keys without a recorded fingerprint are not reported.
+    pub fn run(&self) -> Result<Vec<Triple>, io::Error> {
+        let lock = FingerprintLock::load(&self.lock_file)?;
+
+        Ok(self
+            .translated()?
+            .into_iter()
+            .filter(|triple| {
+                lock.entries
+                    .get(&triple.name)
+                    .is_some_and(|fingerprint| *fingerprint != source_fingerprint(&triple.base))
+            })
+            .collect())
+    }
+
+    pub fn evaluate_with_options(&self, options: &QueryRunOptions) -> Result<QueryResult<Triple>, io::Error> {
+        evaluate_records(self.run()?, "triple", None, false, options)
+    }
+
+    /// Records the fingerprints of the current base values of the translated keys.
+    /// With key or value filters, only the matching keys are updated and other entries are kept.
+    pub fn stamp_with_options(
+        &self,
+        touch: bool,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<SourceFingerprint>, io::Error> {
+        let mut lock = FingerprintLock::load(&self.lock_file)?;
+        let translated = self.translated()?;
+        lock.entries
+            .retain(|name, _| translated.iter().any(|triple| triple.name == *name));
+
+        let fingerprints = translated
+            .iter()
+            .map(|triple| SourceFingerprint {
+                name: triple.name.clone(),
+                fingerprint: source_fingerprint(&triple.base),
+            })
+            .collect::<Vec<_>>();
+
+        let output = RecordOutput {
+            path: &self.lock_file,
+            render: Box::new(move |fingerprints: &[SourceFingerprint]| {
+                for fingerprint in fingerprints {
+                    lock.entries
+                        .insert(fingerprint.name.clone(), fingerprint.fingerprint.clone());
+                }
+                Ok(lock.to_json().into_bytes())
+            }),
+            print_records: false,
+        };
+
+        evaluate_records(fingerprints, "fingerprint", Some(output), touch, options)
+    }
+}
+
+#[test]
+fn test_source_fingerprint_is_sha256_hex() {
+    assert_eq!(
+        source_fingerprint("hello"),
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+}
+
+#[test]
+fn test_stale_reports_changed_source_values() {
+    let files = TempFiles::new();
+
+    fs::write(
+        files.path("Strings.restext"),
+        "lblBoat=I'm on a boat.\r\nlblYolo=You only live once\r\n",
+    )
+    .expect("failed to write base");
+    fs::write(
+        files.path("Strings.fr.restext"),
+        "lblBoat=Je suis sur un bateau\r\nlblYolo=Juste une vie a vivre\r\n",
+    )
+    .expect("failed to write target");
+
+    let query = query_stale(&files.path("Strings.restext"), &files.path("Strings.fr.restext"), None);
+    query
+        .stamp_with_options(
+            false,
+            &QueryRunOptions {
+                summary: true,
+                ..QueryRunOptions::default()
+            },
+        )
+        .expect("stamp failed")
+        .write_pending()
+        .expect("failed to write lock file");
+    assert!(query.run().expect("stale failed").is_empty());
+
+    fs::write(
+        files.path("Strings.restext"),
+        "lblBoat=I'm on a ship.\r\nlblYolo=You only live once\r\n",
+    )
+    .expect("failed to update base");
+
+    let query = query_stale(&files.path("Strings.restext"), &files.path("Strings.fr.restext"), None);
+    let stale = query.run().expect("stale failed");
+    assert_eq!(
+        stale,
+        vec![Triple::new("lblBoat", "Je suis sur un bateau", "I'm on a ship.")]
+    );
+
+    let lock = FingerprintLock::load(&default_lock_path(&files.path("Strings.fr.restext"))).expect("lock load failed");
+    assert_eq!(lock.entries.len(), 2);
+}
+
+#[test]
+fn test_stale_fails_on_missing_files_and_keeps_the_lock() {
+    let files = TempFiles::new();
+    let base = files.path("Strings.restext");
+    let target = files.path("Strings.fr.restext");
+    fs::write(&base, "lblBoat=I'm on a boat.\r\n").expect("failed to write base");
+    fs::write(&target, "lblBoat=Je suis sur un bateau\r\n").expect("failed to write target");
+
+    query_stale(&base, &target, None)
+        .stamp_with_options(false, &QueryRunOptions::default())
+        .expect("stamp failed")
+        .write_pending()
+        .expect("write failed");
+    let lock_path = default_lock_path(&target);
+    let lock = fs::read_to_string(&lock_path).expect("failed to read lock");
+
+    let missing = files.path("Missing.restext");
+    for (base, target) in [(&missing, &target), (&base, &missing)] {
+        let query = query_stale(base, target, Some(&lock_path));
+        assert!(query.run().is_err());
+        assert!(query.stamp_with_options(false, &QueryRunOptions::default()).is_err());
+    }
+    assert_eq!(fs::read_to_string(&lock_path).expect("failed to read lock"), lock);
+}
@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+
+use serde::Serialize;
+
+use crate::handoff::handoff_export;
+use crate::placeholder::placeholder_len_at;
+use crate::query::{QueryRecord, QueryResult, QueryRunOptions, evaluate_records};
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * Source text statistics of the keys that need translation, for translation cost estimates:
+ * words and characters of the translatable text, without placeholders and markup,
+ * and the segments that repeat an earlier segment of the same file.
+ */
+
+/// Counts of the source text of a set of keys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TextCounts {
+    pub segments: usize,
+    pub words: usize,
+    /// Characters of the translatable text, without whitespace.
+    pub characters: usize,
+    pub placeholders: usize,
+    /// Segments whose text repeats an earlier segment of the same file.
+    pub repeated_segments: usize,
+    pub repeated_words: usize,
+}
+
+impl TextCounts {
+    fn add(&mut self, other: &TextCounts) {
+        self.segments += other.segments;
+        self.words += other.words;
+        self.characters += other.characters;
+        self.placeholders += other.placeholders;
+        self.repeated_segments += other.repeated_segments;
+        self.repeated_words += other.repeated_words;
+    }
+}
+
+/// Returns the translatable text of a value, with its placeholders and markup tags replaced by spaces,
+/// and the number of placeholders.
+pub fn translatable_text(value: &str) -> (String, usize) {
+    let mut text = String::with_capacity(value.len());
+    let mut placeholders = 0;
+    let mut index = 0;
+
+    while let Some(c) = value[index..].chars().next() {
+        let rest = &value[index..];
+        let skipped = match c {
+            '{' | '%' => placeholder_len_at(rest).inspect(|_| placeholders += 1),
+            // markup tags such as <b> and </a>, and entities such as &amp;
+            '<' if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/') => {
+                rest.find('>').map(|end| end + 1)
+            }
+            '&' => rest
+                .find(';')
+                .filter(|end| *end > 1 && rest[1..*end].chars().all(|c| c.is_ascii_alphanumeric() || c == '#'))
+                .map(|end| end + 1),
+            _ => None,
+        };
+
+        match skipped {
+            Some(len) => {
+                text.push(' ');
+                index += len;
+            }
+            None => {
+                text.push(c);
+                index += c.len_utf8();
+            }
+        }
+    }
+
+    (text, placeholders)
+}
+
+/// Counts the words of a text: runs of non-whitespace characters with at least one letter or digit.
+pub fn count_words(text: &str) -> usize {
+    text.split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+/// Returns the counts of a single segment.
+pub fn segment_counts(value: &str) -> TextCounts {
+    let (text, placeholders) = translatable_text(value);
+    TextCounts {
+        segments: 1,
+        words: count_words(&text),
+        characters: text.chars().filter(|c| !c.is_whitespace()).count(),
+        placeholders,
+        repeated_segments: 0,
+        repeated_words: 0,
+    }
+}
+
+/// The prefix of a key: the part before its first `.` or `_`, or for camel case keys such as `lblTitle`,
+/// before the first uppercase letter.
+pub fn key_prefix(key: &str) -> &str {
+    if let Some(end) = key.find(['.', '_']) {
+        return &key[..end];
+    }
+
+    key.char_indices()
+        .skip(1)
+        .find(|(_, c)| c.is_uppercase())
+        .filter(|_| key.starts_with(char::is_lowercase))
+        .map_or(key, |(end, _)| &key[..end])
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatsRow {
+    /// The file the keys need translation in.
+    pub file: String,
+    /// The key prefix, or none for the totals of the file.
+    pub prefix: Option<String>,
+    #[serde(flatten)]
+    pub counts: TextCounts,
+}
+
+impl QueryRecord for StatsRow {
+    const COLUMNS: &'static [&'static str] = &[
+        "file",
+        "prefix",
+        "segments",
+        "words",
+        "characters",
+        "placeholders",
+        "repeated_segments",
+        "repeated_words",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        let counts = &self.counts;
+        vec![
+            self.file.clone(),
+            self.prefix.clone().unwrap_or_default(),
+            counts.segments.to_string(),
+            counts.words.to_string(),
+            counts.characters.to_string(),
+            counts.placeholders.to_string(),
+            counts.repeated_segments.to_string(),
+            counts.repeated_words.to_string(),
+        ]
+    }
+
+    fn filter_key(&self) -> &str {
+        self.prefix.as_deref().unwrap_or_default()
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.file
+    }
+}
+
+pub struct TranslationStats {
+    base: String,
+    targets: Vec<String>,
+    since: Option<String>,
+}
+
+/// Statistics of the keys of `base` that need translation in each of `targets`: keys missing from the target,
+/// and with `since`, keys whose value in `base` changed since that version.
+pub fn translation_stats(base: &str, targets: &[&str], since: Option<&str>) -> TranslationStats {
+    TranslationStats {
+        base: base.to_owned(),
+        targets: targets.iter().map(|target| (*target).to_owned()).collect(),
+        since: since.map(str::to_owned),
+    }
+}
+
+impl TranslationStats {
+    /// Returns the rows of each target, failing when an input file cannot be loaded rather than counting no keys.
+    pub fn run(&self) -> Result<Vec<StatsRow>, io::Error> {
+        let mut rows = Vec::new();
+
+        for target in &self.targets {
+            let units = handoff_export(&self.base, target, self.since.as_deref()).run()?;
+            let mut total = TextCounts::default();
+            let mut prefixes = BTreeMap::<&str, TextCounts>::new();
+            let mut seen = HashSet::new();
+
+            for unit in &units {
+                let mut counts = segment_counts(&unit.source);
+                let segment = unit.source.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !seen.insert(segment) {
+                    counts.repeated_segments = 1;
+                    counts.repeated_words = counts.words;
+                }
+
+                total.add(&counts);
+                prefixes.entry(key_prefix(&unit.name)).or_default().add(&counts);
+            }
+
+            rows.push(StatsRow {
+                file: target.clone(),
+                prefix: None,
+                counts: total,
+            });
+            rows.extend(prefixes.into_iter().map(|(prefix, counts)| StatsRow {
+                file: target.clone(),
+                prefix: Some(prefix.to_owned()),
+                counts,
+            }));
+        }
+
+        Ok(rows)
+    }
+
+    pub fn evaluate_with_options(&self, options: &QueryRunOptions) -> Result<QueryResult<StatsRow>, io::Error> {
+        let rows = self.run()?;
+        info!("stats: {} row(s) for {} file(s)", rows.len(), self.targets.len());
+        evaluate_records(rows, "stats", None, false, options)
+    }
+}
+
+#[test]
+fn test_segment_counts_skip_placeholders_and_markup() {
+    let counts = segment_counts("Hello <b>{0}</b>, you have %d new messages &amp; alerts!");
+    assert_eq!(counts.words, 6);
+    assert_eq!(counts.placeholders, 2);
+    assert_eq!(counts.characters, "Hello,youhavenewmessagesalerts!".chars().count());
+
+    assert_eq!(segment_counts("100% sure - a < b").words, 4);
+    assert_eq!(segment_counts("").words, 0);
+}
+
+#[test]
+fn test_key_prefix() {
+    assert_eq!(key_prefix("lblTitle"), "lbl");
+    assert_eq!(key_prefix("menu.file.open"), "menu");
+    assert_eq!(key_prefix("Error_NotFound"), "Error");
+    assert_eq!(key_prefix("Title"), "Title");
+    assert_eq!(key_prefix("title"), "title");
+}
+
+#[test]
+fn test_translation_stats_by_file_and_prefix() {
+    let files = TempFiles::new();
+    std::fs::write(
+        files.path("Strings.restext"),
+        "lblOpen=Open the file\r\nlblSave=Save {0} files\r\nmsgSaved=Open the file\r\nmsgDone=Done\r\n",
+    )
+    .expect("failed to write base");
+    std::fs::write(files.path("Strings.fr.restext"), "msgDone=Terminé\r\n").expect("failed to write target");
+
+    let rows = translation_stats(
+        &files.path("Strings.restext"),
+        &[files.path("Strings.fr.restext").as_str()],
+        None,
+    )
+    .run()
+    .expect("stats failed");
+    let summary = rows
+        .iter()
+        .map(|row| {
+            (
+                row.prefix.as_deref(),
+                row.counts.segments,
+                row.counts.words,
+                row.counts.repeated_words,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![(None, 3, 8, 3), (Some("lbl"), 2, 5, 0), (Some("msg"), 1, 3, 3)]
+    );
+    assert_eq!(rows[0].counts.placeholders, 1);
+}
+
+#[test]
+fn test_translation_stats_fail_on_load_errors() {
+    let files = TempFiles::new();
+    std::fs::write(files.path("Strings.restext"), "lblOpen=Open the file\r\n").expect("failed to write base");
+
+    let error = translation_stats(
+        &files.path("Nope.restext"),
+        &[files.path("Strings.fr.restext").as_str()],
+        None,
+    )
+    .evaluate_with_options(&QueryRunOptions::default())
+    .expect_err("missing base");
+    assert!(error.to_string().contains("Nope.restext"), "{}", error);
+
+    let error = translation_stats(
+        &files.path("Strings.restext"),
+        &[files.path("Strings.fr.restext").as_str()],
+        Some(&files.path("Strings.old.restext")),
+    )
+    .run()
+    .expect_err("missing previous base");
+    assert!(error.to_string().contains("Strings.old.restext"), "{}", error);
+}
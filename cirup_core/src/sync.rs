@@ -0,0 +1,306 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::Resource;
+use crate::file::{OutputEncoding, load_resources, output_bytes_for_file};
+use crate::query::{
+    CirupEngine, PULL_QUERY, PUSH_QUERY, QueryRecord, QueryResult, QueryRunOptions, RecordOutput, evaluate_records,
+};
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * Structural synchronization of culture files:
+ * pull brings a translation file into the key set and order of its base file,
+ * push applies the values changed in a working copy back into a master file.
+ */
+
+/// Placeholder of the keys added by a pull: an empty value, which coverage counts as missing
+/// and lint reports as an empty translation, rather than a copy of the base value that would pass for translated.
+pub const DEFAULT_PLACEHOLDER: &str = "";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncChangeKind {
+    /// The key was missing and was added with a placeholder.
+    Added,
+    /// The key is obsolete and was dropped.
+    Removed,
+    /// The value was replaced.
+    Updated,
+}
+
+impl SyncChangeKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            SyncChangeKind::Added => "added",
+            SyncChangeKind::Removed => "removed",
+            SyncChangeKind::Updated => "updated",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyncChange {
+    pub name: String,
+    pub kind: SyncChangeKind,
+    /// The new value, or the dropped value of removed keys.
+    pub value: String,
+}
+
+impl QueryRecord for SyncChange {
+    const COLUMNS: &'static [&'static str] = &["name", "kind", "value"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.name.clone(), self.kind.as_str().to_owned(), self.value.clone()]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.name
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.value
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncResult {
+    pub changes: Vec<SyncChange>,
+    /// The synchronized file content.
+    pub resources: Vec<Resource>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncDirection {
+    Pull,
+    Push,
+}
+
+pub struct SyncQuery {
+    direction: SyncDirection,
+    source: String,
+    target: String,
+    placeholder: String,
+}
+
+/// Brings `target` into the key set and order of `base`: missing keys are added with a placeholder
+/// and keys that are not in `base` are dropped. A missing `target` file is created.
+pub fn sync_pull(base: &str, target: &str) -> SyncQuery {
+    SyncQuery {
+        direction: SyncDirection::Pull,
+        source: base.to_owned(),
+        target: target.to_owned(),
+        placeholder: DEFAULT_PLACEHOLDER.to_owned(),
+    }
+}
+
+/// Applies the values of `working` that differ from `master` back into `master`.
+/// Keys that only exist in one of the files are left as they are.
+pub fn sync_push(working: &str, master: &str) -> SyncQuery {
+    SyncQuery {
+        direction: SyncDirection::Push,
+        source: working.to_owned(),
+        target: master.to_owned(),
+        placeholder: DEFAULT_PLACEHOLDER.to_owned(),
+    }
+}
+
+/// Expands the `{key}` and `{value}` tokens of a placeholder for a base resource.
+fn expand_placeholder(placeholder: &str, resource: &Resource) -> String {
+    placeholder
+        .replace("{key}", &resource.name)
+        .replace("{value}", &resource.value)
+}
+
+fn load_optional_resources(filename: &str) -> Result<Vec<Resource>, io::Error> {
+    if Path::new(filename).exists() {
+        load_resources(filename)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+impl SyncQuery {
+    /// Sets the value of the keys added by a pull, where `{key}` and `{value}` stand for the key and base value.
+    #[must_use]
+    pub fn with_placeholder(mut self, placeholder: &str) -> Self {
+        self.placeholder = placeholder.to_owned();
+        self
+    }
+
+    fn operation_name(&self) -> &'static str {
+        match self.direction {
+            SyncDirection::Pull => "file-pull",
+            SyncDirection::Push => "file-push",
+        }
+    }
+
+    pub fn run(&self) -> Result<SyncResult, io::Error> {
+        match self.direction {
+            SyncDirection::Pull => self.run_pull(),
+            SyncDirection::Push => self.run_push(),
+        }
+    }
+
+    fn run_pull(&self) -> Result<SyncResult, io::Error> {
+        let base = load_resources(&self.source)?;
+        let target = load_optional_resources(&self.target)?;
+
+        let base_keys = base
+            .iter()
+            .map(|resource| resource.name.as_str())
+            .collect::<HashSet<_>>();
+        let target_keys = target
+            .iter()
+            .map(|resource| resource.name.as_str())
+            .collect::<HashSet<_>>();
+
+        let mut changes = Vec::new();
+        let mut placeholders = Vec::with_capacity(base.len());
+        for resource in &base {
+            let value = expand_placeholder(&self.placeholder, resource);
+            if !target_keys.contains(resource.name.as_str()) {
+                changes.push(SyncChange {
+                    name: resource.name.clone(),
+                    kind: SyncChangeKind::Added,
+                    value: value.clone(),
+                });
+            }
+            placeholders.push(Resource::from_owned(resource.name.clone(), value));
+        }
+
+        changes.extend(
+            target
+                .iter()
+                .filter(|resource| !base_keys.contains(resource.name.as_str()))
+                .map(|resource| SyncChange {
+                    name: resource.name.clone(),
+                    kind: SyncChangeKind::Removed,
+                    value: resource.value.clone(),
+                }),
+        );
+
+        let mut engine = CirupEngine::new();
+        engine.register_table_from_resources("A", placeholders);
+        engine.register_table_from_resources("B", target);
+
+        Ok(SyncResult {
+            changes,
+            resources: engine.query_resource(PULL_QUERY),
+        })
+    }
+
+    fn run_push(&self) -> Result<SyncResult, io::Error> {
+        let mut engine = CirupEngine::new();
+        engine.register_table_from_resources("A", load_resources(&self.target)?);
+        engine.register_table_from_resources("B", load_resources(&self.source)?);
+
+        let changes = engine
+            .query_resource(PUSH_QUERY)
+            .into_iter()
+            .map(|resource| SyncChange {
+                name: resource.name,
+                kind: SyncChangeKind::Updated,
+                value: resource.value,
+            })
+            .collect();
+
+        // the master keys in their order, with the values of the working copy
+        Ok(SyncResult {
+            changes,
+            resources: engine.query_resource(PULL_QUERY),
+        })
+    }
+
+    /// Returns the changes and the synchronized file to write to `out_file`, or over the target file.
+    pub fn evaluate_with_options(
+        &self,
+        out_file: Option<&str>,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<SyncChange>, io::Error> {
+        let out_file = out_file.unwrap_or(self.target.as_str());
+        let result = self.run()?;
+        let bytes = output_bytes_for_file(out_file, &result.resources, output_encoding)?;
+        info!(
+            "{}: {} change(s) to {}",
+            self.operation_name(),
+            result.changes.len(),
+            self.target
+        );
+
+        let output = RecordOutput {
+            path: out_file,
+            render: Box::new(move |_: &[SyncChange]| Ok(bytes)),
+            print_records: true,
+        };
+
+        evaluate_records(result.changes, "sync-change", Some(output), touch, options)
+    }
+}
+
+#[test]
+fn test_sync_pull_follows_base_keys_and_order() {
+    let files = TempFiles::new();
+    let base = files.path("Strings.restext");
+    let target = files.path("Strings.fr.restext");
+    std::fs::write(&base, "lblOne=One\r\nlblTwo=Two\r\nlblThree=Three\r\n").expect("failed to write base");
+    std::fs::write(&target, "lblThree=Trois\r\nlblOld=Vieux\r\nlblOne=Un\r\n").expect("failed to write target");
+
+    let result = sync_pull(&base, &target)
+        .with_placeholder("TODO {key}: {value}")
+        .run()
+        .expect("pull failed");
+
+    assert_eq!(
+        result.resources,
+        vec![
+            Resource::new("lblOne", "Un"),
+            Resource::new("lblTwo", "TODO lblTwo: Two"),
+            Resource::new("lblThree", "Trois"),
+        ]
+    );
+    let changes = result
+        .changes
+        .iter()
+        .map(|change| (change.name.as_str(), change.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        vec![("lblTwo", SyncChangeKind::Added), ("lblOld", SyncChangeKind::Removed)]
+    );
+
+    let missing = files.path("Strings.de.restext");
+    let result = sync_pull(&base, &missing).run().expect("pull into a new file failed");
+    assert_eq!(result.resources[1], Resource::new("lblTwo", ""));
+    assert_eq!(result.changes.len(), 3);
+}
+
+#[test]
+fn test_sync_push_applies_changed_values() {
+    let files = TempFiles::new();
+    let working = files.path("working.restext");
+    let master = files.path("master.restext");
+    std::fs::write(&master, "lblOne=One\r\nlblTwo=Two\r\n").expect("failed to write master");
+    std::fs::write(&working, "lblTwo=Second\r\nlblOne=One\r\nlblNew=New\r\n").expect("failed to write working copy");
+
+    let result = sync_push(&working, &master).run().expect("push failed");
+
+    assert_eq!(
+        result.resources,
+        vec![Resource::new("lblOne", "One"), Resource::new("lblTwo", "Second")]
+    );
+    assert_eq!(
+        result.changes,
+        vec![SyncChange {
+            name: String::from("lblTwo"),
+            kind: SyncChangeKind::Updated,
+            value: String::from("Second"),
+        }]
+    );
+}
@@ -0,0 +1,25 @@
+use std::path::Path;
+
+use tempfile::TempDir;
+
+/// A temporary directory for the files of a test, removed when dropped, even when the test fails.
+pub(crate) struct TempFiles {
+    dir: TempDir,
+}
+
+impl TempFiles {
+    pub(crate) fn new() -> Self {
+        Self {
+            dir: tempfile::tempdir().expect("failed to create temp dir"),
+        }
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Returns the path of `name` in the directory.
+    pub(crate) fn path(&self, name: &str) -> String {
+        self.dir.path().join(name).to_string_lossy().into_owned()
+    }
+}
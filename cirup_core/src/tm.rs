@@ -0,0 +1,791 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use serde::Serialize;
+
+#[cfg(feature = "rusqlite-c")]
+use rusqlite::Connection;
+#[cfg(feature = "turso-rust")]
+use tokio::runtime::{Builder as RuntimeBuilder, Runtime};
+#[cfg(feature = "turso-rust")]
+use turso::{Builder as TursoBuilder, Connection as TursoConnection, Database as TursoDatabase};
+
+use crate::Resource;
+use crate::file::{OutputEncoding, culture_from_filename, load_error, load_resource_file, output_bytes_for_file};
+use crate::query::{
+    CirupEngine, DIFF_QUERY, MERGE_QUERY, QueryRecord, QueryResult, QueryRunOptions, RecordOutput, TRANSLATED_QUERY,
+    evaluate_records, evaluate_records_to_outputs, select_records,
+};
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * The translation memory is a SQLite database with a single table of
+ * (language, source, target, key) rows harvested from base/translation pairs.
+ * A filled file is written with a <file>.review list of the suggested keys that need review.
+ */
+
+const TM_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS tm (language TEXT, source TEXT, target TEXT, key TEXT);";
+const TM_SELECT_LANGUAGE: &str = "SELECT language, source, target, key FROM tm WHERE language = ?1";
+const TM_INSERT: &str = "INSERT INTO tm (language, source, target, key) VALUES (?1, ?2, ?3, ?4)";
+
+pub const DEFAULT_MIN_SCORE: u8 = 75;
+
+const REVIEW_LIST_SUFFIX: &str = ".review";
+
+/// Returns the path of the list of keys that need review written next to a filled file.
+fn review_list_path(out_file: &str) -> String {
+    format!("{}{}", out_file, REVIEW_LIST_SUFFIX)
+}
+
+fn review_list_bytes(suggestions: &[Suggestion]) -> Vec<u8> {
+    suggestions
+        .iter()
+        .filter(|suggestion| suggestion.needs_review)
+        .map(|suggestion| format!("{}\r\n", suggestion.name))
+        .collect::<String>()
+        .into_bytes()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TmEntry {
+    pub language: String,
+    pub source: String,
+    pub target: String,
+    pub key: String,
+}
+
+impl QueryRecord for TmEntry {
+    const COLUMNS: &'static [&'static str] = &["key", "language", "source", "target"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.key.clone(),
+            self.language.clone(),
+            self.source.clone(),
+            self.target.clone(),
+        ]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.key
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.target
+    }
+}
+
+trait TmStore {
+    fn entries(&self, language: &str) -> Result<Vec<TmEntry>, io::Error>;
+    fn insert(&self, entries: &[TmEntry]) -> Result<(), io::Error>;
+}
+
+#[cfg(feature = "turso-rust")]
+struct TursoTmStore {
+    runtime: Runtime,
+    _db: TursoDatabase,
+    conn: TursoConnection,
+}
+
+#[cfg(feature = "turso-rust")]
+impl TursoTmStore {
+    fn open(path: &str) -> Result<Self, io::Error> {
+        let runtime = RuntimeBuilder::new_current_thread().enable_all().build()?;
+        let db = runtime
+            .block_on(async { TursoBuilder::new_local(path).build().await })
+            .map_err(|e| io::Error::other(format!("failed to open translation memory '{}': {}", path, e)))?;
+        let conn = db.connect().map_err(io::Error::other)?;
+        runtime
+            .block_on(async { conn.execute_batch(TM_SCHEMA).await })
+            .map_err(io::Error::other)?;
+
+        Ok(Self { runtime, _db: db, conn })
+    }
+}
+
+#[cfg(feature = "turso-rust")]
+impl TmStore for TursoTmStore {
+    fn entries(&self, language: &str) -> Result<Vec<TmEntry>, io::Error> {
+        self.runtime
+            .block_on(async {
+                let mut statement = self.conn.prepare(TM_SELECT_LANGUAGE).await?;
+                let mut rows = statement.query((language.to_owned(),)).await?;
+                let mut entries = Vec::new();
+
+                while let Some(row) = rows.next().await? {
+                    entries.push(TmEntry {
+                        language: row.get(0)?,
+                        source: row.get(1)?,
+                        target: row.get(2)?,
+                        key: row.get(3)?,
+                    });
+                }
+
+                Ok::<Vec<TmEntry>, turso::Error>(entries)
+            })
+            .map_err(io::Error::other)
+    }
+
+    fn insert(&self, entries: &[TmEntry]) -> Result<(), io::Error> {
+        self.runtime
+            .block_on(async {
+                self.conn.execute("BEGIN", ()).await?;
+                let mut statement = self.conn.prepare(TM_INSERT).await?;
+
+                for entry in entries {
+                    let params = (
+                        entry.language.clone(),
+                        entry.source.clone(),
+                        entry.target.clone(),
+                        entry.key.clone(),
+                    );
+                    if let Err(e) = statement.execute(params).await {
+                        let _ = self.conn.execute("ROLLBACK", ()).await;
+                        return Err(e);
+                    }
+                }
+
+                self.conn.execute("COMMIT", ()).await?;
+                Ok(())
+            })
+            .map_err(io::Error::other)
+    }
+}
+
+#[cfg(feature = "rusqlite-c")]
+struct RusqliteTmStore {
+    db: Connection,
+}
+
+#[cfg(feature = "rusqlite-c")]
+impl RusqliteTmStore {
+    fn open(path: &str) -> Result<Self, io::Error> {
+        let db = Connection::open(path)
+            .map_err(|e| io::Error::other(format!("failed to open translation memory '{}': {}", path, e)))?;
+        db.execute_batch(TM_SCHEMA).map_err(io::Error::other)?;
+        Ok(Self { db })
+    }
+}
+
+#[cfg(feature = "rusqlite-c")]
+impl TmStore for RusqliteTmStore {
+    fn entries(&self, language: &str) -> Result<Vec<TmEntry>, io::Error> {
+        let mut statement = self.db.prepare(TM_SELECT_LANGUAGE).map_err(io::Error::other)?;
+        let rows = statement
+            .query_map(&[&language], |row| TmEntry {
+                language: row.get(0),
+                source: row.get(1),
+                target: row.get(2),
+                key: row.get(3),
+            })
+            .map_err(io::Error::other)?;
+
+        rows.collect::<Result<Vec<_>, _>>().map_err(io::Error::other)
+    }
+
+    fn insert(&self, entries: &[TmEntry]) -> Result<(), io::Error> {
+        self.db.execute_batch("BEGIN").map_err(io::Error::other)?;
+
+        for entry in entries {
+            if let Err(e) = self
+                .db
+                .execute(TM_INSERT, &[&entry.language, &entry.source, &entry.target, &entry.key])
+            {
+                let _ = self.db.execute_batch("ROLLBACK");
+                return Err(io::Error::other(e));
+            }
+        }
+
+        self.db.execute_batch("COMMIT").map_err(io::Error::other)
+    }
+}
+
+fn open_store(path: &str) -> Result<Box<dyn TmStore>, io::Error> {
+    #[cfg(feature = "turso-rust")]
+    {
+        Ok(Box::new(TursoTmStore::open(path)?))
+    }
+
+    #[cfg(all(not(feature = "turso-rust"), feature = "rusqlite-c"))]
+    {
+        return Ok(Box::new(RusqliteTmStore::open(path)?));
+    }
+
+    #[cfg(all(not(feature = "turso-rust"), not(feature = "rusqlite-c")))]
+    {
+        panic!("no query backend feature enabled: enable 'turso-rust' or 'rusqlite-c'");
+    }
+}
+
+/// A local translation memory stored in a SQLite database file.
+pub struct TranslationMemory {
+    store: Box<dyn TmStore>,
+}
+
+impl TranslationMemory {
+    /// Opens the translation memory at `path`, creating it if needed.
+    pub fn open(path: &str) -> Result<Self, io::Error> {
+        Ok(Self {
+            store: open_store(path)?,
+        })
+    }
+
+    pub fn entries(&self, language: &str) -> Result<Vec<TmEntry>, io::Error> {
+        self.store.entries(language)
+    }
+
+    /// Returns the entries whose (language, source, target) is not in the memory yet.
+    pub fn new_entries(&self, entries: Vec<TmEntry>) -> Result<Vec<TmEntry>, io::Error> {
+        let mut known: HashMap<String, HashSet<(String, String)>> = HashMap::new();
+        let mut fresh = Vec::new();
+
+        for entry in entries {
+            if !known.contains_key(&entry.language) {
+                let existing = self
+                    .entries(&entry.language)?
+                    .into_iter()
+                    .map(|existing| (existing.source, existing.target))
+                    .collect();
+                known.insert(entry.language.clone(), existing);
+            }
+
+            let pairs = known
+                .get_mut(&entry.language)
+                .expect("language entries were just loaded");
+            if pairs.insert((entry.source.clone(), entry.target.clone())) {
+                fresh.push(entry);
+            }
+        }
+
+        Ok(fresh)
+    }
+
+    pub fn insert(&self, entries: &[TmEntry]) -> Result<(), io::Error> {
+        self.store.insert(entries)
+    }
+}
+
+fn target_language(target: &str, language: Option<&str>) -> Result<String, io::Error> {
+    language
+        .map(str::to_owned)
+        .or_else(|| culture_from_filename(target))
+        .ok_or_else(|| {
+            io::Error::other(format!(
+                "cannot infer the language of '{}', pass it explicitly with --language",
+                target
+            ))
+        })
+}
+
+/// Character-level Levenshtein distance, or `None` if it exceeds `max`.
+/// Only the cells within `max` of the diagonal are computed, and the rows stop
+/// as soon as every computed cell exceeds `max`.
+fn bounded_edit_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    // cells outside the band, or beyond `max`, hold `over`
+    let over = max + 1;
+    let mut previous = (0..=b.len()).map(|j| j.min(over)).collect::<Vec<_>>();
+    let mut current = vec![over; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        let row = i + 1;
+        let first = row.saturating_sub(max).max(1);
+        let last = (row + max).min(b.len());
+
+        current[first - 1] = if first == 1 { row.min(over) } else { over };
+        let mut row_min = current[first - 1];
+        for j in first..=last {
+            let substitution = previous[j - 1] + usize::from(*ca != b[j - 1]);
+            let cell = substitution.min(previous[j] + 1).min(current[j - 1] + 1).min(over);
+            current[j] = cell;
+            row_min = row_min.min(cell);
+        }
+        if last < b.len() {
+            current[last + 1] = over;
+        }
+
+        if row_min > max {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    Some(previous[b.len()]).filter(|distance| *distance <= max)
+}
+
+/// Similarity between two strings as a percentage, 100 meaning identical.
+pub fn similarity_score(a: &str, b: &str) -> u8 {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    similarity_at_least(&a, &b, 0).unwrap_or_default()
+}
+
+/// Similarity as `similarity_score`, or `None` if it is below `min_score`,
+/// without computing the edit distance of strings whose lengths are too far apart.
+fn similarity_at_least(a: &[char], b: &[char], min_score: u8) -> Option<u8> {
+    let longest = a.len().max(b.len());
+
+    if longest == 0 {
+        return Some(100).filter(|score| *score >= min_score);
+    }
+
+    // the score is at least `min_score` while the distance leaves this many characters the same
+    let min_same = (usize::from(min_score) * longest).div_ceil(100);
+    let distance = bounded_edit_distance(a, b, longest.saturating_sub(min_same))?;
+    let same = longest - distance;
+    Some(u8::try_from(same * 100 / longest).unwrap_or(100)).filter(|score| *score >= min_score)
+}
+
+fn load(file: &str) -> Result<Vec<Resource>, io::Error> {
+    load_resource_file(file).map_err(|e| load_error(file, e))
+}
+
+/// Loads the translations of `target`, as `load`, except that a target that does not exist yet
+/// has no translations, so that suggestions can fill a new translation file.
+fn load_target(target: &str) -> Result<Vec<Resource>, io::Error> {
+    match load(target) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        result => result,
+    }
+}
+
+pub struct TmAdd {
+    tm: String,
+    base: String,
+    targets: Vec<String>,
+    language: Option<String>,
+}
+
+/// Adds the translated values of each of `targets` to the translation memory at `tm`,
+/// using the values of `base` as the source text.
+pub fn tm_add(tm: &str, base: &str, targets: &[&str]) -> TmAdd {
+    TmAdd {
+        tm: tm.to_owned(),
+        base: base.to_owned(),
+        targets: targets.iter().map(|target| (*target).to_owned()).collect(),
+        language: None,
+    }
+}
+
+impl TmAdd {
+    /// Overrides the language that is otherwise inferred from each target file name.
+    #[must_use]
+    pub fn with_language(mut self, language: Option<&str>) -> Self {
+        self.language = language.map(str::to_owned);
+        self
+    }
+
+    /// Returns the base/translation pairs of the target files.
+    /// Fails if the base or a target file cannot be loaded.
+    pub fn pairs(&self) -> Result<Vec<TmEntry>, io::Error> {
+        let base = load(&self.base)?;
+        let mut entries = Vec::new();
+
+        for target in &self.targets {
+            let language = target_language(target, self.language.as_deref())?;
+            let mut engine = CirupEngine::new();
+            engine.register_table_from_resources("A", base.clone());
+            engine.register_table_from_resources("B", load(target)?);
+
+            entries.extend(
+                engine
+                    .query_triple(TRANSLATED_QUERY)
+                    .into_iter()
+                    .filter(|triple| !triple.base.is_empty() && !triple.value.is_empty())
+                    .map(|triple| TmEntry {
+                        language: language.clone(),
+                        source: triple.base,
+                        target: triple.value,
+                        key: triple.name,
+                    }),
+            );
+        }
+
+        Ok(entries)
+    }
+
+    /// Stores the pairs that are not in the translation memory yet, unless `options.dry_run` is set.
+    pub fn add_with_options(&self, options: &QueryRunOptions) -> Result<QueryResult<TmEntry>, io::Error> {
+        let memory = TranslationMemory::open(&self.tm)?;
+        let entries = select_records(memory.new_entries(self.pairs()?)?, options)?;
+
+        if !options.dry_run {
+            memory.insert(&entries)?;
+            info!("added {} entries to translation memory {}", entries.len(), self.tm);
+        }
+
+        evaluate_records(entries, "tm-entry", None, false, options)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatchKind {
+    Exact,
+    Fuzzy,
+}
+
+impl MatchKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            MatchKind::Exact => "exact",
+            MatchKind::Fuzzy => "fuzzy",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Suggestion {
+    pub name: String,
+    pub source: String,
+    pub target: String,
+    pub match_kind: MatchKind,
+    pub score: u8,
+    pub needs_review: bool,
+    pub tm_source: String,
+    pub tm_key: String,
+}
+
+impl QueryRecord for Suggestion {
+    const COLUMNS: &'static [&'static str] = &["name", "match", "score", "review", "source", "target"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.match_kind.as_str().to_owned(),
+            self.score.to_string(),
+            self.needs_review.to_string(),
+            self.source.clone(),
+            self.target.clone(),
+        ]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.name
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.target
+    }
+}
+
+pub struct TmSuggest {
+    tm: String,
+    base: String,
+    target: String,
+    language: Option<String>,
+    min_score: u8,
+}
+
+/// Suggests translations for the keys of `base` that are missing from `target`.
+pub fn tm_suggest(tm: &str, base: &str, target: &str) -> TmSuggest {
+    TmSuggest {
+        tm: tm.to_owned(),
+        base: base.to_owned(),
+        target: target.to_owned(),
+        language: None,
+        min_score: DEFAULT_MIN_SCORE,
+    }
+}
+
+impl TmSuggest {
+    /// Overrides the language that is otherwise inferred from the target file name.
+    #[must_use]
+    pub fn with_language(mut self, language: Option<&str>) -> Self {
+        self.language = language.map(str::to_owned);
+        self
+    }
+
+    /// Sets the minimum similarity score, in percent, of fuzzy matches.
+    #[must_use]
+    pub fn min_score(mut self, min_score: u8) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Exact matches come first; fuzzy matches are scored by edit distance and always need review,
+    /// as do exact matches with more than one known translation.
+    /// Fails if the base or the target cannot be loaded; a target that does not exist yet is empty.
+    pub fn run(&self) -> Result<Vec<Suggestion>, io::Error> {
+        self.suggest(load_target(&self.target)?)
+    }
+
+    fn suggest(&self, translations: Vec<Resource>) -> Result<Vec<Suggestion>, io::Error> {
+        let language = target_language(&self.target, self.language.as_deref())?;
+        let memory = TranslationMemory::open(&self.tm)?;
+        let entries = memory.entries(&language)?;
+
+        let mut by_source: HashMap<&str, Vec<&TmEntry>> = HashMap::new();
+        for entry in &entries {
+            by_source.entry(entry.source.as_str()).or_default().push(entry);
+        }
+        let sources = entries
+            .iter()
+            .map(|entry| entry.source.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let mut engine = CirupEngine::new();
+        engine.register_table_from_resources("A", load(&self.base)?);
+        engine.register_table_from_resources("B", translations);
+
+        let mut suggestions = Vec::new();
+
+        for missing in engine.query_resource(DIFF_QUERY) {
+            if missing.value.is_empty() {
+                continue;
+            }
+
+            if let Some(matches) = by_source.get(missing.value.as_str()) {
+                let first = matches[0];
+                let ambiguous = matches.iter().any(|entry| entry.target != first.target);
+                suggestions.push(Suggestion {
+                    target: first.target.clone(),
+                    match_kind: MatchKind::Exact,
+                    score: 100,
+                    needs_review: ambiguous,
+                    tm_source: first.source.clone(),
+                    tm_key: first.key.clone(),
+                    name: missing.name,
+                    source: missing.value,
+                });
+                continue;
+            }
+
+            let value = missing.value.chars().collect::<Vec<_>>();
+            let best = entries
+                .iter()
+                .zip(&sources)
+                .filter_map(|(entry, source)| {
+                    similarity_at_least(&value, source, self.min_score).map(|score| (score, entry))
+                })
+                .fold(None::<(u8, &TmEntry)>, |best, candidate| match best {
+                    Some(best) if best.0 >= candidate.0 => Some(best),
+                    _ => Some(candidate),
+                });
+
+            if let Some((score, entry)) = best {
+                suggestions.push(Suggestion {
+                    target: entry.target.clone(),
+                    match_kind: MatchKind::Fuzzy,
+                    score,
+                    needs_review: true,
+                    tm_source: entry.source.clone(),
+                    tm_key: entry.key.clone(),
+                    name: missing.name,
+                    source: missing.value,
+                });
+            }
+        }
+
+        Ok(suggestions)
+    }
+
+    /// Returns the suggestions and, with `out_file`, the target file filled with them
+    /// and the list of the filled keys that need review, one per line.
+    pub fn evaluate_with_options(
+        &self,
+        out_file: Option<&str>,
+        touch: bool,
+        output_encoding: OutputEncoding,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<Suggestion>, io::Error> {
+        let translations = load_target(&self.target)?;
+        let suggestions = self.suggest(translations.clone())?;
+
+        let Some(out_file) = out_file else {
+            return evaluate_records(suggestions, "suggestion", None, touch, options);
+        };
+
+        let review_file = review_list_path(out_file);
+        let output = RecordOutput {
+            path: out_file,
+            render: Box::new(move |suggestions: &[Suggestion]| {
+                let mut engine = CirupEngine::new();
+                engine.register_table_from_resources("A", translations);
+                engine.register_table_from_resources(
+                    "B",
+                    suggestions
+                        .iter()
+                        .map(|suggestion| Resource::new(&suggestion.name, &suggestion.target))
+                        .collect(),
+                );
+                output_bytes_for_file(out_file, &engine.query_resource(MERGE_QUERY), output_encoding)
+            }),
+            print_records: true,
+        };
+        let review_output = RecordOutput {
+            path: &review_file,
+            render: Box::new(|suggestions: &[Suggestion]| Ok(review_list_bytes(suggestions))),
+            print_records: true,
+        };
+
+        evaluate_records_to_outputs(
+            suggestions,
+            "suggestion",
+            vec![output, review_output],
+            touch,
+            options,
+            None,
+        )
+    }
+}
+
+#[test]
+fn test_similarity_score() {
+    assert_eq!(similarity_score("Save file", "Save file"), 100);
+    assert_eq!(similarity_score("Save file", "Save files"), 90);
+    assert_eq!(similarity_score("", ""), 100);
+    assert!(similarity_score("Open", "Close the connection") < 50);
+}
+
+#[test]
+fn test_bounded_edit_distance_matches_full_distance() {
+    let (kitten, sitting) = (
+        "kitten".chars().collect::<Vec<_>>(),
+        "sitting".chars().collect::<Vec<_>>(),
+    );
+    assert_eq!(bounded_edit_distance(&kitten, &sitting, 3), Some(3));
+    assert_eq!(bounded_edit_distance(&kitten, &sitting, 2), None);
+
+    let words = [
+        "",
+        "a",
+        "Save",
+        "Save file",
+        "Save files",
+        "Safe fill",
+        "file Save",
+        "Close the connection",
+    ];
+
+    for a in words {
+        for b in words {
+            let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+            let distance = bounded_edit_distance(&a, &b, a.len().max(b.len())).expect("distance within the longest");
+            for max in 0..=a.len().max(b.len()) {
+                assert_eq!(
+                    bounded_edit_distance(&a, &b, max),
+                    Some(distance).filter(|distance| *distance <= max)
+                );
+            }
+            for min_score in [0, 50, 80, 90, 100] {
+                let score = similarity_at_least(&a, &b, 0).expect("score without minimum");
+                assert_eq!(
+                    similarity_at_least(&a, &b, min_score),
+                    Some(score).filter(|score| *score >= min_score)
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_tm_add_and_suggest() {
+    let files = TempFiles::new();
+
+    std::fs::write(
+        files.path("UIResources.restext"),
+        "lblSave=Save file\r\nlblOpen=Open file\r\n",
+    )
+    .expect("failed to write base");
+    std::fs::write(
+        files.path("UIResources.fr.restext"),
+        "lblSave=Enregistrer le fichier\r\nlblOpen=Ouvrir le fichier\r\n",
+    )
+    .expect("failed to write target");
+    std::fs::write(
+        files.path("MsgResources.restext"),
+        "msgSave=Save file\r\nmsgSaveAll=Save files\r\nmsgQuit=Quit now\r\n",
+    )
+    .expect("failed to write second base");
+    std::fs::write(files.path("MsgResources.fr.restext"), "").expect("failed to write second target");
+
+    let tm = files.path("tm.db");
+    let add = tm_add(
+        &tm,
+        &files.path("UIResources.restext"),
+        &[files.path("UIResources.fr.restext").as_str()],
+    );
+    let options = QueryRunOptions {
+        summary: true,
+        ..QueryRunOptions::default()
+    };
+    add.add_with_options(&options).expect("tm add failed");
+    add.add_with_options(&options).expect("second tm add failed");
+
+    let memory = TranslationMemory::open(&tm).expect("tm open failed");
+    assert_eq!(memory.entries("fr").expect("tm entries failed").len(), 2);
+
+    let (base, target) = (
+        files.path("MsgResources.restext"),
+        files.path("MsgResources.fr.restext"),
+    );
+    let suggestions = tm_suggest(&tm, &base, &target).run().expect("suggest failed");
+    let summary = suggestions
+        .iter()
+        .map(|suggestion| {
+            (
+                suggestion.name.as_str(),
+                suggestion.target.as_str(),
+                suggestion.match_kind,
+                suggestion.needs_review,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            ("msgSave", "Enregistrer le fichier", MatchKind::Exact, false),
+            ("msgSaveAll", "Enregistrer le fichier", MatchKind::Fuzzy, true),
+        ]
+    );
+
+    let filled = files.path("MsgResources.filled.fr.restext");
+    let result = tm_suggest(&tm, &base, &target)
+        .evaluate_with_options(
+            Some(&filled),
+            false,
+            OutputEncoding::Utf8NoBom,
+            &QueryRunOptions::default(),
+        )
+        .expect("suggest evaluation failed");
+    let writes = result
+        .pending_writes
+        .iter()
+        .map(|write| (write.path.clone(), String::from_utf8_lossy(&write.bytes).into_owned()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        writes,
+        vec![
+            (
+                filled.clone(),
+                String::from("msgSave=Enregistrer le fichier\r\nmsgSaveAll=Enregistrer le fichier\r\n")
+            ),
+            (review_list_path(&filled), String::from("msgSaveAll\r\n")),
+        ]
+    );
+}
+
+#[test]
+fn test_tm_fails_on_files_that_cannot_be_loaded() {
+    let files = TempFiles::new();
+    let tm = files.path("tm.db");
+    let base = files.path("Strings.restext");
+    let target = files.path("Strings.fr.restext");
+    let invalid = files.path("Strings.de.json");
+    let missing = files.path("Missing.fr.restext");
+    std::fs::write(&base, "lblSave=Save file\r\n").expect("failed to write base");
+    std::fs::write(&target, "lblSave=Enregistrer\r\n").expect("failed to write target");
+    std::fs::write(&invalid, "{").expect("failed to write invalid target");
+
+    assert!(tm_add(&tm, &base, &[target.as_str()]).pairs().is_ok());
+    assert!(tm_add(&tm, &missing, &[target.as_str()]).pairs().is_err());
+    assert!(tm_add(&tm, &base, &[missing.as_str()]).pairs().is_err());
+    assert!(tm_suggest(&tm, &missing, &target).run().is_err());
+    assert!(tm_suggest(&tm, &base, &invalid).run().is_err());
+    assert!(tm_suggest(&tm, &base, &missing).run().is_ok());
+}
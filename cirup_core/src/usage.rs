@@ -0,0 +1,356 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::file::{load_error, load_resource_file};
+use crate::query::{QueryRecord, QueryResult, QueryRunOptions, evaluate_records};
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * Resource key references in C#, XAML, TypeScript and Razor sources:
+ * Resources.lblFoo, GetString("lblFoo"), t('foo.bar'), {x:Static res:Strings.lblFoo}, Localizer["lblFoo"]
+ * Generated *.Designer.cs files reference every key and are skipped when scanning a directory.
+ */
+
+const SOURCE_EXTENSIONS: &[&str] = &["cs", "xaml", "ts", "tsx", "js", "jsx", "cshtml", "razor"];
+const SKIPPED_DIRECTORIES: &[&str] = &[".git", ".vs", "bin", "obj", "node_modules", "target"];
+const GENERATED_SUFFIX: &str = ".designer.cs";
+
+/// Members of generated resource classes that are not resource keys.
+const RESOURCE_CLASS_MEMBERS: &[&str] = &["ResourceManager", "Culture"];
+
+const BUILTIN_EXTRACTORS: &[&str] = &[
+    r"\b(?:\w*(?:Resources|Strings)\.)+([A-Za-z_]\w*)\b",
+    r#"\bGet(?:String|Object)\(\s*@?"([^"]+)""#,
+    r#"\bt\(\s*['"`]([^'"`$]+)['"`]"#,
+    r"\{x:Static\s+(?:\w+:)?\w+\.(\w+)\s*\}",
+    r#"\b(?:Localizer|L|T)\[\s*"([^"]+)"\s*\]"#,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UsageFindingKind {
+    /// The key is defined in a resource file but never referenced.
+    Unused,
+    /// The key is referenced but not defined in any resource file.
+    Undefined,
+}
+
+impl UsageFindingKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            UsageFindingKind::Unused => "unused",
+            UsageFindingKind::Undefined => "undefined",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UsageFinding {
+    pub name: String,
+    pub kind: UsageFindingKind,
+    /// The resource file defining an unused key, or the `file:line` of an undefined reference.
+    pub location: String,
+}
+
+impl QueryRecord for UsageFinding {
+    const COLUMNS: &'static [&'static str] = &["name", "kind", "location"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.name.clone(), self.kind.as_str().to_owned(), self.location.clone()]
+    }
+
+    fn filter_key(&self) -> &str {
+        &self.name
+    }
+
+    fn filter_value(&self) -> &str {
+        &self.location
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyReference {
+    pub key: String,
+    pub file: String,
+    pub line: usize,
+}
+
+fn compile_extractor(pattern: &str) -> Result<Regex, io::Error> {
+    let regex = Regex::new(pattern).map_err(|e| io::Error::other(format!("invalid extractor '{}': {}", pattern, e)))?;
+
+    if regex.captures_len() < 2 {
+        return Err(io::Error::other(format!(
+            "extractor '{}' must have a capture group for the key",
+            pattern
+        )));
+    }
+
+    Ok(regex)
+}
+
+fn collect_source_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), io::Error> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(fs::DirEntry::file_name);
+
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name();
+
+        if path.is_dir() {
+            if !SKIPPED_DIRECTORIES.iter().any(|skipped| name == *skipped) {
+                collect_source_files(&path, files)?;
+            }
+        } else if name.to_string_lossy().to_ascii_lowercase().ends_with(GENERATED_SUFFIX) {
+            continue;
+        } else if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| SOURCE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+        {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Extracts the key references in `text`, using the `key` group or the first non-empty capture group of each match.
+/// A key matched by several extractors on the same line is reported once.
+pub fn extract_references(text: &str, file: &str, extractors: &[Regex]) -> Vec<KeyReference> {
+    let mut references = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let mut line_keys = HashSet::new();
+
+        for extractor in extractors {
+            for captures in extractor.captures_iter(line) {
+                let Some(key) = captures
+                    .name("key")
+                    .or_else(|| captures.iter().skip(1).flatten().find(|group| !group.is_empty()))
+                else {
+                    continue;
+                };
+
+                let key = key.as_str();
+                if RESOURCE_CLASS_MEMBERS.contains(&key) || !line_keys.insert(key) {
+                    continue;
+                }
+
+                references.push(KeyReference {
+                    key: key.to_owned(),
+                    file: file.to_owned(),
+                    line: index + 1,
+                });
+            }
+        }
+    }
+
+    references
+}
+
+pub struct UsageQuery {
+    resource_files: Vec<String>,
+    sources: Vec<String>,
+    extractors: Vec<String>,
+    builtin_extractors: bool,
+}
+
+/// Compares the keys of `resource_files` with the key references found in `sources`.
+pub fn query_usage(resource_files: &[&str], sources: &[&str]) -> UsageQuery {
+    UsageQuery {
+        resource_files: resource_files.iter().map(|file| (*file).to_owned()).collect(),
+        sources: sources.iter().map(|source| (*source).to_owned()).collect(),
+        extractors: Vec::new(),
+        builtin_extractors: true,
+    }
+}
+
+impl UsageQuery {
+    /// Adds regex extractors; the key is the `key` named group, or the first capture group.
+    #[must_use]
+    pub fn with_extractors(mut self, extractors: &[String]) -> Self {
+        self.extractors.extend(extractors.iter().cloned());
+        self
+    }
+
+    /// Enables or disables the built-in C#, XAML, TypeScript and Razor extractors.
+    #[must_use]
+    pub fn builtin_extractors(mut self, enabled: bool) -> Self {
+        self.builtin_extractors = enabled;
+        self
+    }
+
+    pub fn references(&self) -> Result<Vec<KeyReference>, io::Error> {
+        let builtin = if self.builtin_extractors {
+            BUILTIN_EXTRACTORS
+        } else {
+            &[]
+        };
+        let extractors = builtin
+            .iter()
+            .copied()
+            .chain(self.extractors.iter().map(String::as_str))
+            .map(compile_extractor)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut files = Vec::new();
+        for source in &self.sources {
+            collect_source_files(Path::new(source), &mut files)
+                .map_err(|e| io::Error::other(format!("{}: {}", source, e)))?;
+        }
+
+        let mut references = Vec::new();
+        for file in files {
+            let Ok(text) = fs::read_to_string(&file) else {
+                warn!("skipping unreadable source file {}", file.display());
+                continue;
+            };
+            references.extend(extract_references(&text, &file.to_string_lossy(), &extractors));
+        }
+
+        Ok(references)
+    }
+
+    pub fn run(&self) -> Result<Vec<UsageFinding>, io::Error> {
+        let references = self.references()?;
+        let referenced_keys = references
+            .iter()
+            .map(|reference| reference.key.as_str())
+            .collect::<HashSet<_>>();
+
+        let mut findings = Vec::new();
+        let mut defined = HashSet::new();
+
+        for resource_file in &self.resource_files {
+            let resources = load_resource_file(resource_file).map_err(|e| load_error(resource_file, e))?;
+
+            for resource in resources {
+                if !referenced_keys.contains(resource.name.as_str()) {
+                    findings.push(UsageFinding {
+                        name: resource.name.clone(),
+                        kind: UsageFindingKind::Unused,
+                        location: resource_file.clone(),
+                    });
+                }
+                defined.insert(resource.name);
+            }
+        }
+
+        findings.extend(
+            references
+                .into_iter()
+                .filter(|reference| !defined.contains(&reference.key))
+                .map(|reference| UsageFinding {
+                    location: format!("{}:{}", reference.file, reference.line),
+                    name: reference.key,
+                    kind: UsageFindingKind::Undefined,
+                }),
+        );
+
+        Ok(findings)
+    }
+
+    /// Returns the findings, optionally restricted to one kind.
+    pub fn evaluate_with_options(
+        &self,
+        kind: Option<UsageFindingKind>,
+        options: &QueryRunOptions,
+    ) -> Result<QueryResult<UsageFinding>, io::Error> {
+        let mut findings = self.run()?;
+        if let Some(kind) = kind {
+            findings.retain(|finding| finding.kind == kind);
+        }
+
+        evaluate_records(findings, "usage-finding", None, false, options)
+    }
+}
+
+#[test]
+fn test_extract_builtin_references() {
+    let extractors = BUILTIN_EXTRACTORS
+        .iter()
+        .map(|pattern| compile_extractor(pattern).expect("builtin extractor"))
+        .collect::<Vec<_>>();
+
+    let csharp = "var a = Resources.lblFoo;\nvar b = UIResources.ResourceManager.GetString(\"lblBar\");\nvar c = Properties.Resources.Strings.lblNested.Length;\n";
+    let xaml = "<TextBlock Text=\"{x:Static res:Strings.lblXaml}\" />";
+    let ts = "const title = i18n.t('page.title'); t(`dyn.${x}`);";
+    let razor = "<h1>@Localizer[\"lblRazor\"]</h1>";
+
+    let keys = [csharp, xaml, ts, razor]
+        .iter()
+        .flat_map(|text| extract_references(text, "file", &extractors))
+        .map(|reference| reference.key)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        keys,
+        vec!["lblFoo", "lblBar", "lblNested", "lblXaml", "page.title", "lblRazor"]
+    );
+}
+
+#[test]
+fn test_custom_extractor_requires_capture_group() {
+    assert!(compile_extractor(r"Tr\(\w+\)").is_err());
+
+    let extractor = compile_extractor(r#"Tr\("(?P<key>[^"]+)"\)"#).expect("custom extractor");
+    let references = extract_references("x = Tr(\"lblCustom\");", "a.cs", &[extractor]);
+    assert_eq!(
+        references,
+        vec![KeyReference {
+            key: String::from("lblCustom"),
+            file: String::from("a.cs"),
+            line: 1,
+        }]
+    );
+}
+
+#[test]
+fn test_usage_reports_unused_and_undefined_keys() {
+    let files = TempFiles::new();
+    fs::create_dir(files.path("src")).expect("failed to create source dir");
+
+    fs::write(files.path("Strings.restext"), "lblUsed=Used\r\nlblUnused=Unused\r\n")
+        .expect("failed to write resources");
+    fs::write(
+        files.path("src/Form.cs"),
+        "label.Text = Strings.lblUsed;\nother.Text = Strings.lblMissing;\n",
+    )
+    .expect("failed to write source");
+    fs::write(
+        files.path("src/Strings.Designer.cs"),
+        "public static string lblUnused => ResourceManager.GetString(\"lblUnused\");\n",
+    )
+    .expect("failed to write generated source");
+
+    let findings = query_usage(&[files.path("Strings.restext").as_str()], &[files.path("src").as_str()])
+        .run()
+        .expect("usage failed");
+
+    assert_eq!(
+        findings,
+        vec![
+            UsageFinding {
+                name: String::from("lblUnused"),
+                kind: UsageFindingKind::Unused,
+                location: files.path("Strings.restext"),
+            },
+            UsageFinding {
+                name: String::from("lblMissing"),
+                kind: UsageFindingKind::Undefined,
+                location: format!("{}:2", files.path("src/Form.cs")),
+            },
+        ]
+    );
+}
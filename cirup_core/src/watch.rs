@@ -0,0 +1,151 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, channel};
+use std::time::Duration;
+
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{DebounceEventResult, Debouncer, new_debouncer};
+use sha2::{Digest, Sha256};
+
+#[cfg(test)]
+use crate::test_util::TempFiles;
+
+/*
+ * Watch mode: input files are watched through their parent directory, so that editors
+ * replacing a file on save are still noticed. Bursts of events are debounced, and a change
+ * is only reported when the content of a watched file differs from what was last seen.
+ */
+
+pub const DEFAULT_DEBOUNCE_MS: u64 = 200;
+
+struct WatchedFile {
+    name: String,
+    path: PathBuf,
+    digest: Option<Vec<u8>>,
+}
+
+fn content_digest(path: &Path) -> Option<Vec<u8>> {
+    fs::read(path).ok().map(|bytes| Sha256::digest(&bytes).to_vec())
+}
+
+fn watched_path(file: &str) -> Result<PathBuf, io::Error> {
+    let path = Path::new(file);
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::other(format!("cannot watch '{}'", file)))?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    Ok(parent.canonicalize()?.join(name))
+}
+
+pub struct FileWatcher {
+    files: Vec<WatchedFile>,
+    directories: Vec<PathBuf>,
+    receiver: Receiver<DebounceEventResult>,
+    debouncer: Debouncer<RecommendedWatcher>,
+}
+
+impl FileWatcher {
+    /// Watches `files`, reporting changes once no event was received for `debounce`.
+    pub fn new(files: &[&str], debounce: Duration) -> Result<Self, io::Error> {
+        let (sender, receiver) = channel();
+        let debouncer = new_debouncer(debounce, sender).map_err(io::Error::other)?;
+
+        let mut watcher = Self {
+            files: Vec::new(),
+            directories: Vec::new(),
+            receiver,
+            debouncer,
+        };
+        watcher.watch_files(files)?;
+        Ok(watcher)
+    }
+
+    /// Watches `files` as well, from their current content. Files already watched are skipped.
+    pub fn watch_files(&mut self, files: &[&str]) -> Result<(), io::Error> {
+        for file in files {
+            let path = watched_path(file)?;
+            if self.files.iter().any(|watched| watched.path == path) {
+                continue;
+            }
+
+            if let Some(directory) = path.parent()
+                && !self.directories.iter().any(|watched| watched == directory)
+            {
+                self.debouncer
+                    .watcher()
+                    .watch(directory, RecursiveMode::NonRecursive)
+                    .map_err(|e| io::Error::other(format!("{}: {}", file, e)))?;
+                self.directories.push(directory.to_path_buf());
+            }
+
+            self.files.push(WatchedFile {
+                name: (*file).to_owned(),
+                digest: content_digest(&path),
+                path,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Records the current content of the watched files, before a run, so that
+    /// the changes made while it runs are reported by the next wait.
+    pub fn mark_seen(&mut self) {
+        for file in &mut self.files {
+            file.digest = content_digest(&file.path);
+        }
+    }
+
+    /// Blocks until the content of a watched file changes, and returns the changed files.
+    pub fn wait_for_change(&mut self) -> Result<Vec<String>, io::Error> {
+        loop {
+            let events = self
+                .receiver
+                .recv()
+                .map_err(io::Error::other)?
+                .map_err(io::Error::other)?;
+
+            if !events
+                .iter()
+                .any(|event| self.files.iter().any(|file| file.path == event.path))
+            {
+                continue;
+            }
+
+            let mut changed = Vec::new();
+            for file in &mut self.files {
+                let digest = content_digest(&file.path);
+                if digest != file.digest {
+                    file.digest = digest;
+                    changed.push(file.name.clone());
+                }
+            }
+
+            if !changed.is_empty() {
+                return Ok(changed);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_watcher_reports_content_changes_only() {
+    let files = TempFiles::new();
+    let path = files.path("strings.restext");
+    let other = files.path("other.restext");
+    fs::write(&path, "lblOne=One\r\n").expect("failed to write resources");
+
+    let mut watcher = FileWatcher::new(&[path.as_str()], Duration::from_millis(50)).expect("failed to watch");
+
+    // unrelated files and rewrites with the same content are ignored
+    fs::write(&other, "lblOther=Other\r\n").expect("failed to write other file");
+    fs::write(&path, "lblOne=One\r\n").expect("failed to rewrite resources");
+    fs::write(&path, "lblOne=Uno\r\n").expect("failed to update resources");
+
+    assert_eq!(watcher.wait_for_change().expect("watch failed"), vec![path]);
+}
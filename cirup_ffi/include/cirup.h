@@ -0,0 +1,74 @@
+#ifndef CIRUP_H
+#define CIRUP_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CIRUP_OK 0
+#define CIRUP_ERROR -1
+/* Returned by cirup_run for an operation it does not run. */
+#define CIRUP_ERROR_UNSUPPORTED -2
+
+typedef struct CirupResource
+{
+	char* name;
+	char* value;
+} CirupResource;
+
+typedef struct CirupResourceList
+{
+	CirupResource* items;
+	size_t count;
+} CirupResourceList;
+
+typedef struct CirupRunResult CirupRunResult;
+
+/* Returns the library version. */
+const char* cirup_version(void);
+
+/* Returns the message of the last error on the calling thread, or NULL.
+ * The string is valid until the next call to the library on the same thread. */
+const char* cirup_last_error(void);
+
+/* Parses a resource file into key/value pairs, in the format given by its extension. */
+int32_t cirup_parse_file(const char* filename, CirupResourceList** list);
+
+/* Parses in-memory resources in the format given by extension, such as "resx". */
+int32_t cirup_parse_bytes(const uint8_t* data, size_t length, const char* extension, CirupResourceList** list);
+
+void cirup_resource_list_free(CirupResourceList* list);
+
+/* Runs an operation on input_count input files. The supported operations are:
+ *   "file-print", "file-convert" and "file-sort" on one file;
+ *   "file-diff", "file-diff-changes", "file-merge", "file-intersect" and "file-subtract" on two files;
+ *   "diff-with-base" on three files, without an output file.
+ * Other operations fail with CIRUP_ERROR_UNSUPPORTED.
+ * When output_file is not NULL, the output is rendered in the format given by its extension
+ * and returned by cirup_run_result_output, without writing the file. */
+int32_t cirup_run(const char* operation, const char* const* input_files, size_t input_count, const char* output_file,
+                  CirupRunResult** result);
+
+/* Returns the execution report as a JSON object, valid until the result is freed. */
+const char* cirup_run_result_report_json(const CirupRunResult* result);
+
+/* Returns the rows as a JSON array, valid until the result is freed. Rows read from text formats
+ * have a "span" object with their file and 1-based line, column, end_line and end_column. */
+const char* cirup_run_result_rows_json(const CirupRunResult* result);
+
+/* Returns the content of the output file, or NULL when the run has no output file. */
+const uint8_t* cirup_run_result_output(const CirupRunResult* result, size_t* length);
+
+/* Writes the output file, unless it already has the same content. */
+int32_t cirup_run_result_write_output(const CirupRunResult* result);
+
+void cirup_run_result_free(CirupRunResult* result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CIRUP_H */
@@ -0,0 +1,695 @@
+//! C ABI for cirup, to parse resource files and run operations in-process, from .NET or any
+//! language with a C foreign function interface. The functions are declared in `include/cirup.h`.
+//!
+//! Functions returning `int32_t` return `CIRUP_OK` on success and `CIRUP_ERROR` on failure, or
+//! `CIRUP_ERROR_UNSUPPORTED` for an operation that `cirup_run` does not run, in which case
+//! `cirup_last_error` returns the error message. Objects returned by the library are
+//! owned by the caller and released with the matching `_free` function. A panic in the library is
+//! caught and reported as `CIRUP_ERROR` rather than unwinding into the caller.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::error::Error;
+use std::ffi::{CStr, CString, c_char};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::{ptr, slice};
+
+use cirup_core::merge::merge_files;
+use cirup_core::query::{CirupQuery, PendingWrite, QueryExecutionReport, QueryOperation, QueryResult, QueryRunOptions};
+use cirup_core::{CirupError, OutputEncoding, Resource, load_resources, parse_resources, resources_to_bytes};
+
+pub const CIRUP_OK: i32 = 0;
+pub const CIRUP_ERROR: i32 = -1;
+pub const CIRUP_ERROR_UNSUPPORTED: i32 = -2;
+
+/// An operation that `cirup_run` does not run, such as a command that only the CLI has.
+#[derive(Debug)]
+struct UnsupportedOperation(String);
+
+impl fmt::Display for UnsupportedOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unsupported operation '{}'", self.0)
+    }
+}
+
+impl Error for UnsupportedOperation {}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+fn clear_last_error() {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = None);
+}
+
+/// Stores `result` into `out`, or records its error, and returns the status code.
+fn complete<T>(result: Result<T, Box<dyn Error>>, out: *mut *mut T) -> i32 {
+    if out.is_null() {
+        set_last_error("output pointer is null");
+        return CIRUP_ERROR;
+    }
+
+    match result {
+        Ok(value) => {
+            clear_last_error();
+            // SAFETY: `out` was checked to be non-null, and the caller guarantees it is writable.
+            unsafe { *out = Box::into_raw(Box::new(value)) };
+            CIRUP_OK
+        }
+        Err(e) => {
+            set_last_error(&e.to_string());
+            // SAFETY: `out` was checked to be non-null, and the caller guarantees it is writable.
+            unsafe { *out = ptr::null_mut() };
+            if e.is::<UnsupportedOperation>() {
+                CIRUP_ERROR_UNSUPPORTED
+            } else {
+                CIRUP_ERROR
+            }
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| (*message).to_owned())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| String::from("unknown panic"));
+    format!("internal error: {}", message)
+}
+
+/// Runs the body of an exported function, returning the message of a panic instead of unwinding into the caller.
+fn catch_panic<R>(body: impl FnOnce() -> R) -> Result<R, String> {
+    panic::catch_unwind(AssertUnwindSafe(body)).map_err(|payload| panic_message(&*payload))
+}
+
+/// Runs the body of an exported function, returning `fallback` and recording the error on panic.
+fn guard<R>(fallback: R, body: impl FnOnce() -> R) -> R {
+    catch_panic(body).unwrap_or_else(|message| {
+        set_last_error(&message);
+        fallback
+    })
+}
+
+/// Runs the body of an exported function that stores its result into `out`, clearing `out` on panic.
+fn guard_out<T>(out: *mut *mut T, body: impl FnOnce() -> i32) -> i32 {
+    catch_panic(body).unwrap_or_else(|message| complete::<T>(Err(message.into()), out))
+}
+
+/// Reads a nul-terminated UTF-8 string, returning `None` for a null pointer.
+///
+/// # Safety
+///
+/// `value` must be null or point to a nul-terminated string.
+unsafe fn read_str(value: *const c_char, what: &str) -> Result<Option<String>, Box<dyn Error>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+
+    // SAFETY: `value` is non-null and nul-terminated, as guaranteed by the caller.
+    let value = unsafe { CStr::from_ptr(value) };
+    let value = value.to_str().map_err(|_| format!("{} is not valid UTF-8", what))?;
+    Ok(Some(value.to_owned()))
+}
+
+/// Reads a required nul-terminated UTF-8 string.
+///
+/// # Safety
+///
+/// `value` must be null or point to a nul-terminated string.
+unsafe fn read_required_str(value: *const c_char, what: &str) -> Result<String, Box<dyn Error>> {
+    // SAFETY: forwarded from the caller.
+    let value = unsafe { read_str(value, what) }?;
+    value.ok_or_else(|| format!("{} is null", what).into())
+}
+
+fn to_c_string(value: &str) -> *mut c_char {
+    CString::new(value.replace('\0', " ")).unwrap_or_default().into_raw()
+}
+
+/// A key/value pair, with nul-terminated UTF-8 strings.
+#[repr(C)]
+pub struct CirupResource {
+    pub name: *mut c_char,
+    pub value: *mut c_char,
+}
+
+/// An array of key/value pairs, in file order.
+#[repr(C)]
+pub struct CirupResourceList {
+    pub items: *mut CirupResource,
+    pub count: usize,
+}
+
+impl CirupResourceList {
+    fn new(resources: &[Resource]) -> Self {
+        let items = resources
+            .iter()
+            .map(|resource| CirupResource {
+                name: to_c_string(&resource.name),
+                value: to_c_string(&resource.value),
+            })
+            .collect::<Box<[_]>>();
+        let count = items.len();
+
+        Self {
+            items: Box::into_raw(items).cast(),
+            count,
+        }
+    }
+}
+
+impl Drop for CirupResourceList {
+    fn drop(&mut self) {
+        // SAFETY: `items` and `count` come from the boxed slice leaked in `CirupResourceList::new`.
+        let items = unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(self.items, self.count)) };
+        for item in &items {
+            // SAFETY: `name` was created by `CString::into_raw` in `to_c_string`.
+            drop(unsafe { CString::from_raw(item.name) });
+            // SAFETY: `value` was created by `CString::into_raw` in `to_c_string`.
+            drop(unsafe { CString::from_raw(item.value) });
+        }
+    }
+}
+
+/// The result of `cirup_run`: the JSON report and rows, and the content of the output file.
+pub struct CirupRunResult {
+    report_json: CString,
+    rows_json: CString,
+    output: Option<Vec<u8>>,
+    pending_writes: Vec<PendingWrite>,
+}
+
+impl CirupRunResult {
+    fn new(
+        report: &QueryExecutionReport,
+        rows_json: String,
+        output: Option<Vec<u8>>,
+        pending_writes: Vec<PendingWrite>,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            report_json: CString::new(serde_json::to_string(report)?)?,
+            rows_json: CString::new(rows_json)?,
+            output,
+            pending_writes,
+        })
+    }
+
+    /// Renders the resources of a run in the format of `output_file`.
+    fn from_resources(result: QueryResult<Resource>, output_file: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let output = match output_file {
+            Some(output_file) => {
+                let extension = Path::new(output_file)
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .unwrap_or_default();
+                Some(resources_to_bytes(&result.rows, extension, OutputEncoding::Utf8NoBom)?)
+            }
+            None => None,
+        };
+
+        Self::new(
+            &result.report,
+            serde_json::to_string(&result.rows)?,
+            output,
+            result.pending_writes,
+        )
+    }
+}
+
+fn run(operation: &str, input_files: &[String], output_file: Option<&str>) -> Result<CirupRunResult, Box<dyn Error>> {
+    #[cfg(test)]
+    if operation == "test-panic" {
+        panic!("test panic in {}", operation);
+    }
+
+    let operation = QueryOperation::from_name(operation).ok_or_else(|| UnsupportedOperation(operation.to_owned()))?;
+    let inputs = input_files.iter().map(String::as_str).collect::<Vec<_>>();
+    let options = QueryRunOptions::default().with_context(operation.name(), &inputs, output_file);
+
+    match operation {
+        QueryOperation::Merge => {
+            // merged as by the file-merge command, with the merge counts in the report
+            let [file_one, file_two] = inputs[..] else {
+                return Err(CirupError::InputCount {
+                    operation: operation.name(),
+                    expected: operation.input_count(),
+                    actual: inputs.len(),
+                }
+                .into());
+            };
+            let result = merge_files(file_one, file_two).evaluate_with_options(
+                output_file,
+                false,
+                OutputEncoding::Utf8NoBom,
+                &options,
+            )?;
+            CirupRunResult::from_resources(result, output_file)
+        }
+        _ if operation.returns_triples() => {
+            if output_file.is_some() {
+                return Err(format!("{} does not write an output file", operation.name()).into());
+            }
+
+            let result = CirupQuery::from_files(operation, &inputs)?.evaluate_triple_with_options(&options)?;
+            CirupRunResult::new(
+                &result.report,
+                serde_json::to_string(&result.rows)?,
+                None,
+                result.pending_writes,
+            )
+        }
+        _ => {
+            let result = CirupQuery::from_files(operation, &inputs)?.evaluate_with_options(
+                output_file,
+                false,
+                OutputEncoding::Utf8NoBom,
+                &options,
+            )?;
+            CirupRunResult::from_resources(result, output_file)
+        }
+    }
+}
+
+/// Returns the version of the library, as a static nul-terminated string.
+#[unsafe(no_mangle)]
+pub extern "C" fn cirup_version() -> *const c_char {
+    guard(ptr::null(), || concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast())
+}
+
+/// Returns the message of the last error on the calling thread, or null.
+/// The string is valid until the next call to the library on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn cirup_last_error() -> *const c_char {
+    guard(ptr::null(), || {
+        LAST_ERROR.with(|last_error| {
+            last_error
+                .borrow()
+                .as_ref()
+                .map_or(ptr::null(), |message| message.as_ptr())
+        })
+    })
+}
+
+/// Parses a resource file into key/value pairs, in the format given by its extension.
+///
+/// # Safety
+///
+/// `filename` must be a nul-terminated string, and `out` must be a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cirup_parse_file(filename: *const c_char, out: *mut *mut CirupResourceList) -> i32 {
+    guard_out(out, || {
+        // SAFETY: forwarded from the caller.
+        let filename = unsafe { read_required_str(filename, "filename") };
+        let result = filename.and_then(|filename| Ok(CirupResourceList::new(&load_resources(&filename)?)));
+        complete(result, out)
+    })
+}
+
+/// Parses in-memory resources into key/value pairs, in the format given by `extension`, such as `resx`.
+///
+/// # Safety
+///
+/// `data` must point to `length` readable bytes, or be null if `length` is zero. `extension` must be
+/// a nul-terminated string, and `out` must be a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cirup_parse_bytes(
+    data: *const u8,
+    length: usize,
+    extension: *const c_char,
+    out: *mut *mut CirupResourceList,
+) -> i32 {
+    guard_out(out, || {
+        let data = if length == 0 {
+            &[][..]
+        } else if data.is_null() {
+            return complete(Err("data is null".into()), out);
+        } else {
+            // SAFETY: `data` is non-null and points to `length` bytes, as guaranteed by the caller.
+            unsafe { slice::from_raw_parts(data, length) }
+        };
+
+        // SAFETY: forwarded from the caller.
+        let extension = unsafe { read_required_str(extension, "extension") };
+        let result = extension.and_then(|extension| Ok(CirupResourceList::new(&parse_resources(data, &extension)?)));
+        complete(result, out)
+    })
+}
+
+/// Releases a list returned by `cirup_parse_file` or `cirup_parse_bytes`.
+///
+/// # Safety
+///
+/// `list` must be null or a list returned by the library that was not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cirup_resource_list_free(list: *mut CirupResourceList) {
+    guard((), || {
+        if !list.is_null() {
+            // SAFETY: `list` was created by `Box::into_raw` in `complete`.
+            drop(unsafe { Box::from_raw(list) });
+        }
+    });
+}
+
+/// Runs an operation, such as `file-diff` or `file-merge`, on `input_count` input files. The operations
+/// are those of `QueryOperation`: `file-print`, `file-convert`, `file-sort`, `file-diff`, `file-diff-changes`,
+/// `diff-with-base`, `file-merge`, `file-intersect` and `file-subtract`. Other operations fail with
+/// `CIRUP_ERROR_UNSUPPORTED`.
+///
+/// When `output_file` is not null, the output is rendered in the format given by its extension
+/// and returned by `cirup_run_result_output`, without writing the file.
+///
+/// # Safety
+///
+/// `operation` must be a nul-terminated string, `input_files` must point to `input_count`
+/// nul-terminated strings, `output_file` must be null or a nul-terminated string, and `out`
+/// must be a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cirup_run(
+    operation: *const c_char,
+    input_files: *const *const c_char,
+    input_count: usize,
+    output_file: *const c_char,
+    out: *mut *mut CirupRunResult,
+) -> i32 {
+    guard_out(out, || {
+        let input_files = if input_count == 0 {
+            &[][..]
+        } else if input_files.is_null() {
+            return complete(Err("input_files is null".into()), out);
+        } else {
+            // SAFETY: `input_files` is non-null and points to `input_count` pointers, as guaranteed by the caller.
+            unsafe { slice::from_raw_parts(input_files, input_count) }
+        };
+
+        let result = (|| {
+            // SAFETY: forwarded from the caller.
+            let operation = unsafe { read_required_str(operation, "operation") }?;
+            // SAFETY: forwarded from the caller.
+            let output_file = unsafe { read_str(output_file, "output_file") }?;
+            let input_files = input_files
+                .iter()
+                // SAFETY: each input file is a nul-terminated string, as guaranteed by the caller.
+                .map(|input_file| unsafe { read_required_str(*input_file, "input file") })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            run(&operation, &input_files, output_file.as_deref())
+        })();
+
+        complete(result, out)
+    })
+}
+
+/// Returns the execution report of a run as a JSON object, valid until the result is released.
+///
+/// # Safety
+///
+/// `result` must be a result returned by `cirup_run` that was not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cirup_run_result_report_json(result: *const CirupRunResult) -> *const c_char {
+    guard(ptr::null(), || {
+        // SAFETY: `result` is a live result, as guaranteed by the caller.
+        unsafe { result.as_ref() }.map_or(ptr::null(), |result| result.report_json.as_ptr())
+    })
+}
+
+/// Returns the rows of a run as a JSON array, valid until the result is released.
+///
+/// # Safety
+///
+/// `result` must be a result returned by `cirup_run` that was not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cirup_run_result_rows_json(result: *const CirupRunResult) -> *const c_char {
+    guard(ptr::null(), || {
+        // SAFETY: `result` is a live result, as guaranteed by the caller.
+        unsafe { result.as_ref() }.map_or(ptr::null(), |result| result.rows_json.as_ptr())
+    })
+}
+
+/// Returns the content of the output file of a run and stores its length in `length`,
+/// or returns null when the run has no output file.
+///
+/// # Safety
+///
+/// `result` must be a result returned by `cirup_run` that was not released yet, and `length`
+/// must be a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cirup_run_result_output(result: *const CirupRunResult, length: *mut usize) -> *const u8 {
+    guard(ptr::null(), || {
+        // SAFETY: `result` is a live result, as guaranteed by the caller.
+        let output = unsafe { result.as_ref() }.and_then(|result| result.output.as_deref());
+
+        if !length.is_null() {
+            // SAFETY: `length` is non-null and writable, as guaranteed by the caller.
+            unsafe { *length = output.map_or(0, <[u8]>::len) };
+        }
+
+        output.map_or(ptr::null(), <[u8]>::as_ptr)
+    })
+}
+
+/// Writes the output file of a run, unless it already has the same content.
+///
+/// # Safety
+///
+/// `result` must be a result returned by `cirup_run` that was not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cirup_run_result_write_output(result: *const CirupRunResult) -> i32 {
+    guard(CIRUP_ERROR, || {
+        // SAFETY: `result` is a live result, as guaranteed by the caller.
+        let Some(result) = (unsafe { result.as_ref() }) else {
+            set_last_error("result is null");
+            return CIRUP_ERROR;
+        };
+
+        match result.pending_writes.iter().try_for_each(PendingWrite::write) {
+            Ok(()) => {
+                clear_last_error();
+                CIRUP_OK
+            }
+            Err(e) => {
+                set_last_error(&e.to_string());
+                CIRUP_ERROR
+            }
+        }
+    })
+}
+
+/// Releases a result returned by `cirup_run`.
+///
+/// # Safety
+///
+/// `result` must be null or a result returned by `cirup_run` that was not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cirup_run_result_free(result: *mut CirupRunResult) {
+    guard((), || {
+        if !result.is_null() {
+            // SAFETY: `result` was created by `Box::into_raw` in `complete`.
+            drop(unsafe { Box::from_raw(result) });
+        }
+    });
+}
+
+#[cfg(test)]
+fn c_string(value: &str) -> CString {
+    CString::new(value).expect("string contains a nul byte")
+}
+
+#[cfg(test)]
+fn last_error() -> String {
+    let message = cirup_last_error();
+    assert!(!message.is_null());
+    // SAFETY: the last error is a nul-terminated string owned by the library.
+    unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+}
+
+#[test]
+fn test_parse_bytes() {
+    let data = b"lblBoat=I'm on a boat\r\nlblYolo=You only live once\r\n";
+    let extension = c_string("restext");
+    let mut list = ptr::null_mut();
+
+    // SAFETY: `data` and `extension` are valid for the duration of the call.
+    let status = unsafe { cirup_parse_bytes(data.as_ptr(), data.len(), extension.as_ptr(), &mut list) };
+    assert_eq!(status, CIRUP_OK);
+
+    // SAFETY: the call succeeded, so `list` points to a valid list.
+    let list_ref = unsafe { &*list };
+    // SAFETY: `items` points to `count` resources owned by the list.
+    let items = unsafe { slice::from_raw_parts(list_ref.items, list_ref.count) };
+    let pairs = items
+        .iter()
+        .map(|item| {
+            // SAFETY: names and values are nul-terminated strings owned by the list.
+            let name = unsafe { CStr::from_ptr(item.name) }.to_string_lossy().into_owned();
+            // SAFETY: names and values are nul-terminated strings owned by the list.
+            let value = unsafe { CStr::from_ptr(item.value) }.to_string_lossy().into_owned();
+            (name, value)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        pairs,
+        vec![
+            ("lblBoat".to_owned(), "I'm on a boat".to_owned()),
+            ("lblYolo".to_owned(), "You only live once".to_owned()),
+        ]
+    );
+
+    // SAFETY: `list` was returned by the library and is released once.
+    unsafe { cirup_resource_list_free(list) };
+
+    let extension = c_string("txt");
+    // SAFETY: `data` and `extension` are valid for the duration of the call.
+    let status = unsafe { cirup_parse_bytes(data.as_ptr(), data.len(), extension.as_ptr(), &mut list) };
+    assert_eq!(status, CIRUP_ERROR);
+    assert!(list.is_null());
+    assert!(last_error().contains("unsupported resource format"));
+}
+
+#[test]
+fn test_run_operation() {
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let base = dir.path().join("strings.restext");
+    let target = dir.path().join("strings.fr.restext");
+    let output = dir.path().join("missing.json");
+    std::fs::write(&base, "lblBoat=I'm on a boat\r\nlblYolo=You only live once\r\n").expect("failed to write base");
+    std::fs::write(&target, "lblBoat=Je suis sur un bateau\r\n").expect("failed to write target");
+
+    let operation = c_string("file-diff");
+    let inputs = [c_string(&base.to_string_lossy()), c_string(&target.to_string_lossy())];
+    let input_ptrs = inputs.iter().map(|input| input.as_ptr()).collect::<Vec<_>>();
+    let output_file = c_string(&output.to_string_lossy());
+    let mut result = ptr::null_mut();
+
+    // SAFETY: all strings are valid for the duration of the call.
+    let status = unsafe {
+        cirup_run(
+            operation.as_ptr(),
+            input_ptrs.as_ptr(),
+            input_ptrs.len(),
+            output_file.as_ptr(),
+            &mut result,
+        )
+    };
+    assert_eq!(status, CIRUP_OK);
+
+    // SAFETY: `result` is a live result returned by the library.
+    let report = unsafe { cirup_run_result_report_json(result) };
+    // SAFETY: the report is a nul-terminated string owned by the result.
+    let report = unsafe { CStr::from_ptr(report) };
+    let report: serde_json::Value = serde_json::from_slice(report.to_bytes()).expect("invalid report");
+    assert_eq!(report["operation"], "file-diff");
+    assert_eq!(report["output_count"], 1);
+    assert_eq!(report["would_write"], true);
+
+    // SAFETY: `result` is a live result returned by the library.
+    let rows = unsafe { cirup_run_result_rows_json(result) };
+    // SAFETY: the rows are a nul-terminated string owned by the result.
+    let rows = unsafe { CStr::from_ptr(rows) };
+    let rows: serde_json::Value = serde_json::from_slice(rows.to_bytes()).expect("invalid rows");
+    assert_eq!(rows[0]["name"], "lblYolo");
+    assert_eq!(rows[0]["value"], "You only live once");
+    assert_eq!(rows[0]["span"]["file"], base.to_string_lossy().as_ref());
+    assert_eq!(rows[0]["span"]["line"], 2);
+
+    let mut length = 0;
+    // SAFETY: `result` is a live result returned by the library.
+    let bytes = unsafe { cirup_run_result_output(result, &mut length) };
+    // SAFETY: the output is `length` bytes owned by the result.
+    let bytes = unsafe { slice::from_raw_parts(bytes, length) };
+    assert!(String::from_utf8_lossy(bytes).contains("\"lblYolo\": \"You only live once\""));
+    assert!(!output.exists());
+
+    // SAFETY: `result` is a live result returned by the library.
+    assert_eq!(unsafe { cirup_run_result_write_output(result) }, CIRUP_OK);
+    assert_eq!(std::fs::read(&output).expect("output not written"), bytes);
+
+    // SAFETY: `result` was returned by the library and is released once.
+    unsafe { cirup_run_result_free(result) };
+
+    let operation = c_string("file-frobnicate");
+    // SAFETY: all strings are valid for the duration of the call.
+    let status = unsafe { cirup_run(operation.as_ptr(), input_ptrs.as_ptr(), 1, ptr::null(), &mut result) };
+    assert_eq!(status, CIRUP_ERROR_UNSUPPORTED);
+    assert!(result.is_null());
+    assert_eq!(last_error(), "unsupported operation 'file-frobnicate'");
+}
+
+#[test]
+fn test_run_merge_reports_merge_counts() {
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let file_one = dir.path().join("strings.restext");
+    let file_two = dir.path().join("strings.new.restext");
+    let output = dir.path().join("merged.restext");
+    std::fs::write(&file_one, "lblBoat=I'm on a boat\r\n").expect("failed to write first file");
+    std::fs::write(&file_two, "lblBoat=I'm on a ship\r\nlblYolo=You only live once\r\n")
+        .expect("failed to write second file");
+
+    let operation = c_string("file-merge");
+    let inputs = [
+        c_string(&file_one.to_string_lossy()),
+        c_string(&file_two.to_string_lossy()),
+    ];
+    let input_ptrs = inputs.iter().map(|input| input.as_ptr()).collect::<Vec<_>>();
+    let output_file = c_string(&output.to_string_lossy());
+    let mut result = ptr::null_mut();
+
+    // SAFETY: all strings are valid for the duration of the call.
+    let status = unsafe {
+        cirup_run(
+            operation.as_ptr(),
+            input_ptrs.as_ptr(),
+            input_ptrs.len(),
+            output_file.as_ptr(),
+            &mut result,
+        )
+    };
+    assert_eq!(status, CIRUP_OK);
+
+    // SAFETY: `result` is a live result returned by the library.
+    let report = unsafe { cirup_run_result_report_json(result) };
+    // SAFETY: the report is a nul-terminated string owned by the result.
+    let report = unsafe { CStr::from_ptr(report) };
+    let report: serde_json::Value = serde_json::from_slice(report.to_bytes()).expect("invalid report");
+    assert_eq!(report["operation"], "file-merge");
+    assert_eq!(report["merge"]["strategy"], "prefer-right");
+    assert_eq!(report["merge"]["added"], 1);
+    assert_eq!(report["merge"]["updated"], 1);
+
+    let mut length = 0;
+    // SAFETY: `result` is a live result returned by the library.
+    let bytes = unsafe { cirup_run_result_output(result, &mut length) };
+    // SAFETY: the output is `length` bytes owned by the result.
+    let bytes = unsafe { slice::from_raw_parts(bytes, length) };
+    assert_eq!(
+        String::from_utf8_lossy(bytes),
+        "lblBoat=I'm on a ship\r\nlblYolo=You only live once\r\n"
+    );
+
+    // SAFETY: `result` was returned by the library and is released once.
+    unsafe { cirup_run_result_free(result) };
+
+    // SAFETY: all strings are valid for the duration of the call.
+    let status = unsafe { cirup_run(operation.as_ptr(), input_ptrs.as_ptr(), 1, ptr::null(), &mut result) };
+    assert_eq!(status, CIRUP_ERROR);
+    assert_eq!(last_error(), "file-merge expects 2 inputs, got 1");
+}
+
+#[test]
+fn test_panic_is_reported_as_error() {
+    let operation = c_string("test-panic");
+    let mut result = ptr::dangling_mut::<CirupRunResult>();
+
+    // SAFETY: `operation` is valid for the duration of the call, and there are no input files.
+    let status = unsafe { cirup_run(operation.as_ptr(), ptr::null(), 0, ptr::null(), &mut result) };
+    assert_eq!(status, CIRUP_ERROR);
+    assert!(result.is_null());
+    assert_eq!(last_error(), "internal error: test panic in test-panic");
+}
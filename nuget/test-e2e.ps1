@@ -101,6 +101,78 @@ function Assert-FileExists {
     }
 }
 
+function Assert-ResourcesInterop {
+    param(
+        [Parameter(Mandatory = $true)]
+        [string]$CirupBinary,
+        [Parameter(Mandatory = $true)]
+        [string]$WorkDir
+    )
+
+    # ResourceWriter lives in System.Resources.Writer on .NET, and in mscorlib on .NET Framework
+    try {
+        Add-Type -AssemblyName System.Resources.Writer
+    }
+    catch {
+    }
+
+    $expected = [ordered]@{
+        lblBoat    = "I'm on a boat"
+        lblEmpty   = ""
+        lblUnicode = "Caf$([char]0xE9) $([char]::ConvertFromUtf32(0x65E5)) $([char]::ConvertFromUtf32(0x1F600))"
+        lblYolo    = "You only live once"
+    }
+
+    # a file written by System.Resources.ResourceWriter is read by cirup
+    $dotnetFile = Join-Path $WorkDir "dotnet.resources"
+    $writer = New-Object System.Resources.ResourceWriter -ArgumentList $dotnetFile
+    try {
+        foreach ($key in $expected.Keys) {
+            $writer.AddResource($key, $expected[$key])
+        }
+    }
+    finally {
+        $writer.Dispose()
+    }
+
+    $json = & $CirupBinary --output-format json file-print $dotnetFile
+    if ($LASTEXITCODE -ne 0) {
+        throw "cirup failed to read $dotnetFile with exit code $LASTEXITCODE"
+    }
+    $actual = @{}
+    foreach ($resource in (($json -join "") | ConvertFrom-Json)) {
+        $actual[$resource.name] = $resource.value
+    }
+    foreach ($key in $expected.Keys) {
+        if ($actual[$key] -cne $expected[$key]) {
+            throw "cirup read '$($actual[$key])' for $key from $dotnetFile, expected '$($expected[$key])'"
+        }
+    }
+
+    # a file written by cirup is read by System.Resources.ResourceReader
+    $cirupFile = Join-Path $WorkDir "cirup.resources"
+    & $CirupBinary file-convert $dotnetFile $cirupFile
+    if ($LASTEXITCODE -ne 0) {
+        throw "cirup failed to write $cirupFile with exit code $LASTEXITCODE"
+    }
+    $reader = New-Object System.Resources.ResourceReader -ArgumentList $cirupFile
+    try {
+        $actual = @{}
+        $entries = $reader.GetEnumerator()
+        while ($entries.MoveNext()) {
+            $actual[$entries.Key] = $entries.Value
+        }
+    }
+    finally {
+        $reader.Dispose()
+    }
+    foreach ($key in $expected.Keys) {
+        if ($actual[$key] -cne $expected[$key]) {
+            throw "ResourceReader read '$($actual[$key])' for $key from $cirupFile, expected '$($expected[$key])'"
+        }
+    }
+}
+
 $scriptRoot = $PSScriptRoot
 if (-not $scriptRoot) {
     $scriptRoot = Split-Path -Path $MyInvocation.MyCommand.Path -Parent
@@ -203,7 +275,7 @@ try {
         throw "dotnet build failed with exit code $LASTEXITCODE"
     }
 
-    dotnet msbuild $sampleProject '/t:CirupDiffResources;CirupChangedValues;CirupMergeResources;CirupSubtractResources;CirupConvertResources;CirupSyncResources' `
+    dotnet msbuild $sampleProject '/t:CirupDiffResources;CirupChangedValues;CirupMergeResources;CirupSubtractResources;CirupConvertResources;CirupLintResources;CirupCoverageResources;CirupSyncResources' `
         -p:CirupBuildVersion=$Version
     if ($LASTEXITCODE -ne 0) {
         throw "dotnet msbuild Cirup targets failed with exit code $LASTEXITCODE"
@@ -221,5 +293,6 @@ Assert-FileExists -Path (Join-Path $sampleDir "artifacts\cirup\changed.fr.restex
 Assert-FileExists -Path (Join-Path $sampleDir "artifacts\cirup\merged.resx")
 Assert-FileExists -Path (Join-Path $sampleDir "artifacts\cirup\fr-only.restext")
 Assert-FileExists -Path (Join-Path $sampleDir "artifacts\cirup\Strings.restext")
+Assert-ResourcesInterop -CirupBinary $sourceBinary -WorkDir $workRoot
 
 Write-Host "E2E validation succeeded. Package: $($packagePath.FullName)"
\ No newline at end of file